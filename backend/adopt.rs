@@ -0,0 +1,773 @@
+use axum::{extract::State, Extension, Json};
+use diesel::SqliteConnection;
+use diesel_async::pooled_connection::bb8::Pool;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+use diesel_async::AsyncConnection;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use tokio::fs;
+use tracing::{debug, error, info};
+
+use crate::audit::{self, AuditTarget};
+use crate::auth::CurrentUser;
+use crate::error::AppError;
+use crate::libraries::Library;
+use crate::parse_library::{add_or_update_model, read_readme};
+use crate::types::{
+    AdoptRequest, AdoptionCandidate, AdoptionResult, AuditAction, AuthorGuess, Model3D,
+    ModelPackV0_1,
+};
+use crate::upload::categorize_file;
+use crate::Config;
+
+/// Folder names which usually only group the files of a model, the model itself is the parent
+const GENERIC_FOLDER_NAMES: &[&str] = &[
+    "files", "stl", "stls", "step", "steps", "cad", "models", "parts", "3mf", "obj",
+];
+const LICENSE_FILE_NAMES: &[&str] = &[
+    "LICENSE",
+    "LICENSE.md",
+    "LICENSE.txt",
+    "license",
+    "license.md",
+    "license.txt",
+    "LICENCE",
+    "LICENCE.md",
+    "LICENCE.txt",
+];
+const README_FILE_NAMES: &[&str] = &["README.md", "readme.md", "README.txt", "readme.txt"];
+
+fn is_hidden(pth: &Path) -> bool {
+    pth.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'))
+}
+
+fn is_model_file(pth: &Path) -> bool {
+    matches!(categorize_file(pth), "mesh" | "cad")
+}
+
+fn adoption_root(dir: &Path, library_root: &Path) -> PathBuf {
+    let is_generic = dir
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| GENERIC_FOLDER_NAMES.contains(&name.to_ascii_lowercase().as_str()));
+
+    match dir.parent() {
+        Some(parent) if is_generic && parent != library_root => parent.to_path_buf(),
+        _ => dir.to_path_buf(),
+    }
+}
+
+/// Finds folders containing mesh or CAD files which are not part of a ModelPack yet
+pub async fn find_adoptable_directories(start_path: PathBuf) -> anyhow::Result<Vec<PathBuf>> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    let mut dirs_to_check = vec![start_path.clone()];
+
+    while let Some(current_dir) = dirs_to_check.pop() {
+        let mut dir_entries = match fs::read_dir(&current_dir).await {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        let mut has_modelpack = false;
+        let mut has_model_files = false;
+        let mut subdirs = Vec::new();
+
+        while let Ok(Some(entry)) = dir_entries.next_entry().await {
+            let pth = entry.path();
+            if is_hidden(&pth) {
+                continue;
+            }
+            if pth.is_dir() {
+                subdirs.push(pth);
+            } else if pth.file_name() == Some("modelpack.json".as_ref()) {
+                has_modelpack = true;
+            } else if is_model_file(&pth) {
+                has_model_files = true;
+            }
+        }
+
+        if has_modelpack {
+            continue;
+        }
+
+        if has_model_files && current_dir != start_path {
+            let root = adoption_root(&current_dir, &start_path);
+            // a parent might already contain a ModelPack, e.g. files/ of an incomplete pack
+            if !root.join("modelpack.json").exists() && !candidates.contains(&root) {
+                debug!("Found adoptable folder at: {:?}", root);
+                candidates.push(root);
+            }
+        } else {
+            dirs_to_check.extend(subdirs);
+        }
+    }
+
+    candidates.sort();
+    Ok(candidates)
+}
+
+fn clean_folder_name(folder_name: &str) -> String {
+    let cleaned = folder_name.replace(['_', '+'], " ");
+    let cleaned = cleaned.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    // strip trailing ids of downloads like "Benchy - 763622"
+    match cleaned.rsplit_once(" - ") {
+        Some((title, id)) if id.chars().all(|c| c.is_ascii_digit()) => title.trim().to_string(),
+        _ => cleaned,
+    }
+}
+
+/// Splits a name like "Benchy - by Creative Tools" or "Benchy (by Creative Tools)" into title and
+/// author, the flag is false for a plain " by " which is just as well part of titles like "Stand by Me"
+fn author_from_folder_name(name: &str) -> Option<(String, String, bool)> {
+    let split = |title: &str, author: &str, marked: bool| {
+        let (title, author) = (title.trim(), author.trim());
+        (!title.is_empty() && !author.is_empty())
+            .then(|| (title.to_string(), author.to_string(), marked))
+    };
+    if let Some((title, author)) = name
+        .strip_suffix(')')
+        .and_then(|name| name.rsplit_once("(by "))
+    {
+        return split(title, author, true);
+    }
+    if let Some((title, author)) = name.rsplit_once(" - by ") {
+        return split(title, author, true);
+    }
+    name.rsplit_once(" by ")
+        .and_then(|(title, author)| split(title, author, false))
+}
+
+fn folder_name(dir: &Path) -> String {
+    dir.file_name()
+        .map(|name| clean_folder_name(&name.to_string_lossy()))
+        .unwrap_or_default()
+}
+
+fn value_after_label<'a>(line: &'a str, labels: &[&str]) -> Option<&'a str> {
+    let stripped = line.trim().trim_start_matches(['-', '*', '#', '>', ' ']);
+    let lowercase = stripped.to_lowercase();
+    for label in labels {
+        if lowercase.starts_with(label) {
+            let value = stripped[label.len()..]
+                .trim_start_matches([':', ' '])
+                .trim()
+                .trim_matches('*')
+                .trim();
+            if !value.is_empty() {
+                return Some(value);
+            }
+        }
+    }
+    None
+}
+
+fn first_url(text: &str) -> Option<String> {
+    text.split(|c: char| c.is_whitespace() || ['(', ')', '<', '>', '[', ']'].contains(&c))
+        .find(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .map(|url| url.trim_end_matches(['.', ',', ';']).to_string())
+}
+
+fn license_from_text(text: &str) -> Option<String> {
+    let lowercase = text.to_lowercase();
+    let version = ["4.0", "3.0", "2.5", "2.0", "1.0"]
+        .into_iter()
+        .find(|version| lowercase.contains(version));
+
+    let creative_commons = [
+        ("attribution-noncommercial-sharealike", "CC BY-NC-SA"),
+        ("attribution-noncommercial-noderivatives", "CC BY-NC-ND"),
+        ("attribution-noncommercial-noderivs", "CC BY-NC-ND"),
+        ("attribution-noncommercial", "CC BY-NC"),
+        ("attribution-sharealike", "CC BY-SA"),
+        ("attribution-noderivatives", "CC BY-ND"),
+        ("attribution-noderivs", "CC BY-ND"),
+        ("creative commons attribution", "CC BY"),
+    ];
+
+    if lowercase.contains("cc0") || lowercase.contains("public domain dedication") {
+        return Some("CC0 1.0".to_string());
+    }
+
+    for (needle, license) in creative_commons {
+        if lowercase.contains(needle) {
+            return Some(match version {
+                Some(version) => format!("{} {}", license, version),
+                None => license.to_string(),
+            });
+        }
+    }
+
+    let others = [
+        ("gnu lesser general public license", "LGPL"),
+        ("gnu general public license", "GPL"),
+        ("mit license", "MIT"),
+        ("apache license", "Apache-2.0"),
+        ("cern open hardware licence", "CERN-OHL"),
+        ("bsd", "BSD"),
+    ];
+
+    for (needle, license) in others {
+        if lowercase.contains(needle) {
+            return Some(match (license, version) {
+                ("GPL" | "LGPL", Some(version)) => format!("{}-{}", license, version),
+                _ => license.to_string(),
+            });
+        }
+    }
+
+    None
+}
+
+async fn read_license(dir: &Path) -> Option<String> {
+    for name in LICENSE_FILE_NAMES {
+        if let Ok(content) = fs::read_to_string(dir.join(name)).await {
+            return license_from_text(&content).or_else(|| {
+                content
+                    .lines()
+                    .map(|line| line.trim())
+                    .find(|line| !line.is_empty())
+                    .map(|line| line.chars().take(256).collect())
+            });
+        }
+    }
+    None
+}
+
+/// Suggests the modelpack.json content for a folder from its name, README and LICENSE,
+/// a plain " by " in the name only counts if the README names the same author
+pub async fn suggest_model_pack(dir: &Path) -> ModelPackV0_1 {
+    let name = folder_name(dir);
+    let name_author = author_from_folder_name(&name);
+    let (mut title, mut author) = match &name_author {
+        Some((title, author, true)) => (title.clone(), Some(author.clone())),
+        _ => (name.clone(), None),
+    };
+    let mut license = read_license(dir).await;
+    let mut origin = None;
+
+    if let Ok(readme) = read_readme(dir).await {
+        for line in readme.lines() {
+            if let Some(heading) = line.strip_prefix("# ") {
+                if title.is_empty() {
+                    title = heading.trim().to_string();
+                }
+            } else if let Some(value) =
+                value_after_label(line, &["author", "designer", "created by", "designed by"])
+            {
+                author.get_or_insert_with(|| value.to_string());
+            } else if let Some(value) = value_after_label(line, &["license", "licence"]) {
+                license
+                    .get_or_insert_with(|| license_from_text(value).unwrap_or(value.to_string()));
+            } else if let Some(value) = value_after_label(line, &["source", "origin", "url"]) {
+                if origin.is_none() {
+                    origin = first_url(value);
+                }
+            }
+        }
+        if origin.is_none() {
+            origin = first_url(&readme);
+        }
+    }
+
+    if let (Some((short_title, suffix, false)), Some(author)) = (&name_author, &author) {
+        if title == name && suffix.eq_ignore_ascii_case(author) {
+            title = short_title.clone();
+        }
+    }
+
+    ModelPackV0_1 {
+        version: "0.1".to_string(),
+        title,
+        author: author.unwrap_or_default(),
+        origin: origin.unwrap_or_default(),
+        license: license.unwrap_or_default(),
+    }
+}
+
+fn relative_files(dir: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !is_hidden(entry.path()))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| pathdiff::diff_paths(entry.path(), dir))
+        .collect()
+}
+
+/// The author a plain " by " in the folder name might stand for, if nothing else names one
+fn guess_author(dir: &Path, suggestion: &ModelPackV0_1) -> Option<AuthorGuess> {
+    if !suggestion.author.is_empty() {
+        return None;
+    }
+    match author_from_folder_name(&folder_name(dir)) {
+        Some((title, author, false)) => Some(AuthorGuess { title, author }),
+        _ => None,
+    }
+}
+
+pub async fn get_adoption_candidate(library: &Library, dir: &Path) -> AdoptionCandidate {
+    let files = relative_files(dir);
+    let folder_path = pathdiff::diff_paths(dir, &library.path).unwrap_or_default();
+    let suggestion = suggest_model_pack(dir).await;
+
+    AdoptionCandidate {
+        library: library.name.clone(),
+        folder_path: folder_path.to_string_lossy().into_owned(),
+        author_guess: guess_author(dir, &suggestion),
+        suggestion,
+        model_files: files
+            .iter()
+            .filter(|file| is_model_file(file))
+            .map(|file| file.to_string_lossy().into_owned())
+            .collect(),
+        image_files: files
+            .iter()
+            .filter(|file| categorize_file(file) == "image")
+            .map(|file| file.to_string_lossy().into_owned())
+            .collect(),
+        has_readme: README_FILE_NAMES
+            .iter()
+            .any(|name| dir.join(name).is_file()),
+        has_license: LICENSE_FILE_NAMES
+            .iter()
+            .any(|name| dir.join(name).is_file()),
+    }
+}
+
+/// Candidates of every library which is not read-only, adopting writes modelpack.json
+pub async fn list_adoption_candidates(config: &Config) -> anyhow::Result<Vec<AdoptionCandidate>> {
+    let mut candidates = Vec::new();
+    for library in config
+        .libraries()
+        .iter()
+        .filter(|library| !library.read_only)
+    {
+        for dir in find_adoptable_directories(library.path.clone()).await? {
+            candidates.push(get_adoption_candidate(library, &dir).await);
+        }
+    }
+    Ok(candidates)
+}
+
+/// Resolves a folder relative to the library and makes sure it does not escape it
+fn resolve_library_folder(library: &Library, folder_path: &str) -> anyhow::Result<PathBuf> {
+    let relative = Path::new(folder_path);
+    if relative.as_os_str().is_empty()
+        || relative
+            .components()
+            .any(|component| !matches!(component, Component::Normal(_)))
+    {
+        anyhow::bail!("Invalid folder path {}", folder_path);
+    }
+
+    let dir = library.path.join(relative);
+    if !dir.is_dir() {
+        anyhow::bail!("Folder {} does not exist", folder_path);
+    }
+    if dir.join("modelpack.json").exists() {
+        anyhow::bail!("Folder {} already is a ModelPack", folder_path);
+    }
+    Ok(dir)
+}
+
+/// Target of a file inside the standard ModelPack layout, None if it stays where it is
+fn layout_target(relative_file: &Path) -> Option<PathBuf> {
+    let file_name = relative_file.file_name()?;
+    let mut components = relative_file.components();
+    let first = components.next()?.as_os_str().to_string_lossy().to_string();
+    let is_top_level = relative_file.parent() == Some(Path::new(""));
+
+    if is_top_level
+        && (README_FILE_NAMES.iter().any(|name| file_name == *name)
+            || LICENSE_FILE_NAMES.iter().any(|name| file_name == *name))
+    {
+        return None;
+    }
+
+    let target_dir = match categorize_file(relative_file) {
+        "mesh" | "cad" => "files",
+        // images/ is not scanned recursively, so images end up flat
+        "image" if relative_file.parent() == Some(Path::new("images")) => return None,
+        "image" => return Some(Path::new("images").join(file_name)),
+        _ => "assets",
+    };
+
+    if first == target_dir {
+        return None;
+    }
+
+    Some(Path::new(target_dir).join(relative_file))
+}
+
+async fn unique_path(path: PathBuf) -> PathBuf {
+    if !fs::try_exists(&path).await.unwrap_or(false) {
+        return path;
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut counter = 1;
+    loop {
+        let candidate = path.with_file_name(format!("{}_{}{}", stem, counter, extension));
+        if !fs::try_exists(&candidate).await.unwrap_or(false) {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+/// Directories below `dir`, the deepest first
+fn sub_dirs(dir: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(dir)
+        .contents_first(true)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_dir())
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+fn remove_empty_dirs(dir: &Path) {
+    for sub_dir in sub_dirs(dir) {
+        if sub_dir.parent() == Some(dir)
+            && ["files", "images"]
+                .iter()
+                .any(|name| sub_dir.ends_with(name))
+        {
+            continue;
+        }
+        // only succeeds for empty directories
+        std::fs::remove_dir(&sub_dir).ok();
+    }
+}
+
+/// Writes modelpack.json, moves the files into the standard layout and indexes the model
+pub async fn adopt_directory<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    library: &Library,
+    folder_path: &str,
+    pack: &ModelPackV0_1,
+) -> anyhow::Result<Model3D>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    if library.read_only {
+        anyhow::bail!("The library {} is read-only", library.name);
+    }
+    let dir = resolve_library_folder(library, folder_path)?;
+    if pack.title.trim().is_empty() {
+        anyhow::bail!("A title is required to adopt {}", folder_path);
+    }
+
+    let files = relative_files(&dir);
+    if !files.iter().any(|file| is_model_file(file)) {
+        anyhow::bail!(
+            "Folder {} does not contain any mesh or CAD files",
+            folder_path
+        );
+    }
+
+    // everything is put back if a step fails, so a folder is either adopted or untouched
+    let existing_dirs: HashSet<PathBuf> = sub_dirs(&dir).into_iter().collect();
+    let mut moves = Vec::new();
+    let arranged = arrange_folder(&dir, files, pack, &mut moves).await;
+    let indexed = match arranged {
+        Ok(()) => {
+            let dir = &dir;
+            connection
+                .transaction::<_, anyhow::Error, _>(|conn| {
+                    async move { add_or_update_model(config, conn, dir).await }.scope_boxed()
+                })
+                .await
+        }
+        Err(e) => Err(e),
+    };
+    let model = match indexed {
+        Ok(model) => model,
+        Err(e) => {
+            undo_arrange_folder(&dir, &moves, &existing_dirs).await;
+            return Err(e);
+        }
+    };
+    remove_empty_dirs(&dir);
+    info!("Adopted {}", dir.display());
+
+    // previews are generated outside of the transaction, a failed scan is redone by the next refresh
+    if let Err(e) = model.scan(config, connection).await {
+        error!("Scanning {} failed: {:?}", dir.display(), e);
+    }
+
+    Ok(model)
+}
+
+/// Moves the files into the standard layout and writes modelpack.json, `moves` records every move as it happens
+async fn arrange_folder(
+    dir: &Path,
+    files: Vec<PathBuf>,
+    pack: &ModelPackV0_1,
+    moves: &mut Vec<(PathBuf, PathBuf)>,
+) -> anyhow::Result<()> {
+    fs::create_dir_all(dir.join("files")).await?;
+    fs::create_dir_all(dir.join("images")).await?;
+
+    for file in files {
+        if let Some(target) = layout_target(&file) {
+            let source = dir.join(&file);
+            let target = unique_path(dir.join(target)).await;
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            debug!("Moving {} to {}", file.display(), target.display());
+            fs::rename(&source, &target).await?;
+            moves.push((source, target));
+        }
+    }
+
+    let json = serde_json::to_string_pretty(pack)?;
+    fs::write(dir.join("modelpack.json"), json).await?;
+    Ok(())
+}
+
+/// Moves the files back and removes modelpack.json and the directories `arrange_folder` created
+async fn undo_arrange_folder(
+    dir: &Path,
+    moves: &[(PathBuf, PathBuf)],
+    existing_dirs: &HashSet<PathBuf>,
+) {
+    fs::remove_file(dir.join("modelpack.json")).await.ok();
+    for (source, target) in moves.iter().rev() {
+        if let Err(e) = fs::rename(target, source).await {
+            error!(
+                "Failed to move {} back to {}: {}",
+                target.display(),
+                source.display(),
+                e
+            );
+        }
+    }
+    for created in sub_dirs(dir)
+        .into_iter()
+        .filter(|sub_dir| !existing_dirs.contains(sub_dir))
+    {
+        // only succeeds for empty directories
+        std::fs::remove_dir(&created).ok();
+    }
+    debug!("Put back the files of {}", dir.display());
+}
+
+pub async fn handle_list_adoption_candidates(
+    State(state): State<crate::AppState>,
+) -> Result<Json<Vec<AdoptionCandidate>>, AppError> {
+    match list_adoption_candidates(&state.config).await {
+        Ok(candidates) => Ok(Json(candidates)),
+        Err(e) => Err(AppError::internal(format!(
+            "Failed to list adoption candidates: {}",
+            e
+        ))),
+    }
+}
+
+pub async fn handle_adopt(
+    State(state): State<crate::AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Json(request): Json<AdoptRequest>,
+) -> Result<Json<Vec<AdoptionResult>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let main_library = &state.config.main_library().name;
+    let mut folders: Vec<(String, String, Option<ModelPackV0_1>)> = request
+        .folders
+        .into_iter()
+        .map(|folder| {
+            let library = folder.library.unwrap_or_else(|| main_library.clone());
+            (library, folder.folder_path, folder.pack)
+        })
+        .collect();
+
+    if request.all {
+        let candidates = list_adoption_candidates(&state.config).await?;
+        for candidate in candidates {
+            let listed = folders.iter().any(|(library, folder_path, _)| {
+                *library == candidate.library && *folder_path == candidate.folder_path
+            });
+            if !listed {
+                folders.push((candidate.library, candidate.folder_path, None));
+            }
+        }
+    }
+
+    let mut results = Vec::new();
+    for (library_name, folder_path, pack) in folders {
+        let Some(library) = state.config.library_named(&library_name) else {
+            results.push(AdoptionResult {
+                message: format!("There is no library {}", library_name),
+                library: library_name,
+                folder_path,
+                success: false,
+                slug: None,
+            });
+            continue;
+        };
+        let pack = match pack {
+            Some(pack) => pack,
+            None => suggest_model_pack(&library.path.join(&folder_path)).await,
+        };
+
+        let result =
+            match adopt_directory(&state.config, &mut connection, library, &folder_path, &pack)
+                .await
+            {
+                Ok(model) => {
+                    audit::record(
+                        &mut connection,
+                        &user,
+                        AuditAction::Adopt,
+                        AuditTarget::model(&model),
+                        format!("Adopted the folder {}", folder_path),
+                    )
+                    .await;
+                    AdoptionResult {
+                        library: library_name,
+                        folder_path,
+                        success: true,
+                        slug: Some(model.name),
+                        message: "Adopted".to_string(),
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to adopt {}: {}", folder_path, e);
+                    AdoptionResult {
+                        library: library_name,
+                        folder_path,
+                        success: false,
+                        slug: None,
+                        message: e.to_string(),
+                    }
+                }
+            };
+        results.push(result);
+    }
+
+    Ok(Json(results))
+}
+
+fn confirm(question: &str, options: &str) -> anyhow::Result<char> {
+    print!("{} [{}] ", question, options);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer
+        .trim()
+        .to_ascii_lowercase()
+        .chars()
+        .next()
+        .unwrap_or('n'))
+}
+
+/// `meshvault adopt [--yes] [--dry-run] [FOLDER...]`
+pub async fn run_cli(
+    args: &[String],
+    config: &Config,
+    pool: Pool<SyncConnectionWrapper<SqliteConnection>>,
+) -> anyhow::Result<()> {
+    let mut adopt_all = args.iter().any(|arg| arg == "--yes" || arg == "-y");
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    let selected: Vec<&String> = args.iter().filter(|arg| !arg.starts_with('-')).collect();
+
+    let mut connection = pool.get().await?;
+
+    let candidates = list_adoption_candidates(config).await?;
+    let candidates: Vec<AdoptionCandidate> = candidates
+        .into_iter()
+        .filter(|candidate| {
+            selected.is_empty()
+                || selected
+                    .iter()
+                    .any(|folder| **folder == candidate.folder_path)
+        })
+        .collect();
+
+    if candidates.is_empty() {
+        println!("No folders to adopt found");
+        return Ok(());
+    }
+
+    for candidate in candidates {
+        let mut pack = candidate.suggestion.clone();
+        println!(
+            "{}\n  library: {}\n  title: {}\n  author: {}\n  license: {}\n  origin: {}\n  files: {}, images: {}",
+            candidate.folder_path,
+            candidate.library,
+            pack.title,
+            pack.author,
+            pack.license,
+            pack.origin,
+            candidate.model_files.len(),
+            candidate.image_files.len()
+        );
+        if let Some(guess) = &candidate.author_guess {
+            println!(
+                "  the folder name might mean the title {:?} by {}",
+                guess.title, guess.author
+            );
+        }
+
+        if dry_run {
+            continue;
+        }
+
+        if !adopt_all {
+            if let Some(guess) = &candidate.author_guess {
+                let question = format!("Take {} from the folder name as the author?", guess.author);
+                if confirm(&question, "y/N")? == 'y' {
+                    pack.title = guess.title.clone();
+                    pack.author = guess.author.clone();
+                }
+            }
+            match confirm(
+                &format!(
+                    "Adopt {} as {:?} by {:?}?",
+                    candidate.folder_path, pack.title, pack.author
+                ),
+                "y/N/a/q",
+            )? {
+                'y' => {}
+                'a' => adopt_all = true,
+                'q' => break,
+                _ => continue,
+            }
+        }
+
+        let Some(library) = config.library_named(&candidate.library) else {
+            continue;
+        };
+        match adopt_directory(
+            config,
+            &mut connection,
+            library,
+            &candidate.folder_path,
+            &pack,
+        )
+        .await
+        {
+            Ok(model) => println!("  adopted as {}", model.name),
+            Err(e) => println!("  failed: {}", e),
+        }
+    }
+
+    Ok(())
+}
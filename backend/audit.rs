@@ -0,0 +1,150 @@
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use tracing::error;
+
+use crate::auth::CurrentUser;
+use crate::error::AppError;
+use crate::schema::audit_log;
+use crate::types::{
+    AuditAction, AuditEntry, AuditEntryResponse, AuditLogResponse, AuditParams, AuditTargetType,
+    Collection, Model3D, NewAuditEntry,
+};
+use crate::AppState;
+
+const MAX_PAGE_SIZE: i64 = 500;
+
+/// What an action was applied to
+pub struct AuditTarget {
+    pub kind: AuditTargetType,
+    pub id: Option<i32>,
+    pub name: Option<String>,
+}
+
+impl AuditTarget {
+    pub fn new(kind: AuditTargetType, id: i32, name: impl Into<String>) -> Self {
+        Self {
+            kind,
+            id: Some(id),
+            name: Some(name.into()),
+        }
+    }
+
+    pub fn model(model: &Model3D) -> Self {
+        Self::new(AuditTargetType::Model, model.id, &model.name)
+    }
+
+    pub fn collection(collection: &Collection) -> Self {
+        Self::new(AuditTargetType::Collection, collection.id, &collection.name)
+    }
+
+    /// Everything of a kind, e.g. the whole library for refreshes
+    pub fn all(kind: AuditTargetType) -> Self {
+        Self {
+            kind,
+            id: None,
+            name: None,
+        }
+    }
+}
+
+/// Adds an entry to the audit log, a failure is logged but does not fail the action itself
+pub async fn record<Conn>(
+    connection: &mut Conn,
+    user: &CurrentUser,
+    action: AuditAction,
+    target: AuditTarget,
+    summary: impl Into<String>,
+) where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let entry = NewAuditEntry {
+        user_id: user.id,
+        username: user.username.clone(),
+        action: action.as_str().to_string(),
+        target_type: target.kind.as_str().to_string(),
+        target_id: target.id,
+        target_name: target.name,
+        summary: summary.into(),
+        ip_address: user.ip.clone(),
+    };
+    let inserted = diesel::insert_into(audit_log::table)
+        .values(&entry)
+        .execute(connection)
+        .await;
+    if let Err(e) = inserted {
+        error!(
+            "Failed to record {} of {} by {}: {:?}",
+            entry.action, entry.target_type, entry.username, e
+        );
+    }
+}
+
+/// Audit entries matching the filters, admins only
+pub async fn list_audit(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Query(params): Query<AuditParams>,
+) -> Result<Json<AuditLogResponse>, AppError> {
+    user.require_admin()?;
+    let mut connection = state.pool.get().await?;
+
+    let filtered = || {
+        let mut query = audit_log::table.into_boxed();
+        if let Some(user_id) = params.user_id {
+            query = query.filter(audit_log::user_id.eq(user_id));
+        }
+        if let Some(action) = params.action {
+            query = query.filter(audit_log::action.eq(action.as_str()));
+        }
+        if let Some(target_type) = params.target_type {
+            query = query.filter(audit_log::target_type.eq(target_type.as_str()));
+        }
+        if let Some(target_id) = params.target_id {
+            query = query.filter(audit_log::target_id.eq(target_id));
+        }
+        if let Some(q) = params.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+            let pattern = format!("%{}%", q);
+            query = query.filter(
+                audit_log::target_name
+                    .like(pattern.clone())
+                    .or(audit_log::summary.like(pattern)),
+            );
+        }
+        if let Some(since) = params.since {
+            query = query.filter(audit_log::created_at.ge(since));
+        }
+        if let Some(until) = params.until {
+            query = query.filter(audit_log::created_at.le(until));
+        }
+        query
+    };
+
+    let total: i64 = filtered()
+        .count()
+        .get_result(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params.page_size.unwrap_or(100).clamp(1, MAX_PAGE_SIZE);
+    let entries = filtered()
+        .order((audit_log::created_at.desc(), audit_log::id.desc()))
+        .limit(page_size)
+        .offset((page - 1) * page_size)
+        .load::<AuditEntry>(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+
+    Ok(Json(AuditLogResponse {
+        entries: entries
+            .iter()
+            .map(AuditEntryResponse::try_from)
+            .collect::<Result<_, _>>()
+            .map_err(AppError::internal)?,
+        total: total as i32,
+    }))
+}
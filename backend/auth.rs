@@ -0,0 +1,823 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::{
+    extract::{ConnectInfo, Path, Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use diesel_async::pooled_connection::bb8::Pool;
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use sha2::{Digest, Sha256};
+use std::io::BufRead;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use tracing::{debug, error, info, warn};
+
+use crate::audit::{self, AuditTarget};
+use crate::error::AppError;
+use crate::proxy_auth::{self, ProxyIdentity};
+use crate::schema::{api_tokens, collections, favourites, sessions, users};
+use crate::tokens;
+use crate::types::{
+    AuditAction, AuditTargetType, ChangePasswordRequest, CreateUserRequest, LoginRequest,
+    NewSession, NewUser, Role, SetRoleRequest, TokenScope, User, UserResponse,
+};
+use crate::{AppState, Config};
+
+pub const SESSION_COOKIE: &str = "meshvault_session";
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_USERNAME_LENGTH: usize = 64;
+
+/// The user a request is made by, `require_user` puts it into the request extensions
+#[derive(Clone, Debug)]
+pub struct CurrentUser {
+    pub id: i32,
+    pub username: String,
+    pub role: Role,
+    /// Address of the client, recorded in the audit log
+    pub ip: Option<String>,
+}
+
+impl CurrentUser {
+    /// Id of the local user, it is not stored in the users table
+    pub const LOCAL_ID: i32 = 0;
+    /// Id of visitors without a login on a read-only instance
+    pub const PUBLIC_ID: i32 = -1;
+
+    /// Stands in for every request while authentication is disabled
+    fn local() -> Self {
+        Self {
+            id: Self::LOCAL_ID,
+            username: "local".to_string(),
+            role: Role::Admin,
+            ip: None,
+        }
+    }
+
+    fn public() -> Self {
+        Self {
+            id: Self::PUBLIC_ID,
+            username: "public".to_string(),
+            role: Role::Viewer,
+            ip: None,
+        }
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.role == Role::Admin
+    }
+
+    pub fn require_admin(&self) -> Result<(), AppError> {
+        if self.is_admin() {
+            Ok(())
+        } else {
+            Err(AppError::forbidden("Admin rights required"))
+        }
+    }
+}
+
+impl From<&User> for CurrentUser {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username.clone(),
+            role: user.role(),
+            ip: None,
+        }
+    }
+}
+
+/// What a route requires, declared next to every route of the `ApiRouter`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Access {
+    /// Reachable without a login
+    Public,
+    /// The role of the user and the scope of an API token
+    Requires(Role, TokenScope),
+}
+
+impl Access {
+    /// Browsing, every user and token may do it
+    pub const READ: Access = Access::Requires(Role::Viewer, TokenScope::Read);
+    /// Favourites, which only concern the user
+    pub const FAVOURITE: Access = Access::Requires(Role::Viewer, TokenScope::WriteMetadata);
+    /// The own password and API tokens
+    pub const ACCOUNT: Access = Access::Requires(Role::Viewer, TokenScope::Admin);
+    /// Prints, collections and share links
+    pub const CONTRIBUTE: Access = Access::Requires(Role::Contributor, TokenScope::WriteMetadata);
+    pub const UPLOAD: Access = Access::Requires(Role::Contributor, TokenScope::Upload);
+    /// Changing and deleting models
+    pub const EDIT: Access = Access::Requires(Role::Editor, TokenScope::WriteMetadata);
+    /// Replacing the files of models and refreshing them
+    pub const EDIT_FILES: Access = Access::Requires(Role::Editor, TokenScope::Upload);
+    /// Deleting from the trash for good
+    pub const PURGE: Access = Access::Requires(Role::Admin, TokenScope::WriteMetadata);
+    /// Managing users and reading the audit log
+    pub const ADMIN: Access = Access::Requires(Role::Admin, TokenScope::Admin);
+
+    /// Whether a read-only instance refuses the request
+    fn is_mutating(&self, method: &Method) -> bool {
+        let reads = method == Method::GET || method == Method::HEAD;
+        match self {
+            Access::Public => false,
+            Access::Requires(role, _) => *role > Role::Viewer || !reads,
+        }
+    }
+}
+
+/// How a request was authenticated, `require_user` puts it into the request extensions next to the user
+#[derive(Clone, Debug)]
+pub struct Grant {
+    /// Scopes of the API token, sessions and proxy logins are only limited by the role
+    scopes: Option<Vec<TokenScope>>,
+    read_only: bool,
+}
+
+/// Whether the user may do what `access` requires
+pub fn check_access(
+    user: &CurrentUser,
+    grant: &Grant,
+    access: Access,
+    method: &Method,
+) -> Result<(), AppError> {
+    let Access::Requires(role, scope) = access else {
+        return Ok(());
+    };
+    if let Some(scopes) = &grant.scopes {
+        if !tokens::has_scope(scopes, scope) {
+            return Err(AppError::forbidden(format!(
+                "The API token lacks the {} scope",
+                scope.as_str()
+            )));
+        }
+    }
+    if grant.read_only && access.is_mutating(method) {
+        return Err(AppError::forbidden("This instance is read-only"));
+    }
+    if user.role < role {
+        return Err(AppError::forbidden(format!(
+            "This requires the {} role",
+            role.as_str()
+        )));
+    }
+    Ok(())
+}
+
+/// Argon2 in PHC format, hashing is slow on purpose and runs off the async workers
+pub async fn hash_password(password: &str) -> anyhow::Result<String> {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| anyhow::anyhow!("Failed to hash password: {}", e))
+    })
+    .await?
+}
+
+pub async fn verify_password(password: &str, password_hash: &str) -> bool {
+    let password = password.to_string();
+    let password_hash = password_hash.to_string();
+    tokio::task::spawn_blocking(move || {
+        PasswordHash::new(&password_hash).is_ok_and(|hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok()
+        })
+    })
+    .await
+    .unwrap_or(false)
+}
+
+/// Random token for a session cookie
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Tokens are only stored hashed, a leaked database does not contain usable sessions
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+pub fn validate_new_user(username: &str, password: &str) -> Result<(), String> {
+    validate_username(username)?;
+    validate_password(password)
+}
+
+pub fn validate_username(username: &str) -> Result<(), String> {
+    if username.is_empty() || username.chars().count() > MAX_USERNAME_LENGTH {
+        return Err(format!(
+            "username must be between 1 and {} characters",
+            MAX_USERNAME_LENGTH
+        ));
+    }
+    if username
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err("username must not contain spaces".to_string());
+    }
+    Ok(())
+}
+
+fn validate_password(password: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(format!(
+            "password must be at least {} characters",
+            MIN_PASSWORD_LENGTH
+        ));
+    }
+    Ok(())
+}
+
+pub async fn find_user<Conn>(connection: &mut Conn, username: &str) -> anyhow::Result<Option<User>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    Ok(users::table
+        .filter(users::username.eq(username))
+        .first::<User>(connection)
+        .await
+        .optional()?)
+}
+
+pub async fn create_user<Conn>(
+    connection: &mut Conn,
+    username: &str,
+    password: &str,
+    role: Role,
+) -> anyhow::Result<User>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    validate_new_user(username, password).map_err(anyhow::Error::msg)?;
+    if find_user(connection, username).await?.is_some() {
+        anyhow::bail!("The user {} already exists", username);
+    }
+    let user_count: i64 = users::table.count().get_result(connection).await?;
+
+    let user = diesel::insert_into(users::table)
+        .values(&NewUser {
+            username: username.to_string(),
+            password_hash: hash_password(password).await?,
+            role: role.as_str().to_string(),
+            email: None,
+        })
+        .returning(User::as_returning())
+        .get_result(connection)
+        .await?;
+    info!("Created {} {}", role.as_str(), user.username);
+
+    if user_count == 0 {
+        take_over_local_data(connection, user.id).await?;
+    }
+    Ok(user)
+}
+
+/// The first account takes over what the local user made while logins were disabled and the collections from before accounts
+pub async fn take_over_local_data<Conn>(connection: &mut Conn, user_id: i32) -> anyhow::Result<()>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    diesel::update(favourites::table.filter(favourites::user_id.eq(CurrentUser::LOCAL_ID)))
+        .set(favourites::user_id.eq(user_id))
+        .execute(connection)
+        .await?;
+    diesel::update(
+        collections::table.filter(
+            collections::owner_id
+                .eq(CurrentUser::LOCAL_ID)
+                .or(collections::owner_id.is_null()),
+        ),
+    )
+    .set(collections::owner_id.eq(user_id))
+    .execute(connection)
+    .await?;
+    Ok(())
+}
+
+/// Creates the admin from ADMIN_USERNAME and ADMIN_PASSWORD unless the user already exists
+pub async fn ensure_initial_admin<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+) -> anyhow::Result<()>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    if let (Some(username), Some(password)) = (&config.admin_username, &config.admin_password) {
+        if find_user(connection, username).await?.is_none() {
+            create_user(connection, username, password, Role::Admin).await?;
+        }
+    }
+
+    let user_count: i64 = users::table.count().get_result(connection).await?;
+    if config.auth_enabled && user_count == 0 {
+        warn!(
+            "No user exists yet, set ADMIN_USERNAME and ADMIN_PASSWORD or run `meshvault create-user`"
+        );
+    }
+    Ok(())
+}
+
+async fn create_session<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    user_id: i32,
+) -> anyhow::Result<String>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let now = Utc::now().naive_utc();
+    diesel::delete(sessions::table.filter(sessions::expires_at.le(now)))
+        .execute(connection)
+        .await?;
+
+    let token = generate_token();
+    diesel::insert_into(sessions::table)
+        .values(&NewSession {
+            user_id,
+            token_hash: hash_token(&token),
+            expires_at: now + Duration::days(config.session_lifetime_days as i64),
+        })
+        .execute(connection)
+        .await?;
+    Ok(token)
+}
+
+async fn session_user<Conn>(connection: &mut Conn, token: &str) -> anyhow::Result<Option<User>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    Ok(sessions::table
+        .inner_join(users::table)
+        .filter(sessions::token_hash.eq(hash_token(token)))
+        .filter(sessions::expires_at.gt(Utc::now().naive_utc()))
+        .select(User::as_select())
+        .first::<User>(connection)
+        .await
+        .optional()?)
+}
+
+fn session_cookie(config: &Config, token: String) -> Cookie<'static> {
+    Cookie::build((SESSION_COOKIE, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .secure(config.session_cookie_secure)
+        .max_age(time::Duration::days(config.session_lifetime_days as i64))
+        .build()
+}
+
+/// Bearer token of the Authorization header, used by scripts instead of a session
+fn bearer_token(request: &Request) -> Option<String> {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(|token| token.trim().to_string())
+}
+
+fn peer_ip(request: &Request) -> Option<IpAddr> {
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(address)| address.ip())
+}
+
+/// The user of an API token with its scopes, a trusted proxy or a session, the response is the error to send instead
+async fn authenticate(
+    state: &AppState,
+    jar: &CookieJar,
+    bearer: Option<String>,
+    proxy_identity: Option<ProxyIdentity>,
+) -> Result<(CurrentUser, Option<Vec<TokenScope>>), AppError> {
+    if !state.config.auth_enabled {
+        return Ok((CurrentUser::local(), None));
+    }
+
+    if let Some(token) = bearer {
+        let authenticated = {
+            let mut connection = state.pool.get().await?;
+            tokens::authenticate(&mut connection, &token).await
+        };
+        let (user, scopes) = match authenticated {
+            Ok(Some(found)) => found,
+            Ok(None) => return Err(AppError::new(StatusCode::UNAUTHORIZED, "Invalid API token")),
+            Err(e) => {
+                return Err(AppError::internal(format!(
+                    "Failed to look up API token: {:?}",
+                    e
+                )))
+            }
+        };
+
+        let mut current_user = CurrentUser::from(&user);
+        if !scopes.contains(&TokenScope::Admin) {
+            current_user.role = current_user.role.min(Role::Editor);
+        }
+        return Ok((current_user, Some(scopes)));
+    }
+
+    if let Some(identity) = proxy_identity {
+        let provisioned = {
+            let mut connection = state.pool.get().await?;
+            proxy_auth::provision(&state.config, &mut connection, &identity).await
+        };
+        return match provisioned {
+            Ok(user) => Ok((CurrentUser::from(&user), None)),
+            Err(e) => {
+                error!(
+                    "Failed to sign in {} from the proxy: {:?}",
+                    identity.username, e
+                );
+                Err(AppError::forbidden("The proxy user can not be signed in"))
+            }
+        };
+    }
+
+    let user = match jar.get(SESSION_COOKIE) {
+        Some(cookie) => {
+            let mut connection = state.pool.get().await?;
+            session_user(&mut connection, cookie.value()).await
+        }
+        None => Ok(None),
+    };
+    match user {
+        Ok(Some(user)) => Ok((CurrentUser::from(&user), None)),
+        // a read-only instance can be browsed without a login
+        Ok(None) if state.config.read_only => Ok((CurrentUser::public(), None)),
+        Ok(None) => Err(AppError::new(StatusCode::UNAUTHORIZED, "Login required")),
+        Err(e) => Err(AppError::internal(format!(
+            "Failed to look up session: {:?}",
+            e
+        ))),
+    }
+}
+
+/// Rejects requests without a valid session or API token, protects the API and the static model files,
+/// `authorize` checks what the user may do afterwards
+pub async fn require_user(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let peer = peer_ip(&request);
+    let proxy_identity = proxy_auth::identity(&state.config, request.headers(), peer);
+    let client_ip = proxy_auth::client_ip(&state.config, request.headers(), peer);
+    let bearer = bearer_token(&request);
+    let (mut user, scopes) = match authenticate(&state, &jar, bearer, proxy_identity).await {
+        Ok(authenticated) => authenticated,
+        Err(e) => return e.into_response(),
+    };
+    user.ip = client_ip.map(|ip| ip.to_string());
+
+    request.extensions_mut().insert(user);
+    request.extensions_mut().insert(Grant {
+        scopes,
+        read_only: state.config.read_only,
+    });
+    next.run(request).await
+}
+
+/// Rejects requests the role of the user, the scopes of the token or a read-only instance do not allow,
+/// runs inside of `require_user` on every route which is not `Access::Public`
+pub async fn authorize(access: Access, request: Request, next: Next) -> Response {
+    let extensions = request.extensions();
+    let (Some(user), Some(grant)) = (extensions.get::<CurrentUser>(), extensions.get::<Grant>())
+    else {
+        return AppError::new(StatusCode::UNAUTHORIZED, "Login required").into_response();
+    };
+    if let Err(e) = check_access(user, grant, access, request.method()) {
+        return e.into_response();
+    }
+    next.run(request).await
+}
+
+pub async fn login(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<LoginRequest>,
+) -> Result<(CookieJar, Json<UserResponse>), AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let user = find_user(&mut connection, request.username.trim())
+        .await
+        .map_err(AppError::internal)?;
+    let valid = match &user {
+        Some(user) => verify_password(&request.password, &user.password_hash).await,
+        None => {
+            // takes as long as a wrong password, unknown usernames can not be told apart
+            let _ = hash_password(&request.password).await;
+            false
+        }
+    };
+    let Some(user) = user.filter(|_| valid) else {
+        debug!("Failed login for {:?}", request.username);
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            "Invalid username or password",
+        ));
+    };
+
+    let token = create_session(&state.config, &mut connection, user.id)
+        .await
+        .map_err(AppError::internal)?;
+    info!("{} logged in", user.username);
+
+    Ok((
+        jar.add(session_cookie(&state.config, token)),
+        Json(UserResponse::from(&user)),
+    ))
+}
+
+pub async fn logout(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<(CookieJar, StatusCode), AppError> {
+    if let Some(cookie) = jar.get(SESSION_COOKIE) {
+        let mut connection = state.pool.get().await?;
+        diesel::delete(sessions::table.filter(sessions::token_hash.eq(hash_token(cookie.value()))))
+            .execute(&mut connection)
+            .await
+            .map_err(AppError::internal)?;
+    }
+
+    Ok((
+        jar.remove(Cookie::build(SESSION_COOKIE).path("/")),
+        StatusCode::OK,
+    ))
+}
+
+pub async fn current_user(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+) -> Result<Json<UserResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let stored = users::table
+        .find(user.id)
+        .first::<User>(&mut connection)
+        .await
+        .optional()
+        .map_err(AppError::internal)?;
+    Ok(Json(match stored {
+        Some(stored) => UserResponse::from(&stored),
+        // the local user while authentication is disabled or a visitor of a read-only instance
+        None => UserResponse {
+            id: user.id,
+            username: user.username,
+            role: user.role,
+            email: None,
+            date_added: None,
+        },
+    }))
+}
+
+pub async fn list_users(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+) -> Result<Json<Vec<UserResponse>>, AppError> {
+    user.require_admin()?;
+    let mut connection = state.pool.get().await?;
+
+    let users = users::table
+        .order(users::username.asc())
+        .load::<User>(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    Ok(Json(users.iter().map(UserResponse::from).collect()))
+}
+
+pub async fn handle_create_user(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Json(request): Json<CreateUserRequest>,
+) -> Result<(StatusCode, Json<UserResponse>), AppError> {
+    user.require_admin()?;
+    let mut connection = state.pool.get().await?;
+
+    let username = request.username.trim();
+    validate_new_user(username, &request.password).map_err(AppError::unprocessable)?;
+    if find_user(&mut connection, username)
+        .await
+        .map_err(AppError::internal)?
+        .is_some()
+    {
+        return Err(AppError::conflict(format!(
+            "The user {} already exists",
+            username
+        )));
+    }
+
+    let created = create_user(
+        &mut connection,
+        username,
+        &request.password,
+        request.role.unwrap_or(Role::Contributor),
+    )
+    .await
+    .map_err(AppError::internal)?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Create,
+        AuditTarget::new(AuditTargetType::User, created.id, &created.username),
+        format!("Created the account with the {} role", created.role),
+    )
+    .await;
+    Ok((StatusCode::CREATED, Json(UserResponse::from(&created))))
+}
+
+pub async fn delete_user(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(user_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    user.require_admin()?;
+    if user.id == user_id {
+        return Err(AppError::bad_request("You can not delete your own account"));
+    }
+    let mut connection = state.pool.get().await?;
+
+    let target = users::table
+        .find(user_id)
+        .first::<User>(&mut connection)
+        .await
+        .optional()
+        .map_err(AppError::internal)?
+        .ok_or_else(|| AppError::not_found(format!("User {} not found", user_id)))?;
+
+    diesel::delete(sessions::table.filter(sessions::user_id.eq(user_id)))
+        .execute(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    diesel::delete(api_tokens::table.filter(api_tokens::user_id.eq(user_id)))
+        .execute(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    diesel::delete(users::table.find(user_id))
+        .execute(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Delete,
+        AuditTarget::new(AuditTargetType::User, target.id, &target.username),
+        "Deleted the account with its sessions and API tokens",
+    )
+    .await;
+    Ok(StatusCode::OK)
+}
+
+pub async fn set_role(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(user_id): Path<i32>,
+    Json(request): Json<SetRoleRequest>,
+) -> Result<Json<UserResponse>, AppError> {
+    user.require_admin()?;
+    if user.id == user_id {
+        return Err(AppError::bad_request("You can not change your own role"));
+    }
+    let mut connection = state.pool.get().await?;
+
+    let updated = diesel::update(users::table.find(user_id))
+        .set(users::role.eq(request.role.as_str()))
+        .returning(User::as_returning())
+        .get_result(&mut connection)
+        .await
+        .optional()
+        .map_err(AppError::internal)?
+        .ok_or_else(|| AppError::not_found(format!("User {} not found", user_id)))?;
+    info!(
+        "{} made {} {}",
+        user.username,
+        updated.username,
+        request.role.as_str()
+    );
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Update,
+        AuditTarget::new(AuditTargetType::User, updated.id, &updated.username),
+        format!("Changed the role to {}", request.role.as_str()),
+    )
+    .await;
+    Ok(Json(UserResponse::from(&updated)))
+}
+
+/// Users change their own password with the current one, admins can reset any password
+pub async fn change_password(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    jar: CookieJar,
+    Path(user_id): Path<i32>,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<StatusCode, AppError> {
+    let own_account = user.id == user_id;
+    if !own_account {
+        user.require_admin()?;
+    }
+    validate_password(&request.new_password).map_err(AppError::unprocessable)?;
+    let mut connection = state.pool.get().await?;
+
+    let target = users::table
+        .find(user_id)
+        .first::<User>(&mut connection)
+        .await
+        .optional()
+        .map_err(AppError::internal)?
+        .ok_or_else(|| AppError::not_found(format!("User {} not found", user_id)))?;
+
+    if own_account {
+        let current_password = request.current_password.unwrap_or_default();
+        if !verify_password(&current_password, &target.password_hash).await {
+            return Err(AppError::forbidden("The current password is wrong"));
+        }
+    }
+
+    let password_hash = hash_password(&request.new_password)
+        .await
+        .map_err(AppError::internal)?;
+    diesel::update(users::table.find(user_id))
+        .set(users::password_hash.eq(password_hash))
+        .execute(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+
+    // other sessions of the account end, the one changing the password stays logged in
+    let current_token = jar
+        .get(SESSION_COOKIE)
+        .map(|cookie| hash_token(cookie.value()))
+        .unwrap_or_default();
+    diesel::delete(
+        sessions::table
+            .filter(sessions::user_id.eq(user_id))
+            .filter(sessions::token_hash.ne(current_token)),
+    )
+    .execute(&mut connection)
+    .await
+    .map_err(AppError::internal)?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Update,
+        AuditTarget::new(AuditTargetType::User, target.id, &target.username),
+        if own_account {
+            "Changed the password"
+        } else {
+            "Reset the password"
+        },
+    )
+    .await;
+
+    Ok(StatusCode::OK)
+}
+
+/// `meshvault create-user [--admin | --role=ROLE] USERNAME`, the password is read from MESHVAULT_PASSWORD or stdin
+pub async fn run_create_user_cli(
+    args: &[String],
+    pool: Pool<SyncConnectionWrapper<SqliteConnection>>,
+) -> anyhow::Result<()> {
+    let mut role = Role::Contributor;
+    for arg in args {
+        if arg == "--admin" {
+            role = Role::Admin;
+        } else if let Some(name) = arg.strip_prefix("--role=") {
+            role = Role::from_str(name)?;
+        }
+    }
+    let Some(username) = args.iter().find(|arg| !arg.starts_with("--")) else {
+        anyhow::bail!("Usage: meshvault create-user [--admin | --role=ROLE] USERNAME");
+    };
+
+    let password = match std::env::var("MESHVAULT_PASSWORD") {
+        Ok(password) => password,
+        Err(_) => {
+            eprintln!("Password for {}:", username);
+            let mut password = String::new();
+            std::io::stdin().lock().read_line(&mut password)?;
+            password.trim_end_matches(['\r', '\n']).to_string()
+        }
+    };
+
+    let mut connection = pool.get().await?;
+    let user = create_user(&mut connection, username, &password, role).await?;
+    println!("Created {} {}", role.as_str(), user.username);
+    Ok(())
+}
@@ -0,0 +1,298 @@
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::collections::HashMap;
+use tracing::debug;
+
+use crate::audit::{self, AuditTarget};
+use crate::auth::CurrentUser;
+use crate::error::AppError;
+use crate::schema::{author_aliases, authors, models3d};
+use crate::types::{
+    AuditAction, AuditTargetType, Author, AuthorAlias, AuthorResponse, DetailedAuthorResponse,
+    MergeAuthorRequest, Model3D, ModelResponse, NewAuthor, NewAuthorAlias, UpdateAuthorRequest,
+};
+use crate::{AppState, Config};
+
+/// Spelling variants of a name share this key, e.g. "John Doe", "john_doe" and "JohnDoe"
+pub fn alias_key(name: &str) -> String {
+    deunicode::deunicode(name)
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect()
+}
+
+/// Author for the author text of a ModelPack, it is created the first time the name shows up
+pub async fn author_for_name<Conn>(connection: &mut Conn, name: &str) -> anyhow::Result<Option<i32>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let name = name.trim();
+    let key = alias_key(name);
+    if key.is_empty() {
+        return Ok(None);
+    }
+
+    let existing = author_aliases::table
+        .filter(author_aliases::alias_key.eq(&key))
+        .select(author_aliases::author_id)
+        .first::<i32>(connection)
+        .await
+        .optional()?;
+    if existing.is_some() {
+        return Ok(existing);
+    }
+
+    let author_id = diesel::insert_into(authors::table)
+        .values(&NewAuthor {
+            name: name.to_string(),
+        })
+        .returning(authors::id)
+        .get_result::<i32>(connection)
+        .await?;
+    diesel::insert_into(author_aliases::table)
+        .values(&NewAuthorAlias {
+            author_id,
+            alias: name.to_string(),
+            alias_key: key,
+        })
+        .execute(connection)
+        .await?;
+    debug!("Created author {:?}", name);
+
+    Ok(Some(author_id))
+}
+
+async fn get_author_by_id<Conn>(connection: &mut Conn, author_id: i32) -> Result<Author, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    authors::table
+        .find(author_id)
+        .first::<Author>(connection)
+        .await
+        .optional()
+        .map_err(AppError::internal)?
+        .ok_or_else(|| AppError::not_found(format!("Author {} not found", author_id)))
+}
+
+async fn detailed_author<Conn>(
+    config: &Config,
+    user_id: i32,
+    connection: &mut Conn,
+    author: Author,
+) -> Result<DetailedAuthorResponse, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let aliases = AuthorAlias::belonging_to(&author)
+        .select(author_aliases::alias)
+        .order(author_aliases::alias.asc())
+        .load::<String>(connection)
+        .await
+        .map_err(AppError::internal)?;
+
+    let models = models3d::table
+        .filter(models3d::author_id.eq(author.id))
+        .order(models3d::title.asc())
+        .load::<Model3D>(connection)
+        .await
+        .map_err(AppError::internal)?;
+    let mut model_responses = Vec::new();
+    for model in &models {
+        model_responses.push(
+            ModelResponse::from_model_3d(model, config, user_id, connection)
+                .await
+                .map_err(AppError::internal)?,
+        );
+    }
+
+    Ok(DetailedAuthorResponse {
+        id: author.id,
+        name: author.name,
+        profile_url: author.profile_url,
+        aliases,
+        models: model_responses,
+    })
+}
+
+/// Authors with at least one model or a profile URL, sorted by name
+pub async fn list_authors(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<AuthorResponse>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let counts: HashMap<i32, i64> = models3d::table
+        .filter(models3d::author_id.is_not_null())
+        .group_by(models3d::author_id)
+        .select((
+            models3d::author_id.assume_not_null(),
+            diesel::dsl::count_star(),
+        ))
+        .load::<(i32, i64)>(&mut connection)
+        .await
+        .map_err(AppError::internal)?
+        .into_iter()
+        .collect();
+
+    let mut aliases: HashMap<i32, Vec<String>> = HashMap::new();
+    for alias in author_aliases::table
+        .order(author_aliases::alias.asc())
+        .load::<AuthorAlias>(&mut connection)
+        .await
+        .map_err(AppError::internal)?
+    {
+        aliases
+            .entry(alias.author_id)
+            .or_default()
+            .push(alias.alias);
+    }
+
+    let mut response: Vec<AuthorResponse> = authors::table
+        .load::<Author>(&mut connection)
+        .await
+        .map_err(AppError::internal)?
+        .into_iter()
+        .filter_map(|author| {
+            let model_count = counts.get(&author.id).copied().unwrap_or(0) as i32;
+            if model_count == 0 && author.profile_url.is_none() {
+                return None;
+            }
+            Some(AuthorResponse {
+                id: author.id,
+                aliases: aliases.remove(&author.id).unwrap_or_default(),
+                name: author.name,
+                profile_url: author.profile_url,
+                model_count,
+            })
+        })
+        .collect();
+    response.sort_by_key(|author| author.name.to_lowercase());
+
+    Ok(Json(response))
+}
+
+pub async fn get_author(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(author_id): Path<i32>,
+) -> Result<Json<DetailedAuthorResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let author = get_author_by_id(&mut connection, author_id).await?;
+    Ok(Json(
+        detailed_author(&state.config, user.id, &mut connection, author).await?,
+    ))
+}
+
+pub async fn update_author(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(author_id): Path<i32>,
+    Json(request): Json<UpdateAuthorRequest>,
+) -> Result<Json<DetailedAuthorResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+    let author = get_author_by_id(&mut connection, author_id).await?;
+
+    let name = match request.name.as_deref().map(str::trim) {
+        Some("") => return Err(AppError::unprocessable("name must not be empty")),
+        Some(name) => name.to_string(),
+        None => author.name,
+    };
+    let profile_url = match request.profile_url.as_deref().map(str::trim) {
+        Some("") => None,
+        Some(profile_url) => {
+            let is_web_url = url::Url::parse(profile_url)
+                .is_ok_and(|url| url.scheme() == "http" || url.scheme() == "https");
+            if !is_web_url {
+                return Err(AppError::unprocessable(
+                    "profile_url must be an http or https URL",
+                ));
+            }
+            Some(profile_url.to_string())
+        }
+        None => author.profile_url,
+    };
+
+    let author = diesel::update(authors::table.find(author_id))
+        .set((authors::name.eq(name), authors::profile_url.eq(profile_url)))
+        .returning(Author::as_returning())
+        .get_result(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Update,
+        AuditTarget::new(AuditTargetType::Author, author.id, &author.name),
+        format!(
+            "Set the name to {:?} and the profile URL to {:?}",
+            author.name, author.profile_url
+        ),
+    )
+    .await;
+
+    Ok(Json(
+        detailed_author(&state.config, user.id, &mut connection, author).await?,
+    ))
+}
+
+/// Moves the aliases and models of an author to another one, e.g. after a name was spelled differently
+pub async fn merge_author(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(author_id): Path<i32>,
+    Json(request): Json<MergeAuthorRequest>,
+) -> Result<Json<DetailedAuthorResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    if author_id == request.into {
+        return Err(AppError::bad_request(
+            "An author can not be merged into itself",
+        ));
+    }
+    let source = get_author_by_id(&mut connection, author_id).await?;
+    let target = get_author_by_id(&mut connection, request.into).await?;
+
+    let target_id = target.id;
+    connection
+        .transaction::<_, anyhow::Error, _>(|conn| {
+            async move {
+                diesel::update(
+                    author_aliases::table.filter(author_aliases::author_id.eq(author_id)),
+                )
+                .set(author_aliases::author_id.eq(target_id))
+                .execute(conn)
+                .await?;
+                diesel::update(models3d::table.filter(models3d::author_id.eq(author_id)))
+                    .set(models3d::author_id.eq(target_id))
+                    .execute(conn)
+                    .await?;
+                diesel::delete(authors::table.find(author_id))
+                    .execute(conn)
+                    .await?;
+                anyhow::Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+        .map_err(AppError::internal)?;
+    debug!("Merged author {} into {}", author_id, target_id);
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Merge,
+        AuditTarget::new(AuditTargetType::Author, target.id, &target.name),
+        format!("Merged {:?} ({}) into it", source.name, source.id),
+    )
+    .await;
+
+    Ok(Json(
+        detailed_author(&state.config, user.id, &mut connection, target).await?,
+    ))
+}
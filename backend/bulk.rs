@@ -0,0 +1,342 @@
+use axum::{
+    extract::{Path, State},
+    http::Method,
+    Extension, Json,
+};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::collections::{HashMap, HashSet};
+
+use crate::audit::{self, AuditTarget};
+use crate::auth::{self, Access, CurrentUser, Grant};
+use crate::error::AppError;
+use crate::schema::{model_collections, models3d};
+use crate::types::{
+    AuditAction, BulkAction, BulkItemResult, BulkRequest, BulkResponse, Collection,
+    ListModelParams, Model3D, NewModelCollection, UpdateMetadataRequest,
+};
+use crate::v2::IfMatch;
+use crate::{facets, metadata, AppState};
+
+/// Changes which only touch the database, they are applied in one transaction
+enum DatabaseChange {
+    Favourite(bool),
+    AddTo(Collection),
+    RemoveFrom(Collection),
+}
+
+/// Changes of the model folders, which can not be rolled back, so every model is on its own
+enum FolderChange {
+    Delete,
+    SetLicense(String),
+    SetAuthor(String),
+    Rescan,
+}
+
+enum Change {
+    Database(DatabaseChange),
+    Folder(FolderChange),
+}
+
+/// What an action needs, the same as the routes of single models
+fn access_of(action: BulkAction) -> Access {
+    match action {
+        BulkAction::Favourite | BulkAction::Unfavourite => Access::FAVOURITE,
+        BulkAction::AddToCollection | BulkAction::RemoveFromCollection => Access::CONTRIBUTE,
+        BulkAction::Delete | BulkAction::SetLicense | BulkAction::SetAuthor => Access::EDIT,
+        BulkAction::Rescan => Access::EDIT_FILES,
+    }
+}
+
+fn required<'a>(value: &'a Option<String>, field: &str, action: &str) -> Result<&'a str, AppError> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| AppError::unprocessable(format!("{} is required for {}", field, action)))
+}
+
+/// The change of an action with its parameters, collections are checked up front
+async fn change_of<Conn>(
+    connection: &mut Conn,
+    user: &CurrentUser,
+    action: BulkAction,
+    request: &BulkRequest,
+) -> Result<Change, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let change = match action {
+        BulkAction::Delete => Change::Folder(FolderChange::Delete),
+        BulkAction::Favourite => Change::Database(DatabaseChange::Favourite(true)),
+        BulkAction::Unfavourite => Change::Database(DatabaseChange::Favourite(false)),
+        BulkAction::AddToCollection | BulkAction::RemoveFromCollection => {
+            let id = request.collection_id.ok_or_else(|| {
+                AppError::unprocessable("collection_id is required for collection actions")
+            })?;
+            let collection = crate::visible_collection(connection, user, id).await?;
+            if !collection.can_edit_models(user) {
+                return Err(crate::forbidden());
+            }
+            if action == BulkAction::AddToCollection {
+                Change::Database(DatabaseChange::AddTo(collection))
+            } else {
+                Change::Database(DatabaseChange::RemoveFrom(collection))
+            }
+        }
+        BulkAction::SetLicense => Change::Folder(FolderChange::SetLicense(
+            required(&request.license, "license", "set_license")?.to_string(),
+        )),
+        BulkAction::SetAuthor => Change::Folder(FolderChange::SetAuthor(
+            required(&request.author, "author", "set_author")?.to_string(),
+        )),
+        BulkAction::Rescan => Change::Folder(FolderChange::Rescan),
+    };
+    Ok(change)
+}
+
+/// An empty filter would select every model of every library, which is never meant by accident
+fn has_criteria(filter: &ListModelParams) -> bool {
+    let texts = [
+        &filter.q,
+        &filter.author,
+        &filter.licenses,
+        &filter.file_types,
+        &filter.library,
+    ];
+    texts
+        .iter()
+        .any(|text| text.as_deref().is_some_and(|text| !text.trim().is_empty()))
+        || filter.author_id.is_some()
+        || filter.collection.is_some()
+        || filter.favourite.is_some()
+        || filter.printed.is_some()
+}
+
+/// The selected models in order, `None` for ids without a model
+async fn selected_models<Conn>(
+    connection: &mut Conn,
+    state: &AppState,
+    user: &CurrentUser,
+    request: &BulkRequest,
+) -> Result<Vec<(i32, Option<Model3D>)>, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    match (&request.ids, &request.filter) {
+        (Some(ids), None) => {
+            let mut seen = HashSet::new();
+            let ids: Vec<i32> = ids.iter().copied().filter(|id| seen.insert(*id)).collect();
+            let mut models: HashMap<i32, Model3D> = models3d::table
+                .filter(models3d::id.eq_any(&ids))
+                .load::<Model3D>(connection)
+                .await?
+                .into_iter()
+                .map(|model| (model.id, model))
+                .collect();
+            Ok(ids.into_iter().map(|id| (id, models.remove(&id))).collect())
+        }
+        (None, Some(filter)) if !has_criteria(filter) => Err(AppError::bad_request(
+            "The filter has to contain at least one criterion",
+        )),
+        (None, Some(filter)) => Ok(facets::filter_models(&state.config, filter, user)
+            .load::<Model3D>(connection)
+            .await?
+            .into_iter()
+            .map(|model| (model.id, Some(model)))
+            .collect()),
+        _ => Err(AppError::bad_request(
+            "Select the models either with ids or with a filter",
+        )),
+    }
+}
+
+async fn apply_to_database<Conn>(
+    connection: &mut Conn,
+    user: &CurrentUser,
+    change: &DatabaseChange,
+    model: &Model3D,
+) -> anyhow::Result<()>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    match change {
+        DatabaseChange::Favourite(favourite) => {
+            if model.is_favourite_of(user.id, connection).await? == *favourite {
+                return Ok(());
+            }
+            model.set_favourite(user.id, *favourite, connection).await?;
+            let (action, summary) = if *favourite {
+                (AuditAction::Favourite, "Added to the favourites")
+            } else {
+                (AuditAction::Unfavourite, "Removed from the favourites")
+            };
+            audit::record(connection, user, action, AuditTarget::model(model), summary).await;
+        }
+        DatabaseChange::AddTo(collection) => {
+            let existing: i64 = model_collections::table
+                .filter(model_collections::model_id.eq(model.id))
+                .filter(model_collections::collection_id.eq(collection.id))
+                .count()
+                .get_result(connection)
+                .await?;
+            if existing > 0 {
+                return Ok(());
+            }
+            diesel::insert_into(model_collections::table)
+                .values(&NewModelCollection {
+                    model_id: model.id,
+                    collection_id: collection.id,
+                })
+                .execute(connection)
+                .await?;
+            audit::record(
+                connection,
+                user,
+                AuditAction::AddModel,
+                AuditTarget::collection(collection),
+                format!("Added model {}", model.id),
+            )
+            .await;
+        }
+        DatabaseChange::RemoveFrom(collection) => {
+            let removed = diesel::delete(
+                model_collections::table
+                    .filter(model_collections::model_id.eq(model.id))
+                    .filter(model_collections::collection_id.eq(collection.id)),
+            )
+            .execute(connection)
+            .await?;
+            if removed > 0 {
+                audit::record(
+                    connection,
+                    user,
+                    AuditAction::RemoveModel,
+                    AuditTarget::collection(collection),
+                    format!("Removed model {}", model.id),
+                )
+                .await;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Goes through the handlers of single models, so permissions, revisions and the audit log stay the same
+async fn apply_to_folder(
+    state: &AppState,
+    user: &CurrentUser,
+    change: &FolderChange,
+    model: &Model3D,
+) -> Result<(), AppError> {
+    let state = State(state.clone());
+    let user = Extension(user.clone());
+    let slug = Path(model.name.clone());
+    let metadata = match change {
+        FolderChange::Delete => {
+            return crate::delete_model(state, user, slug, IfMatch::default())
+                .await
+                .map(drop);
+        }
+        FolderChange::Rescan => {
+            return crate::refresh_model(state, user, slug).await.map(drop);
+        }
+        FolderChange::SetLicense(license) => UpdateMetadataRequest {
+            license: Some(license.clone()),
+            ..Default::default()
+        },
+        FolderChange::SetAuthor(author) => UpdateMetadataRequest {
+            author: Some(author.clone()),
+            ..Default::default()
+        },
+    };
+    metadata::update_metadata(state, user, slug, IfMatch::default(), Json(metadata))
+        .await
+        .map(drop)
+}
+
+fn succeeded(model: &Model3D) -> BulkItemResult {
+    BulkItemResult {
+        id: model.id,
+        title: Some(model.title.clone()),
+        success: true,
+        error: None,
+    }
+}
+
+fn failed(id: i32, title: Option<String>, error: String) -> BulkItemResult {
+    BulkItemResult {
+        id,
+        title,
+        success: false,
+        error: Some(error),
+    }
+}
+
+fn missing(id: i32) -> BulkItemResult {
+    failed(id, None, format!("Model {} not found", id))
+}
+
+pub async fn bulk_models(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Extension(grant): Extension<Grant>,
+    Path(action): Path<BulkAction>,
+    Json(request): Json<BulkRequest>,
+) -> Result<Json<BulkResponse>, AppError> {
+    auth::check_access(&user, &grant, access_of(action), &Method::POST)?;
+    let (change, selection) = {
+        let mut connection = state.pool.get().await?;
+        let change = change_of(&mut connection, &user, action, &request).await?;
+        let selection = selected_models(&mut connection, &state, &user, &request).await?;
+        (change, selection)
+    };
+
+    let results = match change {
+        Change::Database(change) => {
+            let user = &user;
+            let change = &change;
+            let mut connection = state.pool.get().await?;
+            connection
+                .transaction::<_, anyhow::Error, _>(|conn| {
+                    async move {
+                        let mut results = Vec::new();
+                        for (id, model) in selection {
+                            let Some(model) = model else {
+                                results.push(missing(id));
+                                continue;
+                            };
+                            apply_to_database(conn, user, change, &model).await?;
+                            results.push(succeeded(&model));
+                        }
+                        Ok(results)
+                    }
+                    .scope_boxed()
+                })
+                .await?
+        }
+        Change::Folder(change) => {
+            let mut results = Vec::new();
+            for (id, model) in selection {
+                let Some(model) = model else {
+                    results.push(missing(id));
+                    continue;
+                };
+                match apply_to_folder(&state, &user, &change, &model).await {
+                    Ok(()) => results.push(succeeded(&model)),
+                    Err(e) => results.push(failed(id, Some(model.title), e.message)),
+                }
+            }
+            results
+        }
+    };
+
+    let succeeded = results.iter().filter(|result| result.success).count() as i32;
+    Ok(Json(BulkResponse {
+        action,
+        succeeded,
+        failed: results.len() as i32 - succeeded,
+        results,
+    }))
+}
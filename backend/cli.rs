@@ -0,0 +1,33 @@
+use diesel::SqliteConnection;
+use diesel_async::pooled_connection::bb8::Pool;
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+
+use crate::adopt;
+use crate::auth;
+use crate::Config;
+
+const USAGE: &str = "Usage: meshvault [COMMAND]
+
+Without a command the server is started.
+
+Commands:
+  adopt [--yes] [--dry-run] [FOLDER...]  Turn folders with mesh or CAD files into ModelPacks
+  create-user [--admin | --role=ROLE] USERNAME
+                                         Create a user, the password is read from MESHVAULT_PASSWORD or stdin";
+
+pub async fn run(
+    args: &[String],
+    config: &Config,
+    pool: Pool<SyncConnectionWrapper<SqliteConnection>>,
+) -> anyhow::Result<()> {
+    match args.first().map(String::as_str) {
+        Some("adopt") => adopt::run_cli(&args[1..], config, pool).await,
+        Some("create-user") => auth::run_create_user_cli(&args[1..], pool).await,
+        Some("help" | "--help" | "-h") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(command) => anyhow::bail!("Unknown command {}\n\n{}", command, USAGE),
+        None => Ok(()),
+    }
+}
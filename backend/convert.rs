@@ -12,7 +12,7 @@ use threemf::{
 use tracing::debug;
 
 pub fn load_stl(stl_path: &PathBuf) -> anyhow::Result<IndexedMesh> {
-    let mut file = OpenOptions::new().read(true).open(stl_path).unwrap();
+    let mut file = OpenOptions::new().read(true).open(stl_path)?;
     stl_io::read_stl(&mut file).map_err(|e| anyhow::anyhow!(e))
 }
 
@@ -20,7 +20,7 @@ fn occt_shape_to_indexed_mesh(shape: &Shape) -> anyhow::Result<IndexedMesh> {
     let temp_stl_path = std::env::temp_dir().join(format!("tmp_{}.stl", uuid::Uuid::new_v4()));
     shape.write_stl(&temp_stl_path)?;
 
-    let mut file = OpenOptions::new().read(true).open(&temp_stl_path).unwrap();
+    let mut file = OpenOptions::new().read(true).open(&temp_stl_path)?;
     let result = stl_io::read_stl(&mut file).map_err(|e| anyhow::anyhow!(e));
 
     std::fs::remove_file(&temp_stl_path)?;
@@ -58,6 +58,22 @@ pub fn iges_to_step(iges_path: &PathBuf) -> anyhow::Result<Vec<u8>> {
 }
 
 pub fn load_obj(path: &PathBuf) -> anyhow::Result<IndexedMesh> {
+    let mesh = read_obj(path)?;
+
+    // Validate the mesh
+    mesh.validate()
+        .map_err(|e| anyhow::anyhow!("Invalid mesh: {:?}", e))?;
+    debug!(
+        "Valid mesh with {} vertices and {} faces",
+        mesh.vertices.len(),
+        mesh.faces.len()
+    );
+
+    Ok(mesh)
+}
+
+/// Reads an OBJ file into an indexed mesh without checking whether the mesh is closed
+pub fn read_obj(path: &PathBuf) -> anyhow::Result<IndexedMesh> {
     let (models, _) = tobj::load_obj(
         path,
         &tobj::LoadOptions {
@@ -67,8 +83,10 @@ pub fn load_obj(path: &PathBuf) -> anyhow::Result<IndexedMesh> {
             ..Default::default()
         },
     )
-    .expect("Failed to OBJ load file");
-    let model = &models[0];
+    .map_err(|e| anyhow::anyhow!("Failed to load OBJ file: {}", e))?;
+    let model = models
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("OBJ file does not contain a model"))?;
 
     // First, create a map of unique vertices to handle potential duplicates
     let mut unique_vertices = Vec::new();
@@ -143,21 +161,10 @@ pub fn load_obj(path: &PathBuf) -> anyhow::Result<IndexedMesh> {
     }
 
     // Create the mesh with unique vertices
-    let mesh = IndexedMesh {
+    Ok(IndexedMesh {
         vertices: unique_vertices,
         faces,
-    };
-
-    // Validate the mesh
-    mesh.validate()
-        .map_err(|e| anyhow::anyhow!("Invalid mesh: {:?}", e))?;
-    debug!(
-        "Valid mesh with {} vertices and {} faces",
-        mesh.vertices.len(),
-        mesh.faces.len()
-    );
-
-    Ok(mesh)
+    })
 }
 
 pub fn stl_mesh_to_3mf_mesh(stl: &IndexedMesh) -> ThreemfMesh {
@@ -193,7 +200,7 @@ pub fn stl_mesh_to_3mf_mesh(stl: &IndexedMesh) -> ThreemfMesh {
 pub fn convert_to_3mf(stl_path: &PathBuf) -> anyhow::Result<Vec<u8>> {
     let stl = load_stl(stl_path);
 
-    save_as_threemf(&stl.unwrap())
+    save_as_threemf(&stl?)
 }
 
 pub fn save_as_threemf(stl: &IndexedMesh) -> anyhow::Result<Vec<u8>> {
@@ -203,12 +210,12 @@ pub fn save_as_threemf(stl: &IndexedMesh) -> anyhow::Result<Vec<u8>> {
     let mut buffer = Vec::new();
     let mut cursor = Cursor::new(&mut buffer);
 
-    threemf::write::write(&mut cursor, model).unwrap();
+    threemf::write::write(&mut cursor, model)?;
     Ok(buffer)
 }
 
 pub fn check_is_ascii(stl_path: &PathBuf) -> anyhow::Result<bool> {
-    let mut file = OpenOptions::new().read(true).open(stl_path).unwrap();
+    let mut file = OpenOptions::new().read(true).open(stl_path)?;
     match AsciiStlReader::probe(&mut file) {
         Ok(()) => Ok(true),
         Err(_) => Ok(false),
@@ -231,15 +238,15 @@ pub fn save_as_stl(stl: &IndexedMesh) -> anyhow::Result<Vec<u8>> {
 
     let mut buffer = Vec::new();
     let mut cursor = Cursor::new(&mut buffer);
-    stl_io::write_stl(&mut cursor, mesh.iter()).unwrap();
+    stl_io::write_stl(&mut cursor, mesh.iter())?;
     Ok(buffer)
 }
 
 pub fn astl_convert_to_bstl(stl_path: &PathBuf) -> anyhow::Result<Vec<u8>> {
-    if !check_is_ascii(stl_path).unwrap() {
+    if !check_is_ascii(stl_path)? {
         debug!("Nothing to do for stl");
     }
 
     let indexed_mesh = load_stl(stl_path);
-    save_as_stl(&indexed_mesh.unwrap())
+    save_as_stl(&indexed_mesh?)
 }
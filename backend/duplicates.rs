@@ -0,0 +1,277 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::error::AppError;
+use crate::schema::{files3d, models3d};
+use crate::types::{
+    DuplicateFile, DuplicateFileGroup, DuplicateModelGroup, DuplicatesParams, DuplicatesResponse,
+    ModelReference,
+};
+
+const DEFAULT_MIN_SIMILARITY: f64 = 0.8;
+
+type FileRow = (i32, String, Option<String>, i32, i32, String, String);
+
+/// Groups of files sharing the same SHA-256, ordered by hash
+pub async fn find_duplicate_files<Conn>(
+    connection: &mut Conn,
+) -> anyhow::Result<Vec<DuplicateFileGroup>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let duplicate_hashes: Vec<Option<String>> = files3d::table
+        .filter(files3d::file_hash.is_not_null())
+        .group_by(files3d::file_hash)
+        .having(count_star().gt(1))
+        .select(files3d::file_hash)
+        .load(connection)
+        .await?;
+
+    let rows: Vec<FileRow> = files3d::table
+        .inner_join(models3d::table)
+        .filter(files3d::file_hash.eq_any(duplicate_hashes))
+        .select((
+            files3d::id,
+            files3d::file_path,
+            files3d::file_hash,
+            files3d::file_size_bytes,
+            models3d::id,
+            models3d::title,
+            models3d::name,
+        ))
+        .load(connection)
+        .await?;
+
+    let mut groups: BTreeMap<String, DuplicateFileGroup> = BTreeMap::new();
+    for (file_id, file_path, file_hash, file_size, model_id, title, name) in rows {
+        let Some(file_hash) = file_hash else {
+            continue;
+        };
+        groups
+            .entry(file_hash.clone())
+            .or_insert_with(|| DuplicateFileGroup {
+                file_hash,
+                file_size: human_bytes::human_bytes(file_size as f64),
+                files: Vec::new(),
+            })
+            .files
+            .push(DuplicateFile {
+                file_id,
+                file_path,
+                model: ModelReference {
+                    id: model_id,
+                    title,
+                    name,
+                },
+            });
+    }
+
+    Ok(groups.into_values().collect())
+}
+
+/// Root of `id` in a union-find forest of model ids, compresses the path on the way
+fn find_root(parents: &mut HashMap<i32, i32>, id: i32) -> i32 {
+    let parent = *parents.entry(id).or_insert(id);
+    if parent == id {
+        return id;
+    }
+    let root = find_root(parents, parent);
+    parents.insert(id, root);
+    root
+}
+
+/// Groups of models linked by pairs whose sets of file hashes overlap by at least `min_similarity`,
+/// so N copies of a pack are one group instead of every pair of them
+pub async fn find_duplicate_models<Conn>(
+    connection: &mut Conn,
+    min_similarity: f64,
+) -> anyhow::Result<Vec<DuplicateModelGroup>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let rows: Vec<(i32, Option<String>)> = files3d::table
+        .filter(files3d::file_hash.is_not_null())
+        .select((files3d::model_id, files3d::file_hash))
+        .load(connection)
+        .await?;
+
+    let mut hashes_by_model: HashMap<i32, HashSet<String>> = HashMap::new();
+    let mut models_by_hash: HashMap<String, HashSet<i32>> = HashMap::new();
+    for (model_id, file_hash) in rows.into_iter() {
+        if let Some(file_hash) = file_hash {
+            hashes_by_model
+                .entry(model_id)
+                .or_default()
+                .insert(file_hash.clone());
+            models_by_hash
+                .entry(file_hash)
+                .or_default()
+                .insert(model_id);
+        }
+    }
+
+    // only models sharing at least one file can be similar
+    let mut shared: BTreeMap<(i32, i32), i32> = BTreeMap::new();
+    for model_ids in models_by_hash.values().filter(|ids| ids.len() > 1) {
+        let mut model_ids: Vec<i32> = model_ids.iter().copied().collect();
+        model_ids.sort();
+        for (i, first) in model_ids.iter().enumerate() {
+            for second in &model_ids[i + 1..] {
+                *shared.entry((*first, *second)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut pairs = Vec::new();
+    for ((first, second), shared_files) in shared {
+        let total = hashes_by_model[&first]
+            .union(&hashes_by_model[&second])
+            .count();
+        let similarity = shared_files as f64 / total as f64;
+        if similarity >= min_similarity {
+            pairs.push((first, second, similarity));
+        }
+    }
+
+    let mut parents: HashMap<i32, i32> = HashMap::new();
+    for (first, second, _) in &pairs {
+        let (first, second) = (
+            find_root(&mut parents, *first),
+            find_root(&mut parents, *second),
+        );
+        if first != second {
+            parents.insert(first.max(second), first.min(second));
+        }
+    }
+
+    // a group is as similar as the weakest pair joining it
+    let mut similarity_by_root: BTreeMap<i32, f64> = BTreeMap::new();
+    for (first, _, similarity) in &pairs {
+        let root = find_root(&mut parents, *first);
+        let group_similarity = similarity_by_root.entry(root).or_insert(*similarity);
+        *group_similarity = group_similarity.min(*similarity);
+    }
+
+    let model_ids: Vec<i32> = parents.keys().copied().collect();
+    let root_of: HashMap<i32, i32> = model_ids
+        .iter()
+        .map(|model_id| (*model_id, find_root(&mut parents, *model_id)))
+        .collect();
+    let mut members_by_root: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+    for (model_id, root) in &root_of {
+        members_by_root.entry(*root).or_default().push(*model_id);
+    }
+
+    let mut shared_by_root: HashMap<i32, i32> = HashMap::new();
+    for model_ids in models_by_hash.values().filter(|ids| ids.len() > 1) {
+        let mut copies: HashMap<i32, i32> = HashMap::new();
+        for root in model_ids.iter().filter_map(|id| root_of.get(id)) {
+            *copies.entry(*root).or_default() += 1;
+        }
+        for (root, count) in copies {
+            if count > 1 {
+                *shared_by_root.entry(root).or_default() += 1;
+            }
+        }
+    }
+
+    let references = model_references(connection, model_ids).await?;
+
+    let mut groups: Vec<DuplicateModelGroup> = members_by_root
+        .into_iter()
+        .map(|(root, mut members)| {
+            members.sort();
+            let similarity = similarity_by_root[&root];
+            DuplicateModelGroup {
+                models: members
+                    .iter()
+                    .filter_map(|id| references.get(id).cloned())
+                    .collect(),
+                shared_files: shared_by_root.get(&root).copied().unwrap_or_default(),
+                similarity,
+                identical: similarity >= 1.0,
+            }
+        })
+        .filter(|group| group.models.len() > 1)
+        .collect();
+    groups.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+
+    Ok(groups)
+}
+
+async fn model_references<Conn>(
+    connection: &mut Conn,
+    model_ids: Vec<i32>,
+) -> anyhow::Result<HashMap<i32, ModelReference>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let rows: Vec<(i32, String, String)> = models3d::table
+        .filter(models3d::id.eq_any(model_ids))
+        .select((models3d::id, models3d::title, models3d::name))
+        .load(connection)
+        .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, title, name)| (id, ModelReference { id, title, name }))
+        .collect())
+}
+
+/// Models containing files with the given hashes, keyed by hash
+pub async fn models_with_hashes<Conn>(
+    connection: &mut Conn,
+    hashes: Vec<String>,
+    exclude_model_id: Option<i32>,
+) -> anyhow::Result<HashMap<String, Vec<ModelReference>>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let rows: Vec<(Option<String>, i32, String, String)> = files3d::table
+        .inner_join(models3d::table)
+        .filter(files3d::file_hash.eq_any(hashes))
+        .filter(models3d::id.ne(exclude_model_id.unwrap_or(-1)))
+        .select((
+            files3d::file_hash,
+            models3d::id,
+            models3d::title,
+            models3d::name,
+        ))
+        .load(connection)
+        .await?;
+
+    let mut models: HashMap<String, Vec<ModelReference>> = HashMap::new();
+    for (file_hash, id, title, name) in rows {
+        if let Some(file_hash) = file_hash {
+            let reference = ModelReference { id, title, name };
+            let entry = models.entry(file_hash).or_default();
+            if !entry.contains(&reference) {
+                entry.push(reference);
+            }
+        }
+    }
+
+    Ok(models)
+}
+
+pub async fn list_duplicates(
+    State(state): State<crate::AppState>,
+    Query(params): Query<DuplicatesParams>,
+) -> Result<Json<DuplicatesResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+    let min_similarity = params
+        .min_similarity
+        .unwrap_or(DEFAULT_MIN_SIMILARITY)
+        .clamp(0.0, 1.0);
+
+    let files = find_duplicate_files(&mut connection).await?;
+    let models = find_duplicate_models(&mut connection, min_similarity).await?;
+
+    Ok(Json(DuplicatesResponse { files, models }))
+}
@@ -0,0 +1,148 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use tracing::error;
+
+use crate::types::ErrorResponse;
+
+/// Longest plain text error body that is turned into a JSON error
+const MAX_TEXT_ERROR_SIZE: usize = 64 * 1024;
+
+/// Error of a handler, sent as an [`ErrorResponse`] with its status code
+#[derive(Debug)]
+pub struct AppError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        AppError {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, message)
+    }
+
+    pub fn unprocessable(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNPROCESSABLE_ENTITY, message)
+    }
+
+    /// Logs the cause, clients only learn that something went wrong
+    pub fn internal(cause: impl std::fmt::Display) -> Self {
+        error!("{}", cause);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.status, self.message)
+    }
+}
+
+fn error_body(status: StatusCode, message: String) -> ErrorResponse {
+    ErrorResponse {
+        status: status.as_u16(),
+        error: status
+            .canonical_reason()
+            .unwrap_or("Unknown error")
+            .to_string(),
+        message,
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        (self.status, Json(error_body(self.status, self.message))).into_response()
+    }
+}
+
+impl From<DieselError> for AppError {
+    fn from(e: DieselError) -> Self {
+        match e {
+            DieselError::NotFound => AppError::not_found("Not found"),
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                AppError::conflict(info.message().to_string())
+            }
+            e => AppError::internal(e),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+/// Handler and database errors keep their meaning, everything else is an internal error
+impl From<anyhow::Error> for AppError {
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<AppError>() {
+            Ok(e) => return e,
+            Err(e) => e,
+        };
+        match e.downcast::<DieselError>() {
+            Ok(e) => e.into(),
+            Err(e) => AppError::internal(format!("{:#}", e)),
+        }
+    }
+}
+
+impl From<diesel_async::pooled_connection::bb8::RunError> for AppError {
+    fn from(e: diesel_async::pooled_connection::bb8::RunError) -> Self {
+        AppError::internal(format!("No database connection: {}", e))
+    }
+}
+
+/// Turns the plain text errors of axum's extractors and middleware into JSON errors
+pub async fn json_errors(request: Request, next: Next) -> Response {
+    let response = next.run(request).await;
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let message = match to_bytes(body, MAX_TEXT_ERROR_SIZE).await {
+        Ok(bytes) if !bytes.is_empty() => String::from_utf8_lossy(&bytes).into_owned(),
+        _ => status.canonical_reason().unwrap_or_default().to_string(),
+    };
+    let body = match serde_json::to_vec(&error_body(status, message)) {
+        Ok(body) => body,
+        Err(e) => return AppError::internal(e).into_response(),
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Unknown API routes get a JSON 404 instead of the frontend
+pub async fn api_not_found() -> AppError {
+    AppError::not_found("No such API endpoint")
+}
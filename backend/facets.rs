@@ -0,0 +1,251 @@
+use diesel::prelude::*;
+use diesel::sqlite::Sqlite;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::debug;
+
+use crate::auth::CurrentUser;
+use crate::libraries::in_library;
+use crate::licenses::count_licenses;
+use crate::schema::{
+    authors, collections, favourites, files3d, model_collections, models3d, prints,
+};
+use crate::types::{
+    visible_collections, AuthorFacet, CollectionFacet, FavouriteFacet, FileTypeFacet,
+    ListModelParams, ModelFacets,
+};
+use crate::Config;
+
+pub type ModelQuery = models3d::BoxedQuery<'static, Sqlite>;
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Lowercase extension of a file path, e.g. "step" for files/part.STEP
+fn file_type(file_path: &str) -> Option<String> {
+    std::path::Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase())
+}
+
+/// Models matching the filters of `params` for the user with `user`, without paging
+pub fn filter_models(config: &Config, params: &ListModelParams, user: &CurrentUser) -> ModelQuery {
+    let mut models = models3d::table.into_boxed();
+
+    if let Some(ref library) = params.library {
+        models = models.filter(in_library(config, library));
+    }
+
+    if let Some(ref q) = params.q {
+        let pattern = format!("%{}%", q);
+        models = models.filter(
+            models3d::name
+                .like(pattern.clone())
+                .or(models3d::title.like(pattern.clone()))
+                .or(models3d::description.like(pattern.clone()))
+                .or(models3d::author.like(pattern.clone())),
+        );
+    }
+
+    if let Some(ref author) = params.author {
+        let pattern = format!("%{}%", author);
+        models = models.filter(models3d::author.like(pattern));
+    }
+
+    if let Some(author_id) = params.author_id {
+        models = models.filter(models3d::author_id.eq(author_id));
+    }
+
+    if let Some(ref licenses) = params.licenses {
+        // SPDX identifiers, unknown licenses are matched by their original text
+        let licenses = split_list(licenses);
+        debug!("Filtering models with licenses: {:?}", licenses);
+        models = models.filter(
+            models3d::license_spdx
+                .eq_any(licenses.clone())
+                .or(models3d::license_spdx
+                    .is_null()
+                    .and(models3d::license.eq_any(licenses))),
+        );
+    }
+
+    if let Some(ref file_types) = params.file_types {
+        let mut with_file_type = files3d::table.select(files3d::model_id).into_boxed();
+        for (i, file_type) in split_list(file_types).iter().enumerate() {
+            let pattern = format!("%.{}", file_type.trim_start_matches('.'));
+            if i == 0 {
+                with_file_type = with_file_type.filter(files3d::file_path.like(pattern));
+            } else {
+                with_file_type = with_file_type.or_filter(files3d::file_path.like(pattern));
+            }
+        }
+        models = models.filter(models3d::id.eq_any(with_file_type));
+    }
+
+    if let Some(collection_id) = params.collection {
+        let visible = visible_collections(user).select(collections::id);
+        let in_collection = model_collections::table
+            .filter(model_collections::collection_id.eq(collection_id))
+            .filter(model_collections::collection_id.eq_any(visible))
+            .select(model_collections::model_id);
+        models = models.filter(models3d::id.eq_any(in_collection));
+    }
+
+    if let Some(favourite) = params.favourite {
+        let liked = favourites::table
+            .filter(favourites::user_id.eq(user.id))
+            .select(favourites::model_id);
+        if favourite {
+            models = models.filter(models3d::id.eq_any(liked));
+        } else {
+            models = models.filter(models3d::id.ne_all(liked));
+        }
+    }
+
+    if let Some(printed) = params.printed {
+        let printed_models = prints::table.select(prints::model_id);
+        if printed {
+            models = models.filter(models3d::id.eq_any(printed_models));
+        } else {
+            models = models.filter(models3d::id.ne_all(printed_models));
+        }
+    }
+
+    models
+}
+
+/// `params` without the filter of one facet, so its other options keep their counts while one is selected
+fn without(params: &ListModelParams, clear: impl FnOnce(&mut ListModelParams)) -> ListModelParams {
+    let mut params = params.clone();
+    clear(&mut params);
+    params
+}
+
+/// Counts of the filter options under the current filters, options without models are left out
+pub async fn model_facets<Conn>(
+    connection: &mut Conn,
+    config: &Config,
+    params: &ListModelParams,
+    user: &CurrentUser,
+) -> anyhow::Result<ModelFacets>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let licenses = filter_models(config, &without(params, |p| p.licenses = None), user)
+        .select((models3d::license_spdx, models3d::license))
+        .load::<(Option<String>, Option<String>)>(connection)
+        .await?;
+
+    let mut author_counts: HashMap<i32, i32> = HashMap::new();
+    let models_for_authors = without(params, |p| {
+        p.author = None;
+        p.author_id = None;
+    });
+    for author_id in filter_models(config, &models_for_authors, user)
+        .filter(models3d::author_id.is_not_null())
+        .select(models3d::author_id.assume_not_null())
+        .load::<i32>(connection)
+        .await?
+    {
+        *author_counts.entry(author_id).or_default() += 1;
+    }
+    let mut authors: Vec<AuthorFacet> = authors::table
+        .filter(authors::id.eq_any(author_counts.keys().copied().collect::<Vec<i32>>()))
+        .select((authors::id, authors::name))
+        .load::<(i32, String)>(connection)
+        .await?
+        .into_iter()
+        .map(|(id, name)| AuthorFacet {
+            id,
+            name,
+            count: author_counts[&id],
+        })
+        .collect();
+    authors.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+
+    let models_for_file_types =
+        filter_models(config, &without(params, |p| p.file_types = None), user).select(models3d::id);
+    let files = files3d::table
+        .filter(files3d::model_id.eq_any(models_for_file_types))
+        .select((files3d::model_id, files3d::file_path))
+        .load::<(i32, String)>(connection)
+        .await?;
+    let mut models_by_type: BTreeMap<String, HashSet<i32>> = BTreeMap::new();
+    for (model_id, file_path) in files {
+        if let Some(file_type) = file_type(&file_path) {
+            models_by_type
+                .entry(file_type)
+                .or_default()
+                .insert(model_id);
+        }
+    }
+    let mut file_types: Vec<FileTypeFacet> = models_by_type
+        .into_iter()
+        .map(|(file_type, models)| FileTypeFacet {
+            file_type,
+            count: models.len() as i32,
+        })
+        .collect();
+    file_types.sort_by(|a, b| b.count.cmp(&a.count).then(a.file_type.cmp(&b.file_type)));
+
+    let model_ids = filter_models(config, &without(params, |p| p.favourite = None), user)
+        .select(models3d::id)
+        .load::<i32>(connection)
+        .await?;
+    let liked: HashSet<i32> = favourites::table
+        .filter(favourites::user_id.eq(user.id))
+        .select(favourites::model_id)
+        .load::<i32>(connection)
+        .await?
+        .into_iter()
+        .collect();
+    let liked_count = model_ids.iter().filter(|id| liked.contains(id)).count() as i32;
+    let favourite = FavouriteFacet {
+        favourite: liked_count,
+        other: model_ids.len() as i32 - liked_count,
+    };
+
+    let models_for_collections =
+        filter_models(config, &without(params, |p| p.collection = None), user).select(models3d::id);
+    let visible = visible_collections(user).select(collections::id);
+    let memberships = model_collections::table
+        .inner_join(collections::table)
+        .filter(model_collections::model_id.eq_any(models_for_collections))
+        .filter(model_collections::collection_id.eq_any(visible))
+        .select((
+            collections::id,
+            collections::name,
+            model_collections::model_id,
+        ))
+        .load::<(i32, String, i32)>(connection)
+        .await?;
+    let mut models_by_collection: BTreeMap<(i32, String), HashSet<i32>> = BTreeMap::new();
+    for (id, name, model_id) in memberships {
+        models_by_collection
+            .entry((id, name))
+            .or_default()
+            .insert(model_id);
+    }
+    let mut collections: Vec<CollectionFacet> = models_by_collection
+        .into_iter()
+        .map(|((id, name), models)| CollectionFacet {
+            id,
+            name,
+            count: models.len() as i32,
+        })
+        .collect();
+    collections.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+
+    Ok(ModelFacets {
+        licenses: count_licenses(licenses),
+        authors,
+        file_types,
+        favourite,
+        collections,
+    })
+}
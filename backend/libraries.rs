@@ -0,0 +1,337 @@
+use axum::extract::{Request, State};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use serde_derive::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::error::AppError;
+use crate::schema::models3d;
+use crate::types::{LibraryResponse, Model3D};
+use crate::{AppState, Config};
+
+/// A folder with models, its name is stored with the models unless it is the main library
+#[derive(Clone, Debug, Serialize)]
+pub struct Library {
+    pub name: String,
+    pub path: PathBuf,
+    /// Models are never changed, moved or deleted on disk
+    pub read_only: bool,
+    /// New models may be uploaded into it
+    pub uploads: bool,
+    /// URL prefix its files are served under
+    pub asset_prefix: String,
+    /// The library at LIBRARIES_PATH, its models have no library name in the database
+    pub main: bool,
+}
+
+impl Library {
+    /// Value of the library column of its models
+    pub fn key(&self) -> Option<&str> {
+        (!self.main).then_some(self.name.as_str())
+    }
+
+    /// Has to be on the same file system as the library for atomic renames, so it is a hidden
+    /// folder inside the library that is never served, see [`hide_dot_paths`]
+    pub fn staging_dir(&self) -> PathBuf {
+        self.path.join(".meshvault_staging")
+    }
+
+    /// Hidden like [`Library::staging_dir`] for the same reason
+    pub fn trash_dir(&self) -> PathBuf {
+        self.path.join(".meshvault_trash")
+    }
+
+    pub fn ensure_writable(&self) -> Result<(), AppError> {
+        if self.read_only {
+            return Err(AppError::forbidden(format!(
+                "The library {} is read-only",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn ensure_uploads(&self) -> Result<(), AppError> {
+        self.ensure_writable()?;
+        if !self.uploads {
+            return Err(AppError::forbidden(format!(
+                "The library {} does not accept uploads",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Whether a relative path is or goes through a hidden file or folder
+pub fn is_hidden_path(path: &Path) -> bool {
+    path.components().any(|component| {
+        component
+            .as_os_str()
+            .to_str()
+            .is_some_and(|name| name.starts_with('.'))
+    })
+}
+
+/// Answers 404 for URLs with a hidden segment, so the asset routes never serve the trash
+/// and staging folders. A segment is checked before percent decoding, `%2E` counts as a dot too.
+pub async fn hide_dot_paths(request: Request, next: Next) -> Response {
+    let hidden = request.uri().path().split('/').any(|segment| {
+        segment.starts_with('.')
+            || segment
+                .get(..3)
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case("%2e"))
+    });
+    if hidden {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    next.run(request).await
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// `name=path` followed by the optional flags `:read_only` and `:no_upload`
+fn parse_entry(config: &Config, entry: &str) -> Result<Library, String> {
+    let (name, rest) = entry
+        .split_once('=')
+        .ok_or_else(|| format!("{:?} has to look like name=path", entry))?;
+    let name = name.trim();
+    if !is_valid_name(name) {
+        return Err(format!(
+            "The library name {:?} may only contain a-z, 0-9, - and _",
+            name
+        ));
+    }
+
+    let mut parts = rest.trim().split(':');
+    let path = PathBuf::from(parts.next().unwrap_or_default());
+    if !path.is_absolute() {
+        return Err(format!("The path of the library {} is not absolute", name));
+    }
+    let mut read_only = false;
+    let mut uploads = true;
+    for flag in parts {
+        match flag.trim() {
+            "read_only" => read_only = true,
+            "no_upload" => uploads = false,
+            other => return Err(format!("Unknown flag {:?} of the library {}", other, name)),
+        }
+    }
+
+    Ok(Library {
+        name: name.to_string(),
+        path,
+        read_only,
+        uploads: uploads && !read_only,
+        asset_prefix: config.library_asset_prefix_of(Some(name)),
+        main: false,
+    })
+}
+
+/// The main library followed by the ones of LIBRARIES
+pub fn parse_libraries(config: &Config) -> Result<Vec<Library>, String> {
+    if !is_valid_name(&config.library_name) {
+        return Err(format!(
+            "The library name {:?} may only contain a-z, 0-9, - and _",
+            config.library_name
+        ));
+    }
+    let mut libraries = vec![Library {
+        name: config.library_name.clone(),
+        path: config.libraries_path.clone(),
+        read_only: config.library_read_only,
+        uploads: config.library_uploads && !config.library_read_only,
+        asset_prefix: config.library_asset_prefix_of(None),
+        main: true,
+    }];
+
+    for entry in config
+        .libraries
+        .iter()
+        .filter(|entry| !entry.trim().is_empty())
+    {
+        let library = parse_entry(config, entry)?;
+        if let Some(other) = libraries
+            .iter()
+            .find(|other| other.name == library.name || nested(&other.path, &library.path))
+        {
+            return Err(format!(
+                "The libraries {} and {} have the same name or are nested",
+                other.name, library.name
+            ));
+        }
+        libraries.push(library);
+    }
+    Ok(libraries)
+}
+
+fn nested(a: &Path, b: &Path) -> bool {
+    a.starts_with(b) || b.starts_with(a)
+}
+
+impl Config {
+    /// URL prefix of the files of a library, also for libraries which are not configured anymore
+    pub fn library_asset_prefix_of(&self, key: Option<&str>) -> String {
+        match key {
+            None => self.asset_prefix.clone(),
+            Some(name) => format!("{}/{}", self.library_asset_prefix, name),
+        }
+    }
+
+    pub fn main_library(&self) -> &Library {
+        &self.library_roots[0]
+    }
+
+    /// Library by the value of the library column, None is the main library
+    pub fn library(&self, key: Option<&str>) -> Option<&Library> {
+        match key {
+            None => Some(self.main_library()),
+            Some(name) => self
+                .library_roots
+                .iter()
+                .find(|library| !library.main && library.name == name),
+        }
+    }
+
+    pub fn library_named(&self, name: &str) -> Option<&Library> {
+        self.library_roots
+            .iter()
+            .find(|library| library.name == name)
+    }
+
+    /// Library containing `dir`
+    pub fn library_of(&self, dir: &Path) -> Option<&Library> {
+        self.library_roots
+            .iter()
+            .find(|library| dir.starts_with(&library.path))
+    }
+
+    pub fn libraries(&self) -> &[Library] {
+        &self.library_roots
+    }
+}
+
+impl Model3D {
+    pub fn library<'a>(&self, config: &'a Config) -> anyhow::Result<&'a Library> {
+        config.library(self.library.as_deref()).ok_or_else(|| {
+            anyhow::anyhow!(
+                "The library {} of {} is not configured",
+                self.library.as_deref().unwrap_or_default(),
+                self.title
+            )
+        })
+    }
+
+    /// The library of the model if its files may be changed
+    pub fn writable_library<'a>(&self, config: &'a Config) -> Result<&'a Library, AppError> {
+        let library = self
+            .library(config)
+            .map_err(|e| AppError::conflict(e.to_string()))?;
+        library.ensure_writable()?;
+        Ok(library)
+    }
+}
+
+/// Library of an upload or adoption by name, the main library if none is given
+pub fn requested_library<'a>(
+    config: &'a Config,
+    name: Option<&str>,
+) -> Result<&'a Library, AppError> {
+    match name.map(str::trim).filter(|name| !name.is_empty()) {
+        None => Ok(config.main_library()),
+        Some(name) => config
+            .library_named(name)
+            .ok_or_else(|| AppError::unprocessable(format!("There is no library {}", name))),
+    }
+}
+
+/// Warns about models of libraries which were removed from the configuration
+pub async fn check_stored_libraries<Conn>(config: &Config, connection: &mut Conn)
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let stored = models3d::table
+        .filter(models3d::library.is_not_null())
+        .group_by(models3d::library)
+        .select((models3d::library, diesel::dsl::count_star()))
+        .load::<(Option<String>, i64)>(connection)
+        .await;
+    match stored {
+        Ok(stored) => {
+            for (name, count) in stored {
+                if config.library(name.as_deref()).is_none() {
+                    warn!(
+                        "{} models belong to the library {} which is not configured, the next refresh removes them",
+                        count,
+                        name.unwrap_or_default()
+                    );
+                }
+            }
+        }
+        Err(e) => warn!("Failed to check the libraries of the models: {}", e),
+    }
+}
+
+pub async fn list_libraries(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<LibraryResponse>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let counts: HashMap<Option<String>, i64> = models3d::table
+        .group_by(models3d::library)
+        .select((models3d::library, diesel::dsl::count_star()))
+        .load::<(Option<String>, i64)>(&mut connection)
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?
+        .into_iter()
+        .collect();
+
+    Ok(Json(
+        state
+            .config
+            .libraries()
+            .iter()
+            .map(|library| LibraryResponse {
+                name: library.name.clone(),
+                read_only: library.read_only,
+                uploads: library.uploads,
+                asset_prefix: library.asset_prefix.clone(),
+                main: library.main,
+                model_count: counts
+                    .get(&library.key().map(str::to_string))
+                    .copied()
+                    .unwrap_or(0) as i32,
+            })
+            .collect(),
+    ))
+}
+
+/// Filter of the models in the library `name`, matches nothing for unknown names
+pub fn in_library(
+    config: &Config,
+    name: &str,
+) -> Box<
+    dyn BoxableExpression<
+        models3d::table,
+        diesel::sqlite::Sqlite,
+        SqlType = diesel::sql_types::Bool,
+    >,
+> {
+    match config.library_named(name) {
+        Some(library) if library.main => Box::new(models3d::library.is_null()),
+        Some(library) => Box::new(models3d::library.is(library.name.clone())),
+        None => Box::new(models3d::id.lt(0)),
+    }
+}
@@ -0,0 +1,279 @@
+use axum::{extract::State, Json};
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::collections::BTreeMap;
+
+use crate::error::AppError;
+use crate::schema::models3d;
+use crate::types::{LicenseFacet, LicenseInfo, ModelReference, UnknownLicenseGroup};
+use crate::AppState;
+
+/// Spellings of the Creative Commons elements, replaced before the text is split into tokens
+const CC_WORDS: &[(&str, &str)] = &[
+    ("creative commons", " cc "),
+    ("creativecommons", " cc "),
+    ("non-commercial", " nc "),
+    ("non commercial", " nc "),
+    ("noncommercial", " nc "),
+    ("share-alike", " sa "),
+    ("share alike", " sa "),
+    ("sharealike", " sa "),
+    ("no-derivatives", " nd "),
+    ("no derivatives", " nd "),
+    ("noderivatives", " nd "),
+    ("no-derivs", " nd "),
+    ("no derivs", " nd "),
+    ("noderivs", " nd "),
+    ("attribution", " by "),
+];
+
+/// Words which do not change the meaning of a license name
+const FILLER_WORDS: &[&str] = &[
+    "license",
+    "licence",
+    "international",
+    "unported",
+    "generic",
+    "version",
+];
+
+/// Replacements for the long names of licenses after spaces and punctuation are removed
+const LONG_NAMES: &[(&str, &str)] = &[
+    ("gnu", ""),
+    ("afferogeneralpublic", "agpl"),
+    ("lessergeneralpublic", "lgpl"),
+    ("librarygeneralpublic", "lgpl"),
+    ("generalpublic", "gpl"),
+    ("mozillapublic", "mpl"),
+];
+
+fn info(spdx: String, non_commercial: bool, share_alike: bool, attribution: bool) -> LicenseInfo {
+    LicenseInfo {
+        spdx,
+        non_commercial,
+        share_alike,
+        attribution,
+    }
+}
+
+/// "4", "40" or "4.0" as "4.0"
+fn parse_version(version: &str) -> Option<String> {
+    let digits: String = version.chars().filter(|c| *c != '.').collect();
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    match digits.len() {
+        1 => Some(format!("{}.0", digits)),
+        2 => Some(format!("{}.{}", &digits[..1], &digits[1..])),
+        _ => None,
+    }
+}
+
+fn normalize_creative_commons(text: &str) -> Option<LicenseInfo> {
+    let mut words = text.to_lowercase();
+    for (spelling, replacement) in CC_WORDS {
+        words = words.replace(spelling, replacement);
+    }
+    for filler in FILLER_WORDS {
+        words = words.replace(filler, " ");
+    }
+
+    let tokens: Vec<&str> = words
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '.')
+        .map(|token| token.trim_matches('.'))
+        .filter(|token| !token.is_empty())
+        .collect();
+    let has = |element: &str| tokens.contains(&element);
+
+    if has("cc0") || (has("cc") && has("zero")) {
+        return Some(info("CC0-1.0".to_string(), false, false, false));
+    }
+
+    let (nc, sa, nd) = (has("nc"), has("sa"), has("nd"));
+    let is_cc = has("cc") && (has("by") || nc || sa || nd);
+    if !is_cc && !(has("by") && (nc || sa || nd)) {
+        return None;
+    }
+    if sa && nd {
+        return None;
+    }
+
+    // Creative Commons versions, a missing version means the current one
+    let version = tokens
+        .iter()
+        .filter_map(|token| parse_version(token.trim_start_matches('v')))
+        .find(|version| ["1.0", "2.0", "2.5", "3.0", "4.0"].contains(&version.as_str()))
+        .unwrap_or_else(|| "4.0".to_string());
+
+    let mut spdx = "CC-BY".to_string();
+    if nc {
+        spdx.push_str("-NC");
+    }
+    if sa {
+        spdx.push_str("-SA");
+    } else if nd {
+        spdx.push_str("-ND");
+    }
+    spdx.push('-');
+    spdx.push_str(&version);
+
+    Some(info(spdx, nc, sa, true))
+}
+
+fn normalize_other(text: &str) -> Option<LicenseInfo> {
+    let mut compact = text.to_lowercase();
+    for filler in FILLER_WORDS {
+        compact = compact.replace(filler, "");
+    }
+    let mut compact: String = compact
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '+')
+        .collect();
+    for (long_name, short_name) in LONG_NAMES {
+        compact = compact.replace(long_name, short_name);
+    }
+
+    match compact.as_str() {
+        "mit" => return Some(info("MIT".to_string(), false, false, true)),
+        "0bsd" => return Some(info("0BSD".to_string(), false, false, false)),
+        "bsd2clause" | "simplifiedbsd" => {
+            return Some(info("BSD-2-Clause".to_string(), false, false, true))
+        }
+        "bsd3clause" | "newbsd" | "revisedbsd" => {
+            return Some(info("BSD-3-Clause".to_string(), false, false, true))
+        }
+        "unlicense" => return Some(info("Unlicense".to_string(), false, false, false)),
+        _ => {}
+    }
+
+    let (rest, or_later) = if let Some(rest) = compact.strip_suffix("orlater") {
+        (rest, true)
+    } else if let Some(rest) = compact.strip_suffix('+') {
+        (rest, true)
+    } else {
+        (compact.strip_suffix("only").unwrap_or(&compact), false)
+    };
+    let name = rest.trim_end_matches(|c: char| c.is_ascii_digit());
+    let version = parse_version(&rest[name.len()..]);
+    let name = name.strip_suffix('v').unwrap_or(name);
+
+    // the GNU licenses are only recognized with a version, the others default to the current one
+    let gnu = |versions: &[&str], spdx_name: &str| {
+        let version = version.clone().filter(|v| versions.contains(&v.as_str()))?;
+        let suffix = if or_later { "or-later" } else { "only" };
+        Some(info(
+            format!("{}-{}-{}", spdx_name, version, suffix),
+            false,
+            true,
+            true,
+        ))
+    };
+    let fixed = |default: &str, spdx: &str, share_alike: bool| {
+        let matches = version.as_deref().is_none_or(|v| v == default);
+        matches.then(|| info(spdx.to_string(), false, share_alike, true))
+    };
+
+    match name {
+        "gpl" => gnu(&["2.0", "3.0"], "GPL"),
+        "lgpl" => gnu(&["2.1", "3.0"], "LGPL"),
+        "agpl" => gnu(&["3.0"], "AGPL"),
+        "apache" => fixed("2.0", "Apache-2.0", false),
+        "mpl" => fixed("2.0", "MPL-2.0", true),
+        "cernohlp" => fixed("2.0", "CERN-OHL-P-2.0", false),
+        "cernohlw" => fixed("2.0", "CERN-OHL-W-2.0", true),
+        "cernohls" => fixed("2.0", "CERN-OHL-S-2.0", true),
+        "cernohl" => fixed("1.2", "CERN-OHL-1.2", true),
+        "taprohl" | "tapr" => fixed("1.0", "TAPR-OHL-1.0", true),
+        _ => None,
+    }
+}
+
+/// Maps the license text of a ModelPack to an SPDX identifier, `None` if it is not known
+pub fn normalize_license(text: &str) -> Option<LicenseInfo> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    normalize_creative_commons(text).or_else(|| normalize_other(text))
+}
+
+/// Counts of the licenses of `(license_spdx, license)` rows, unknown licenses are listed with their original text
+pub fn count_licenses(licenses: Vec<(Option<String>, Option<String>)>) -> Vec<LicenseFacet> {
+    let mut counts: BTreeMap<(String, bool), i32> = BTreeMap::new();
+    for (spdx, license) in licenses {
+        let key = match (spdx, license) {
+            (Some(spdx), _) => (spdx, true),
+            (None, Some(license)) if !license.trim().is_empty() => (license, false),
+            _ => continue,
+        };
+        *counts.entry(key).or_default() += 1;
+    }
+
+    let mut facets: Vec<LicenseFacet> = counts
+        .into_iter()
+        .map(|((license, known), count)| LicenseFacet {
+            license,
+            known,
+            count,
+        })
+        .collect();
+    facets.sort_by(|a, b| b.count.cmp(&a.count).then(a.license.cmp(&b.license)));
+    facets
+}
+
+pub async fn license_facets<Conn>(connection: &mut Conn) -> anyhow::Result<Vec<LicenseFacet>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let licenses = models3d::table
+        .select((models3d::license_spdx, models3d::license))
+        .load::<(Option<String>, Option<String>)>(connection)
+        .await?;
+    Ok(count_licenses(licenses))
+}
+
+pub async fn list_licenses(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<LicenseFacet>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let facets = license_facets(&mut connection)
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?;
+    Ok(Json(facets))
+}
+
+/// Models whose license could not be mapped, grouped by the license text
+pub async fn list_unknown_licenses(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<UnknownLicenseGroup>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let models = models3d::table
+        .filter(models3d::license_spdx.is_null())
+        .select((
+            models3d::id,
+            models3d::title,
+            models3d::name,
+            models3d::license,
+        ))
+        .order(models3d::title.asc())
+        .load::<(i32, String, String, Option<String>)>(&mut connection)
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?;
+
+    let mut groups: BTreeMap<String, Vec<ModelReference>> = BTreeMap::new();
+    for (id, title, name, license) in models {
+        groups
+            .entry(license.unwrap_or_default().trim().to_string())
+            .or_default()
+            .push(ModelReference { id, title, name });
+    }
+
+    Ok(Json(
+        groups
+            .into_iter()
+            .map(|(license, models)| UnknownLicenseGroup { license, models })
+            .collect(),
+    ))
+}
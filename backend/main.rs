@@ -1,12 +1,12 @@
 use axum::http::StatusCode;
-use axum::response::Response;
+use axum::response::{Redirect, Response};
 use axum::{
     body::Body,
     extract::Path,
     extract::{DefaultBodyLimit, Query, State},
     response::IntoResponse,
-    routing::{get, post},
-    Router,
+    routing::{any, get},
+    Extension, Router,
 };
 use axum::{http, Json};
 use diesel::prelude::*;
@@ -15,39 +15,82 @@ use diesel::sqlite::SqliteConnection;
 use diesel_async::pooled_connection::bb8::Pool;
 use diesel_async::pooled_connection::AsyncDieselConnectionManager;
 use diesel_async::pooled_connection::ManagerConfig;
+use diesel_async::scoped_futures::ScopedFutureExt;
 use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
 use diesel_async::{AsyncConnection, RunQueryDsl};
 use diesel_migrations::MigrationHarness;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations};
-use http::header::{self};
+use http::header::{self, HeaderValue};
+use http::Method;
 use schema::files3d::{self};
 use serde_derive::{Deserialize, Serialize};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::str::FromStr;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::CorsLayer;
 use tower_http::services::{ServeDir, ServeFile};
 use tracing::{debug, error, info};
 use tracing_subscriber::EnvFilter;
-use types::{DetailedModelResponse, FileType, ModelResponseList};
-
+use types::{DetailedModelResponse, DownloadParams, FileType, ModelResponseList};
+
+pub mod adopt;
+pub mod audit;
+pub mod auth;
+pub mod authors;
+pub mod bulk;
+pub mod cli;
 pub mod convert;
+pub mod duplicates;
+pub mod error;
+pub mod facets;
+pub mod libraries;
+pub mod licenses;
+pub mod metadata;
+pub mod openapi;
 pub mod parse_library;
+pub mod prints;
+pub mod proxy_auth;
+pub mod revisions;
 pub mod schema;
+pub mod share;
+pub mod slug;
 pub mod stream_dl;
+pub mod tokens;
+pub mod trash;
 pub mod types;
 pub mod upload;
+pub mod v2;
+pub mod validation;
+use crate::audit::AuditTarget;
+use crate::auth::{Access, CurrentUser};
+use crate::error::AppError;
+use crate::openapi::{ApiRouter, FileBody, OrRedirect};
 use crate::schema::{collections, model_collections, models3d};
+use crate::slug::SlugMatch;
+use crate::stream_dl::ZipArchive;
 use crate::types::File3D;
 use crate::types::ListModelParams;
 use crate::types::Model3D;
 use crate::types::{
-    AddModelToCollectionRequest, Collection, CollectionResponse, DetailedCollectionResponse,
-    NewCollection, NewModelCollection, UpdateCollectionRequest,
+    AddModelToCollectionRequest, AuditAction, AuditTargetType, Collection, CollectionResponse,
+    CollectionVisibility, CreateCollectionRequest, DetailedCollectionResponse, NewCollection,
+    NewModelCollection, PatchCollectionRequest, Role, UpdateCollectionRequest,
 };
+use crate::v2::IfMatch;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Config {
+    /// The main library, uploads go there unless another library is chosen
     libraries_path: PathBuf,
+    #[serde(default = "default_library_name")]
+    library_name: String,
+    #[serde(default)]
+    library_read_only: bool,
+    #[serde(default = "default_library_uploads")]
+    library_uploads: bool,
+    /// Further libraries as name=path, optionally followed by :read_only and :no_upload
+    #[serde(default)]
+    libraries: Vec<String>,
     #[serde(default = "default_log_level")]
     log_level: String,
     data_dir: PathBuf,
@@ -57,15 +100,64 @@ pub struct Config {
     port: String,
     #[serde(default = "default_asset_prefix")]
     asset_prefix: String,
+    /// Files of the further libraries are served under it followed by the library name
+    #[serde(default = "default_library_asset_prefix")]
+    library_asset_prefix: String,
     #[serde(default = "default_cache_prefix")]
     cache_prefix: String,
+    #[serde(default = "default_max_upload_file_size_mb")]
+    max_upload_file_size_mb: u64,
+    #[serde(default = "default_max_upload_request_size_mb")]
+    max_upload_request_size_mb: u64,
+    #[serde(default = "default_upload_allowed_extensions")]
+    upload_allowed_extensions: Vec<String>,
+    /// Days until deleted models and files are purged from the trash, 0 keeps them forever
+    #[serde(default = "default_trash_retention_days")]
+    trash_retention_days: u64,
+    /// Without it every request is made as a local admin, e.g. behind an authenticating proxy
+    #[serde(default = "default_auth_enabled")]
+    auth_enabled: bool,
+    /// Admin created on startup if no user with this name exists yet
+    admin_username: Option<String>,
+    #[serde(skip_serializing)]
+    admin_password: Option<String>,
+    #[serde(default = "default_session_lifetime_days")]
+    session_lifetime_days: u64,
+    #[serde(default)]
+    session_cookie_secure: bool,
+    /// Browsing without a login, every route changing something returns 403
+    #[serde(default)]
+    read_only: bool,
+    /// Origins allowed to make requests with credentials, e.g. the vite dev server
+    #[serde(default)]
+    cors_allowed_origins: Vec<String>,
+    /// Addresses or CIDR ranges of reverse proxies whose identity headers are trusted
+    #[serde(default)]
+    trusted_proxies: Vec<String>,
+    #[serde(default = "default_proxy_user_header")]
+    proxy_user_header: String,
+    #[serde(default = "default_proxy_email_header")]
+    proxy_email_header: String,
+    /// Comma separated groups of the user
+    #[serde(default = "default_proxy_groups_header")]
+    proxy_groups_header: String,
+    /// Roles of proxy groups as group:role, e.g. meshvault-admins:admin
+    #[serde(default)]
+    proxy_group_roles: Vec<String>,
+    /// Role of proxy users in none of the mapped groups
+    #[serde(default = "default_proxy_default_role")]
+    proxy_default_role: Role,
     #[serde(skip_deserializing)]
     database_url: PathBuf,
     #[serde(skip_deserializing)]
     upload_cache: PathBuf,
     #[serde(skip_deserializing)]
+    library_roots: Vec<libraries::Library>,
+    #[serde(skip_deserializing)]
     preview_cache_dir: PathBuf,
     #[serde(skip_deserializing)]
+    revisions_dir: PathBuf,
+    #[serde(skip_deserializing)]
     address: String,
 }
 
@@ -81,20 +173,89 @@ fn default_log_level() -> String {
     "info".to_string()
 }
 
+fn default_library_name() -> String {
+    "main".to_string()
+}
+
+fn default_library_uploads() -> bool {
+    true
+}
+
 fn default_asset_prefix() -> String {
     "/3d".to_string()
 }
 
+fn default_library_asset_prefix() -> String {
+    "/libraries".to_string()
+}
+
 fn default_cache_prefix() -> String {
     "/cache".to_string()
 }
 
+fn default_max_upload_file_size_mb() -> u64 {
+    4096
+}
+
+fn default_max_upload_request_size_mb() -> u64 {
+    16384
+}
+
+fn default_upload_allowed_extensions() -> Vec<String> {
+    upload::MESH_FILE_FORMATS
+        .iter()
+        .chain(upload::CAD_FILE_FORMATS)
+        .chain(upload::IMAGE_FILE_FORMATS)
+        .chain(&["json", "md", "txt"])
+        .map(|ext| ext.to_string())
+        .collect()
+}
+
+fn default_trash_retention_days() -> u64 {
+    30
+}
+
+fn default_auth_enabled() -> bool {
+    true
+}
+
+fn default_session_lifetime_days() -> u64 {
+    30
+}
+
+fn default_proxy_user_header() -> String {
+    "Remote-User".to_string()
+}
+
+fn default_proxy_email_header() -> String {
+    "Remote-Email".to_string()
+}
+
+fn default_proxy_groups_header() -> String {
+    "Remote-Groups".to_string()
+}
+
+fn default_proxy_default_role() -> Role {
+    Role::Viewer
+}
+
 impl Config {
-    fn initialize(&mut self) {
+    fn max_upload_file_size(&self) -> u64 {
+        self.max_upload_file_size_mb * 1024 * 1024
+    }
+
+    fn max_upload_request_size(&self) -> u64 {
+        self.max_upload_request_size_mb * 1024 * 1024
+    }
+
+    fn initialize(&mut self) -> Result<(), String> {
         self.database_url = self.data_dir.join("db.sqlite3");
         self.preview_cache_dir = self.data_dir.join("preview_cache");
+        self.revisions_dir = self.data_dir.join("revisions");
         self.address = format!("{}:{}", self.host, self.port);
         self.upload_cache = self.data_dir.join("upload_cache");
+        self.library_roots = libraries::parse_libraries(self)?;
+        Ok(())
     }
 }
 
@@ -103,7 +264,9 @@ fn parse_config() -> Config {
         Result::Ok(config) => config,
         Err(error) => panic!("{:#?}", error),
     };
-    init_config.initialize();
+    if let Err(error) = init_config.initialize() {
+        panic!("{}", error);
+    }
     init_config
 }
 
@@ -119,373 +282,502 @@ async fn healthz() -> impl IntoResponse {
 
 async fn get_model_by_slug(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path(slug): Path<String>,
-) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
-
-    let result = models3d::dsl::models3d
-        .filter(models3d::dsl::name.eq(slug))
-        .first::<Model3D>(&mut connection)
-        .await
-        .unwrap();
-    let response = DetailedModelResponse::from_model_3d(&result, &state.config, &mut connection)
-        .await
-        .unwrap();
-    (StatusCode::OK, Json(response))
+) -> Result<OrRedirect<Json<DetailedModelResponse>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let result = match slug::find_model_by_slug(&mut connection, &slug).await? {
+        Some(SlugMatch::Current(model)) => model,
+        // old links keep working after the slug changed, temporarily as the slug can be taken again
+        Some(SlugMatch::Previous(model)) => {
+            return Ok(OrRedirect::Redirect(Redirect::temporary(&format!(
+                "/api/model/{}",
+                model.name
+            ))))
+        }
+        None => return Err(AppError::not_found(format!("No model with slug {}", slug))),
+    };
+    let response =
+        DetailedModelResponse::from_model_3d(&result, &state.config, user.id, &mut connection)
+            .await?;
+    Ok(OrRedirect::Response(Json(response)))
 }
 
 async fn refresh_model(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path(slug): Path<String>,
-) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
+) -> Result<Json<DetailedModelResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
 
-    let result = models3d::dsl::models3d
-        .filter(models3d::dsl::name.eq(slug.clone()))
-        .first::<Model3D>(&mut connection)
-        .await
-        .unwrap();
-    result.scan(&state.config, &mut connection).await;
+    let result = slug::resolve_model(&mut connection, &slug).await?;
+    result.scan(&state.config, &mut connection).await?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Refresh,
+        AuditTarget::model(&result),
+        "Rescanned the model folder",
+    )
+    .await;
 
     let reloaded_result = models3d::dsl::models3d
-        .filter(models3d::dsl::name.eq(slug))
+        .find(result.id)
         .first::<Model3D>(&mut connection)
-        .await
-        .unwrap();
-    let response =
-        DetailedModelResponse::from_model_3d(&reloaded_result, &state.config, &mut connection)
-            .await
-            .unwrap();
-    (StatusCode::OK, Json(response))
+        .await?;
+    let response = DetailedModelResponse::from_model_3d(
+        &reloaded_result,
+        &state.config,
+        user.id,
+        &mut connection,
+    )
+    .await?;
+    Ok(Json(response))
 }
 
 async fn delete_model(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path(slug): Path<String>,
-) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
-
-    let result = models3d::dsl::models3d
-        .filter(models3d::dsl::name.eq(slug))
-        .first::<Model3D>(&mut connection)
-        .await
-        .unwrap();
-    match result.delete(&state.config, &mut connection).await {
-        Ok(_) => StatusCode::OK,
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
-    }
+    if_match: IfMatch,
+) -> Result<StatusCode, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let result = slug::resolve_model(&mut connection, &slug).await?;
+    result.writable_library(&state.config)?;
+    if_match.check_model(&mut connection, result.id).await?;
+    trash::move_model_to_trash(&state.config, &mut connection, &result, &if_match).await?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Delete,
+        AuditTarget::model(&result),
+        format!("Moved {} to the trash", result.title),
+    )
+    .await;
+    Ok(StatusCode::OK)
 }
 
-async fn delete_file(State(state): State<AppState>, Path(pk): Path<i32>) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
-
-    let result = files3d::dsl::files3d
-        .filter(files3d::dsl::id.eq(pk))
-        .first::<File3D>(&mut connection)
+async fn find_file<Conn>(connection: &mut Conn, id: i32) -> Result<File3D, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    files3d::dsl::files3d
+        .find(id)
+        .first::<File3D>(connection)
         .await
-        .unwrap();
-    match result.delete(&state.config, &mut connection).await {
-        Ok(_) => StatusCode::OK,
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
-    }
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("File {} not found", id)))
 }
 
-async fn toggle_like(State(state): State<AppState>, Path(slug): Path<String>) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
-
-    let model = models3d::dsl::models3d
-        .filter(models3d::dsl::name.eq(slug))
-        .first::<Model3D>(&mut connection)
-        .await
-        .unwrap();
-
-    let new_favourite = !model.favourite;
-    diesel::update(models3d::dsl::models3d.filter(models3d::dsl::id.eq(model.id)))
-        .set(models3d::dsl::favourite.eq(new_favourite))
-        .execute(&mut connection)
-        .await
-        .unwrap();
+async fn delete_file(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(pk): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let result = find_file(&mut connection, pk).await?;
+    let model = result.get_model(&mut connection).await?;
+    model.writable_library(&state.config)?;
+    result.delete(&state.config, &mut connection).await?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Delete,
+        AuditTarget::new(AuditTargetType::File, result.id, &result.file_path),
+        format!(
+            "Moved {} of model {} to the trash",
+            result.file_path, result.model_id
+        ),
+    )
+    .await;
+    Ok(StatusCode::OK)
+}
 
-    (
-        StatusCode::OK,
-        Json(serde_json::json!({"favourite": new_favourite})),
+async fn toggle_like(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(slug): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let model = slug::resolve_model(&mut connection, &slug).await?;
+
+    let new_favourite = !model.is_favourite_of(user.id, &mut connection).await?;
+    model
+        .set_favourite(user.id, new_favourite, &mut connection)
+        .await?;
+    let (action, summary) = if new_favourite {
+        (AuditAction::Favourite, "Added to the favourites")
+    } else {
+        (AuditAction::Unfavourite, "Removed from the favourites")
+    };
+    audit::record(
+        &mut connection,
+        &user,
+        action,
+        AuditTarget::model(&model),
+        summary,
     )
+    .await;
+
+    Ok(Json(serde_json::json!({"favourite": new_favourite})))
 }
 
 async fn convert_file(
     State(state): State<AppState>,
     Path((pk, target_type)): Path<(i32, String)>,
-) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
+) -> Result<FileBody, AppError> {
+    let mut connection = state.pool.get().await?;
 
-    let result = files3d::dsl::files3d
-        .filter(files3d::dsl::id.eq(pk))
-        .first::<File3D>(&mut connection)
-        .await
-        .unwrap();
+    let result = find_file(&mut connection, pk).await?;
 
-    let mut file_ending = "";
-    let buffer = match FileType::from_str(&target_type) {
-        Ok(FileType::STL) => match result.to_stl(&mut connection, &state.config).await {
-            Ok(buffer) => {
-                file_ending = "stl";
-                buffer
-            }
-            Err(e) => {
-                debug!("Error converting file: {}", e);
-                return Err(StatusCode::UNPROCESSABLE_ENTITY);
-            }
-        },
-        Ok(FileType::THREEMF) => match result.to_threemf(&mut connection, &state.config).await {
-            Ok(buffer) => {
-                file_ending = "3mf";
-                buffer
-            }
-            Err(e) => {
-                debug!("Error converting file: {}", e);
-                return Err(StatusCode::UNPROCESSABLE_ENTITY);
-            }
-        },
-        Ok(FileType::IGES) => match result.to_iges(&mut connection, &state.config).await {
-            Ok(buffer) => {
-                file_ending = "iges";
-                buffer
-            }
-            Err(e) => {
-                debug!("Error converting file: {}", e);
-                return Err(StatusCode::UNPROCESSABLE_ENTITY);
-            }
-        },
-        Ok(FileType::STEP) => match result.to_step(&mut connection, &state.config).await {
-            Ok(buffer) => {
-                file_ending = "step";
-                buffer
-            }
-            Err(e) => {
-                debug!("Error converting file: {}", e);
-                return Err(StatusCode::UNPROCESSABLE_ENTITY);
-            }
-        },
-        _ => return Err(StatusCode::UNPROCESSABLE_ENTITY),
+    let (converted, file_ending) = match FileType::from_str(&target_type) {
+        Ok(FileType::STL) => (result.to_stl(&mut connection, &state.config).await, "stl"),
+        Ok(FileType::THREEMF) => (
+            result.to_threemf(&mut connection, &state.config).await,
+            "3mf",
+        ),
+        Ok(FileType::IGES) => (result.to_iges(&mut connection, &state.config).await, "iges"),
+        Ok(FileType::STEP) => (result.to_step(&mut connection, &state.config).await, "step"),
+        _ => {
+            return Err(AppError::unprocessable(format!(
+                "Files can not be converted to {}",
+                target_type
+            )))
+        }
     };
+    let buffer = converted.map_err(|e| {
+        debug!("Error converting file: {}", e);
+        AppError::unprocessable(format!("The file can not be converted: {}", e))
+    })?;
     let body = Body::from(buffer);
 
-    let response = Response::builder()
+    Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, "application/octet-stream")
         .header(
             header::CONTENT_DISPOSITION,
             format!(
                 "attachment; filename=\"{}.{}\"",
-                result.get_file_name().await.unwrap(),
+                result.get_file_name().await.unwrap_or_default(),
                 file_ending
             )
             .as_str(),
         )
         .body(body)
-        .unwrap();
-
-    Ok(response)
+        .map(FileBody)
+        .map_err(AppError::internal)
 }
 
-async fn handle_refresh(State(state): State<AppState>) -> impl IntoResponse {
-    parse_library::refresh_library(state.pool, state.config.clone())
-        .await
-        .unwrap();
+async fn handle_refresh(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+) -> Result<(StatusCode, String), AppError> {
+    parse_library::refresh_library(state.pool.clone(), state.config.clone()).await?;
 
-    (StatusCode::OK, "Done".to_string())
+    let mut connection = state.pool.get().await?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Refresh,
+        AuditTarget::all(AuditTargetType::Library),
+        "Rescanned the library",
+    )
+    .await;
+
+    Ok((StatusCode::OK, "Done".to_string()))
 }
 
 async fn list_models(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Query(params): Query<ListModelParams>,
-) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
-
-    let mut models = models3d::dsl::models3d.into_boxed();
-
-    if let Some(ref q) = params.q {
-        let pattern = format!("%{}%", q);
-        models = models.filter(
-            models3d::dsl::name
-                .like(pattern.clone())
-                .or(models3d::dsl::title.like(pattern.clone()))
-                .or(models3d::dsl::description.like(pattern.clone()))
-                .or(models3d::dsl::author.like(pattern.clone())),
-        );
-    }
-
-    if let Some(ref author) = params.author {
-        let pattern = format!("%{}%", author);
-        models = models.filter(models3d::dsl::author.like(pattern));
-    }
-
-    if let Some(licenses) = params.licenses {
-        let split_licenses: Vec<Option<String>> = licenses
-            .clone()
-            .split(',')
-            .map(|s| Some(s.to_string()))
-            .collect();
-        for (i, license) in split_licenses.iter().enumerate() {
-            debug!("Filtering models with license: {:?}", license.clone());
-            if i == 0 {
-                models = models.filter(models3d::dsl::license.eq(license.clone()));
-            } else {
-                models = models.or_filter(models3d::dsl::license.eq(license.clone()));
-            }
-        }
-    }
-
-    if let Some(favourite) = params.favourite {
-        models = models.filter(models3d::dsl::favourite.eq(favourite));
-    }
-
-    let licenses_to_select: Vec<String> = models3d::dsl::models3d
-        .select(models3d::dsl::license)
-        .distinct()
-        .filter(models3d::dsl::license.ne(""))
-        .load::<Option<String>>(&mut connection)
-        .await
-        .unwrap()
-        .into_iter()
-        .filter_map(|license| license)
-        .collect();
-
+) -> Result<Json<ModelResponseList>, AppError> {
     let page = params.page.unwrap_or(1);
     let page_size = params.page_size.unwrap_or(100);
+    if page < 1 || page_size < 1 {
+        return Err(AppError::bad_request(
+            "page and page_size have to be at least 1",
+        ));
+    }
     let offset = (page - 1) * page_size;
 
-    models = models.limit(page_size).offset(offset);
+    let mut connection = state.pool.get().await?;
+
+    let facets = facets::model_facets(&mut connection, &state.config, &params, &user).await?;
+    let total: i64 = facets::filter_models(&state.config, &params, &user)
+        .count()
+        .get_result(&mut connection)
+        .await?;
+
+    let models = facets::filter_models(&state.config, &params, &user)
+        .limit(page_size)
+        .offset(offset);
 
     let response = ModelResponseList::from_model_3d(
-        models.load::<Model3D>(&mut connection).await.unwrap(),
-        licenses_to_select,
+        models.load::<Model3D>(&mut connection).await?,
+        total as i32,
+        facets,
         &state.config,
+        user.id,
         &mut connection,
     )
-    .await
-    .unwrap();
+    .await?;
 
-    (StatusCode::OK, Json(response))
+    Ok(Json(response))
 }
 
 async fn handle_zip_download(
     State(state): State<AppState>,
     Path(folder_path): Path<String>,
-) -> impl IntoResponse {
-    let mut path = state.config.libraries_path.clone();
-    path.push(folder_path);
-    stream_dl::zip_folder_stream(path, &state.config).await
+    Query(params): Query<DownloadParams>,
+) -> Result<ZipArchive, AppError> {
+    let library = libraries::requested_library(&state.config, params.library.as_deref())?;
+    stream_dl::zip_folder_stream(&library.path, std::path::Path::new(&folder_path)).await
 }
 
 // ============ Collections Handlers ============
 
-async fn list_collections(State(state): State<AppState>) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
+/// The collection if the user may see it, otherwise a 404 so private collections stay hidden
+async fn visible_collection<Conn>(
+    connection: &mut Conn,
+    user: &CurrentUser,
+    id: i32,
+) -> Result<Collection, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    collections::table
+        .find(id)
+        .first::<Collection>(connection)
+        .await
+        .optional()?
+        .filter(|collection| collection.can_view(user))
+        .ok_or_else(|| AppError::not_found(format!("Collection {} not found", id)))
+}
 
-    let collections = collections::table
+fn forbidden() -> AppError {
+    AppError::forbidden("Only the owner can change this collection")
+}
+
+async fn list_collections(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+) -> Result<Json<Vec<CollectionResponse>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let collections = types::visible_collections(&user)
         .load::<Collection>(&mut connection)
-        .await
-        .unwrap();
+        .await?;
 
     let mut responses: Vec<CollectionResponse> = Vec::new();
     for collection in collections {
-        let response =
-            CollectionResponse::from_collection(&collection, &state.config, &mut connection)
-                .await
-                .unwrap();
+        let response = CollectionResponse::from_collection(
+            &collection,
+            &state.config,
+            user.id,
+            &mut connection,
+        )
+        .await?;
         responses.push(response);
     }
 
-    (StatusCode::OK, Json(responses))
+    Ok(Json(responses))
 }
 
-async fn get_collection(State(state): State<AppState>, Path(id): Path<i32>) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
+async fn get_collection(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(id): Path<i32>,
+) -> Result<Json<DetailedCollectionResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
 
-    let collection = collections::table
-        .filter(collections::id.eq(id))
-        .first::<Collection>(&mut connection)
-        .await
-        .unwrap();
+    let collection = visible_collection(&mut connection, &user, id).await?;
 
-    let response =
-        DetailedCollectionResponse::from_collection(&collection, &state.config, &mut connection)
-            .await
-            .unwrap();
+    let response = DetailedCollectionResponse::from_collection(
+        &collection,
+        &state.config,
+        user.id,
+        &mut connection,
+    )
+    .await?;
 
-    (StatusCode::OK, Json(response))
+    Ok(Json(response))
 }
 
 async fn create_collection(
     State(state): State<AppState>,
-    Json(new_collection): Json<NewCollection>,
-) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
-
+    Extension(user): Extension<CurrentUser>,
+    Json(request): Json<CreateCollectionRequest>,
+) -> Result<(StatusCode, Json<CollectionResponse>), AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let new_collection = NewCollection {
+        name: request.name,
+        owner_id: Some(user.id),
+        visibility: request
+            .visibility
+            .unwrap_or(CollectionVisibility::Private)
+            .as_str()
+            .to_string(),
+    };
     let collection = diesel::insert_into(collections::table)
         .values(&new_collection)
         .returning(Collection::as_returning())
         .get_result(&mut connection)
-        .await
-        .unwrap();
+        .await?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Create,
+        AuditTarget::collection(&collection),
+        format!("Created the {} collection", collection.visibility),
+    )
+    .await;
 
-    let response = CollectionResponse::from_collection(&collection, &state.config, &mut connection)
-        .await
-        .unwrap();
+    let response =
+        CollectionResponse::from_collection(&collection, &state.config, user.id, &mut connection)
+            .await?;
 
-    (StatusCode::CREATED, Json(response))
+    Ok((StatusCode::CREATED, Json(response)))
 }
 
 async fn update_collection(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path(id): Path<i32>,
+    if_match: IfMatch,
     Json(update_req): Json<UpdateCollectionRequest>,
-) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
+) -> Result<Json<CollectionResponse>, AppError> {
+    let request = PatchCollectionRequest {
+        name: Some(update_req.name),
+        visibility: update_req.visibility,
+    };
+    patch_collection(&state, &user, id, &if_match, request).await
+}
 
-    diesel::update(collections::table.filter(collections::id.eq(id)))
-        .set(collections::name.eq(update_req.name))
-        .execute(&mut connection)
-        .await
-        .unwrap();
+/// Checks `if_match` in the transaction that writes the changes
+pub async fn patch_collection(
+    state: &AppState,
+    user: &CurrentUser,
+    id: i32,
+    if_match: &IfMatch,
+    request: PatchCollectionRequest,
+) -> Result<Json<CollectionResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let (previous, collection) = connection
+        .transaction::<_, AppError, _>(|connection| {
+            let request = &request;
+            async move {
+                let collection = visible_collection(connection, user, id).await?;
+                if !collection.can_manage(user) {
+                    return Err(forbidden());
+                }
+                if_match.check_collection(&collection)?;
+
+                let name = request.name.as_ref().unwrap_or(&collection.name);
+                let visibility = request
+                    .visibility
+                    .unwrap_or(collection.visibility())
+                    .as_str();
+                let updated = diesel::update(collections::table.find(id))
+                    .set((
+                        collections::name.eq(name),
+                        collections::visibility.eq(visibility),
+                    ))
+                    .returning(Collection::as_returning())
+                    .get_result(connection)
+                    .await?;
+                Ok((collection, updated))
+            }
+            .scope_boxed()
+        })
+        .await?;
 
-    let collection = collections::table
-        .filter(collections::id.eq(id))
-        .first::<Collection>(&mut connection)
-        .await
-        .unwrap();
+    let mut changes = Vec::new();
+    if previous.name != collection.name {
+        changes.push(format!("name {:?} -> {:?}", previous.name, collection.name));
+    }
+    if previous.visibility != collection.visibility {
+        changes.push(format!(
+            "visibility {} -> {}",
+            previous.visibility, collection.visibility
+        ));
+    }
+    if !changes.is_empty() {
+        audit::record(
+            &mut connection,
+            user,
+            AuditAction::Update,
+            AuditTarget::collection(&collection),
+            changes.join(", "),
+        )
+        .await;
+    }
 
-    let response = CollectionResponse::from_collection(&collection, &state.config, &mut connection)
-        .await
-        .unwrap();
+    let response =
+        CollectionResponse::from_collection(&collection, &state.config, user.id, &mut connection)
+            .await?;
 
-    (StatusCode::OK, Json(response))
+    Ok(Json(response))
 }
 
 async fn delete_collection(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
-
-    let collection = collections::table
-        .filter(collections::id.eq(id))
-        .first::<Collection>(&mut connection)
-        .await
-        .unwrap();
-
-    match collection.delete(&mut connection).await {
-        Ok(_) => StatusCode::OK,
-        Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
-    }
+    if_match: IfMatch,
+) -> Result<StatusCode, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let collection = connection
+        .transaction::<_, AppError, _>(|connection| {
+            let user = &user;
+            async move {
+                let collection = visible_collection(connection, user, id).await?;
+                if !collection.can_manage(user) {
+                    return Err(forbidden());
+                }
+                if_match.check_collection(&collection)?;
+                collection.delete(connection).await?;
+                Ok(collection)
+            }
+            .scope_boxed()
+        })
+        .await?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Delete,
+        AuditTarget::collection(&collection),
+        format!("Deleted the collection {:?}", collection.name),
+    )
+    .await;
+    Ok(StatusCode::OK)
 }
 
 async fn add_model_to_collection(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Json(request): Json<AddModelToCollectionRequest>,
-) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
+) -> Result<StatusCode, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let collection = visible_collection(&mut connection, &user, request.collection_id).await?;
+    if !collection.can_edit_models(&user) {
+        return Err(forbidden());
+    }
+    models3d::table
+        .find(request.model_id)
+        .select(models3d::id)
+        .first::<i32>(&mut connection)
+        .await
+        .optional()?
+        .ok_or_else(|| AppError::not_found(format!("Model {} not found", request.model_id)))?;
 
     let new_model_collection = NewModelCollection {
         model_id: request.model_id,
@@ -500,33 +792,41 @@ async fn add_model_to_collection(
                 .and(model_collections::collection_id.eq(request.collection_id)),
         )
         .first::<types::ModelCollection>(&mut connection)
-        .await;
-
-    match existing {
-        Ok(_) => {
-            // Already exists, return OK
-            StatusCode::OK
-        }
-        Err(_) => {
-            // Doesn't exist, insert it
-            diesel::insert_into(model_collections::table)
-                .values(&new_model_collection)
-                .execute(&mut connection)
-                .await
-                .unwrap();
-
-            StatusCode::CREATED
-        }
+        .await
+        .optional()?;
+    if existing.is_some() {
+        return Ok(StatusCode::OK);
     }
+
+    diesel::insert_into(model_collections::table)
+        .values(&new_model_collection)
+        .execute(&mut connection)
+        .await?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::AddModel,
+        AuditTarget::collection(&collection),
+        format!("Added model {}", request.model_id),
+    )
+    .await;
+
+    Ok(StatusCode::CREATED)
 }
 
 async fn remove_model_from_collection(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path((collection_id, model_id)): Path<(i32, i32)>,
-) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
+) -> Result<StatusCode, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let collection = visible_collection(&mut connection, &user, collection_id).await?;
+    if !collection.can_edit_models(&user) {
+        return Err(forbidden());
+    }
 
-    diesel::delete(
+    let removed = diesel::delete(
         model_collections::table.filter(
             model_collections::model_id
                 .eq(model_id)
@@ -534,41 +834,52 @@ async fn remove_model_from_collection(
         ),
     )
     .execute(&mut connection)
-    .await
-    .unwrap();
+    .await?;
+    if removed > 0 {
+        audit::record(
+            &mut connection,
+            &user,
+            AuditAction::RemoveModel,
+            AuditTarget::collection(&collection),
+            format!("Removed model {}", model_id),
+        )
+        .await;
+    }
 
-    StatusCode::OK
+    Ok(StatusCode::OK)
 }
 
 async fn get_model_collections(
     State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
     Path(model_id): Path<i32>,
-) -> impl IntoResponse {
-    let mut connection = state.pool.get().await.unwrap();
+) -> Result<Json<Vec<CollectionResponse>>, AppError> {
+    let mut connection = state.pool.get().await?;
 
     let collection_ids: Vec<i32> = model_collections::table
         .filter(model_collections::model_id.eq(model_id))
         .select(model_collections::collection_id)
         .load::<i32>(&mut connection)
-        .await
-        .unwrap();
+        .await?;
 
-    let collections = collections::table
+    let collections = types::visible_collections(&user)
         .filter(collections::id.eq_any(collection_ids))
         .load::<Collection>(&mut connection)
-        .await
-        .unwrap();
+        .await?;
 
     let mut responses: Vec<CollectionResponse> = Vec::new();
     for collection in collections {
-        let response =
-            CollectionResponse::from_collection(&collection, &state.config, &mut connection)
-                .await
-                .unwrap();
+        let response = CollectionResponse::from_collection(
+            &collection,
+            &state.config,
+            user.id,
+            &mut connection,
+        )
+        .await?;
         responses.push(response);
     }
 
-    (StatusCode::OK, Json(responses))
+    Ok(Json(responses))
 }
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
@@ -623,54 +934,378 @@ async fn main() {
 
     let pool = create_connection_pool(&config).await;
 
+    if let Err(e) = auth::ensure_initial_admin(&config, &mut pool.get().await.unwrap()).await {
+        error!("Failed to create the admin user: {:?}", e);
+    }
+    proxy_auth::check_config(&config);
+    libraries::check_stored_libraries(&config, &mut pool.get().await.unwrap()).await;
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(e) = cli::run(&args, &config, pool).await {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    tokio::spawn(trash::run_purge_task(pool.clone(), config.clone()));
+
     let app_state = AppState {
         config: config.clone(),
         pool,
     };
 
     let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
-    let api = Router::new()
-        .route("/refresh", post(handle_refresh))
-        .route("/models/list", get(list_models))
-        .route("/model/:slug", get(get_model_by_slug))
-        .route("/model/:slug/refresh", get(refresh_model))
-        .route("/model/:slug/update", post(upload::handle_upload_update))
-        .route("/model/:slug/delete", post(delete_model))
-        .route("/model/:slug/like", post(toggle_like))
-        .route("/file/:id/delete", post(delete_file))
-        .route("/file/:id/convert/:target_type", get(convert_file))
-        .route("/download/:folder", get(handle_zip_download))
-        .route("/upload", post(upload::handle_upload))
+        .allow_origin(
+            config
+                .cors_allowed_origins
+                .iter()
+                .filter_map(|origin| origin.trim().parse::<HeaderValue>().ok())
+                .collect::<Vec<_>>(),
+        )
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PATCH,
+            Method::PUT,
+            Method::DELETE,
+        ])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            header::IF_MATCH,
+        ])
+        .allow_credentials(true);
+
+    let require_user = axum::middleware::from_fn_with_state(app_state.clone(), auth::require_user);
+
+    let api = ApiRouter::new()
+        .post("/refresh", Access::EDIT_FILES, handle_refresh)
+        .get("/models/list", Access::READ, list_models)
+        .get("/libraries", Access::READ, libraries::list_libraries)
+        .get("/model/:slug", Access::READ, get_model_by_slug)
+        .get("/model/:slug/refresh", Access::EDIT_FILES, refresh_model)
+        .post(
+            "/model/:slug/update",
+            Access::EDIT_FILES,
+            upload::handle_upload_update,
+        )
+        .post("/model/:slug/delete", Access::EDIT, delete_model)
+        .post("/model/:slug/like", Access::FAVOURITE, toggle_like)
+        .post("/model/:slug/slug", Access::EDIT, slug::handle_change_slug)
+        .patch(
+            "/model/:slug/metadata",
+            Access::EDIT,
+            metadata::update_metadata,
+        )
+        .get(
+            "/model/:slug/revisions",
+            Access::READ,
+            revisions::list_revisions,
+        )
+        .post(
+            "/model/:slug/revisions/:id/restore",
+            Access::EDIT,
+            revisions::restore_revision,
+        )
+        .post("/file/:id/delete", Access::EDIT, delete_file)
+        .get("/file/:id/convert/:target_type", Access::READ, convert_file)
+        .get("/download/:folder", Access::READ, handle_zip_download)
+        .post("/upload", Access::UPLOAD, upload::handle_upload)
+        .get(
+            "/adopt",
+            Access::READ,
+            adopt::handle_list_adoption_candidates,
+        )
+        .post("/adopt", Access::EDIT_FILES, adopt::handle_adopt)
+        .get("/duplicates", Access::READ, duplicates::list_duplicates)
+        .get("/authors", Access::READ, authors::list_authors)
+        .get("/author/:id", Access::READ, authors::get_author)
+        .post("/author/:id", Access::EDIT, authors::update_author)
+        .post("/author/:id/merge", Access::EDIT, authors::merge_author)
+        .get("/licenses", Access::READ, licenses::list_licenses)
+        .get(
+            "/licenses/unknown",
+            Access::READ,
+            licenses::list_unknown_licenses,
+        )
+        .get("/model/:slug/prints", Access::READ, prints::list_prints)
+        .post(
+            "/model/:slug/prints",
+            Access::CONTRIBUTE,
+            prints::create_print,
+        )
+        .get("/print/:id", Access::READ, prints::get_print)
+        .post("/print/:id", Access::CONTRIBUTE, prints::update_print)
+        .post(
+            "/print/:id/delete",
+            Access::CONTRIBUTE,
+            prints::delete_print,
+        )
+        .post(
+            "/print/:id/photo",
+            Access::CONTRIBUTE,
+            prints::upload_print_photo,
+        )
+        .get("/trash", Access::READ, trash::list_trash)
+        .post("/trash/purge", Access::PURGE, trash::empty_trash)
+        .post(
+            "/trash/:id/restore",
+            Access::EDIT,
+            trash::restore_trash_entry,
+        )
+        .post("/trash/:id/purge", Access::PURGE, trash::purge_trash_entry)
         // Collections routes
-        .route("/collections", get(list_collections))
-        .route("/collections", post(create_collection))
-        .route("/collection/:id", get(get_collection))
-        .route("/collection/:id", post(update_collection))
-        .route("/collection/:id/delete", post(delete_collection))
-        .route("/collection/add_model", post(add_model_to_collection))
-        .route(
+        .get("/collections", Access::READ, list_collections)
+        .post("/collections", Access::CONTRIBUTE, create_collection)
+        .get("/collection/:id", Access::READ, get_collection)
+        .post("/collection/:id", Access::CONTRIBUTE, update_collection)
+        .post(
+            "/collection/:id/delete",
+            Access::CONTRIBUTE,
+            delete_collection,
+        )
+        .post(
+            "/collection/add_model",
+            Access::CONTRIBUTE,
+            add_model_to_collection,
+        )
+        .post(
             "/collection/:collection_id/remove_model/:model_id",
-            post(remove_model_from_collection),
+            Access::CONTRIBUTE,
+            remove_model_from_collection,
+        )
+        .get(
+            "/model/:model_id/collections",
+            Access::READ,
+            get_model_collections,
+        )
+        .get("/auth/me", Access::READ, auth::current_user)
+        .get("/users", Access::ADMIN, auth::list_users)
+        .post("/users", Access::ADMIN, auth::handle_create_user)
+        .post("/user/:id/delete", Access::ADMIN, auth::delete_user)
+        .post("/user/:id/password", Access::ACCOUNT, auth::change_password)
+        .post("/user/:id/role", Access::ADMIN, auth::set_role)
+        .get("/audit", Access::ADMIN, audit::list_audit)
+        .get("/tokens", Access::ACCOUNT, tokens::list_tokens)
+        .post("/tokens", Access::ACCOUNT, tokens::create_token)
+        .post("/token/:id/revoke", Access::ACCOUNT, tokens::revoke_token)
+        .post("/model/:slug/share", Access::CONTRIBUTE, share::share_model)
+        .post(
+            "/collection/:id/share",
+            Access::CONTRIBUTE,
+            share::share_collection,
+        )
+        .get("/shares", Access::READ, share::list_share_links)
+        .post(
+            "/share/:id/revoke",
+            Access::CONTRIBUTE,
+            share::revoke_share_link,
+        )
+        // superseded by /v2, kept for existing clients
+        .deprecate(|router| router.route_layer(axum::middleware::from_fn(v2::deprecated)))
+        .post("/v2/refresh", Access::EDIT_FILES, handle_refresh)
+        .get("/v2/libraries", Access::READ, libraries::list_libraries)
+        .get("/v2/models", Access::READ, list_models)
+        .post("/v2/models", Access::UPLOAD, upload::handle_upload)
+        // what a bulk request needs depends on the action, the handler checks it
+        .post("/v2/bulk/:action", Access::READ, bulk::bulk_models)
+        .get("/v2/models/:model", Access::READ, v2::get_model)
+        .patch("/v2/models/:model", Access::EDIT, v2::update_model)
+        .delete("/v2/models/:model", Access::EDIT, v2::delete_model)
+        .post(
+            "/v2/models/:model/refresh",
+            Access::EDIT_FILES,
+            v2::refresh_model,
+        )
+        .put(
+            "/v2/models/:model/files",
+            Access::EDIT_FILES,
+            v2::replace_files,
+        )
+        .get(
+            "/v2/models/:model/download",
+            Access::READ,
+            v2::download_model,
+        )
+        .put(
+            "/v2/models/:model/favourite",
+            Access::FAVOURITE,
+            v2::add_favourite,
         )
-        .route("/model/:model_id/collections", get(get_model_collections))
-        .layer(DefaultBodyLimit::disable())
+        .delete(
+            "/v2/models/:model/favourite",
+            Access::FAVOURITE,
+            v2::remove_favourite,
+        )
+        .put("/v2/models/:model/slug", Access::EDIT, v2::change_slug)
+        .get(
+            "/v2/models/:model/revisions",
+            Access::READ,
+            v2::list_revisions,
+        )
+        .post(
+            "/v2/models/:model/revisions/:id/restore",
+            Access::EDIT,
+            v2::restore_revision,
+        )
+        .get("/v2/models/:model/prints", Access::READ, v2::list_prints)
+        .post(
+            "/v2/models/:model/prints",
+            Access::CONTRIBUTE,
+            v2::create_print,
+        )
+        .get(
+            "/v2/models/:model/collections",
+            Access::READ,
+            v2::list_model_collections,
+        )
+        .post(
+            "/v2/models/:model/shares",
+            Access::CONTRIBUTE,
+            v2::share_model,
+        )
+        .delete("/v2/files/:id", Access::EDIT, delete_file)
+        .get(
+            "/v2/files/:id/convert/:target_type",
+            Access::READ,
+            convert_file,
+        )
+        .get(
+            "/v2/adopt",
+            Access::READ,
+            adopt::handle_list_adoption_candidates,
+        )
+        .post("/v2/adopt", Access::EDIT_FILES, adopt::handle_adopt)
+        .get("/v2/duplicates", Access::READ, duplicates::list_duplicates)
+        .get("/v2/authors", Access::READ, authors::list_authors)
+        .get("/v2/authors/:id", Access::READ, authors::get_author)
+        .patch("/v2/authors/:id", Access::EDIT, authors::update_author)
+        .post("/v2/authors/:id/merge", Access::EDIT, authors::merge_author)
+        .get("/v2/licenses", Access::READ, licenses::list_licenses)
+        .get(
+            "/v2/licenses/unknown",
+            Access::READ,
+            licenses::list_unknown_licenses,
+        )
+        .get("/v2/prints/:id", Access::READ, prints::get_print)
+        .put("/v2/prints/:id", Access::CONTRIBUTE, prints::update_print)
+        .delete("/v2/prints/:id", Access::CONTRIBUTE, prints::delete_print)
+        .put(
+            "/v2/prints/:id/photo",
+            Access::CONTRIBUTE,
+            prints::upload_print_photo,
+        )
+        .get("/v2/trash", Access::READ, trash::list_trash)
+        .delete("/v2/trash", Access::PURGE, trash::empty_trash)
+        .post(
+            "/v2/trash/:id/restore",
+            Access::EDIT,
+            trash::restore_trash_entry,
+        )
+        .delete("/v2/trash/:id", Access::PURGE, trash::purge_trash_entry)
+        .get("/v2/collections", Access::READ, list_collections)
+        .post("/v2/collections", Access::CONTRIBUTE, create_collection)
+        .get("/v2/collections/:id", Access::READ, v2::get_collection)
+        .patch(
+            "/v2/collections/:id",
+            Access::CONTRIBUTE,
+            v2::update_collection,
+        )
+        .delete(
+            "/v2/collections/:id",
+            Access::CONTRIBUTE,
+            v2::delete_collection,
+        )
+        .put(
+            "/v2/collections/:id/models/:model",
+            Access::CONTRIBUTE,
+            v2::add_collection_model,
+        )
+        .delete(
+            "/v2/collections/:id/models/:model",
+            Access::CONTRIBUTE,
+            v2::remove_collection_model,
+        )
+        .post(
+            "/v2/collections/:id/shares",
+            Access::CONTRIBUTE,
+            share::share_collection,
+        )
+        .get("/v2/auth/me", Access::READ, auth::current_user)
+        .get("/v2/users", Access::ADMIN, auth::list_users)
+        .post("/v2/users", Access::ADMIN, auth::handle_create_user)
+        .delete("/v2/users/:id", Access::ADMIN, auth::delete_user)
+        .put(
+            "/v2/users/:id/password",
+            Access::ACCOUNT,
+            auth::change_password,
+        )
+        .put("/v2/users/:id/role", Access::ADMIN, auth::set_role)
+        .get("/v2/audit", Access::ADMIN, audit::list_audit)
+        .get("/v2/tokens", Access::ACCOUNT, tokens::list_tokens)
+        .post("/v2/tokens", Access::ACCOUNT, tokens::create_token)
+        .delete("/v2/tokens/:id", Access::ACCOUNT, tokens::revoke_token)
+        .get("/v2/shares", Access::READ, share::list_share_links)
+        .delete(
+            "/v2/shares/:id",
+            Access::CONTRIBUTE,
+            share::revoke_share_link,
+        )
+        .protect(|router| router.route_layer(require_user.clone()))
+        // reachable without a session
+        .post("/auth/login", Access::Public, auth::login)
+        .post("/auth/logout", Access::Public, auth::logout)
+        .get("/shared/:token", Access::Public, share::get_shared)
+        .get(
+            "/shared/:token/model/:slug",
+            Access::Public,
+            share::get_shared_model,
+        )
+        .get(
+            "/shared/:token/download/:slug",
+            Access::Public,
+            share::download_shared_model,
+        )
+        .get(
+            "/shared/:token/files/*path",
+            Access::Public,
+            share::get_shared_file,
+        )
+        .get(
+            "/shared/:token/previews/*path",
+            Access::Public,
+            share::get_shared_preview,
+        )
+        .into_router()
+        .route("/*path", any(error::api_not_found))
+        // leave room for the multipart framing so uploads hit the more descriptive size check
+        .layer(DefaultBodyLimit::max(
+            (config.max_upload_request_size() + 1024 * 1024) as usize,
+        ))
+        .layer(axum::middleware::from_fn(error::json_errors))
         .with_state(app_state);
 
+    // model files and previews need a session just like the API
+    let mut files = Router::new().nest_service(
+        &config.cache_prefix.to_string(),
+        ServeDir::new(&config.preview_cache_dir),
+    );
+    for library in config.libraries() {
+        files = files.nest_service(&library.asset_prefix, ServeDir::new(&library.path));
+    }
+    let files = files
+        .route_layer(axum::middleware::from_fn(libraries::hide_dot_paths))
+        .route_layer(axum::middleware::from_fn(|request, next| {
+            auth::authorize(Access::READ, request, next)
+        }))
+        .route_layer(require_user);
+
     let app = Router::new()
         .route("/healthz", get(healthz))
         .nest("/api/", api)
-        .nest_service(
-            &config.asset_prefix.to_string(),
-            ServeDir::new(config.libraries_path),
-        )
-        .nest_service(
-            &config.cache_prefix.to_string(),
-            ServeDir::new(config.preview_cache_dir),
-        )
+        .merge(files)
         .nest_service(
             "/",
             ServeDir::new("dist").fallback(ServeFile::new("dist/index.html")),
@@ -684,5 +1319,10 @@ async fn main() {
 
     info!("Server running on {}", config.address);
 
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }
@@ -0,0 +1,371 @@
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use serde_json::Value;
+use std::path::{Path as FsPath, PathBuf};
+use tokio::fs;
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::audit::{self, AuditTarget};
+use crate::auth::CurrentUser;
+use crate::error::AppError;
+use crate::parse_library::add_or_update_model;
+use crate::revisions::{self, PendingRevision};
+use crate::schema::models3d;
+use crate::types::{
+    AuditAction, DetailedModelResponse, Model3D, RevisionChange, RevisionReason,
+    UpdateMetadataRequest,
+};
+use crate::v2::IfMatch;
+use crate::{AppState, Config};
+
+const MANIFEST: &str = "modelpack.json";
+const MAX_FIELD_LENGTH: usize = 256;
+
+/// Collects every problem of the request instead of stopping at the first one
+fn validate(request: &UpdateMetadataRequest) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let single_line = [
+        ("title", &request.title),
+        ("author", &request.author),
+        ("license", &request.license),
+        ("origin", &request.origin),
+    ];
+    for (field, value) in single_line {
+        let Some(value) = value else {
+            continue;
+        };
+        if value.chars().count() > MAX_FIELD_LENGTH {
+            problems.push(format!(
+                "{} is longer than {} characters",
+                field, MAX_FIELD_LENGTH
+            ));
+        }
+        if value.chars().any(char::is_control) {
+            problems.push(format!("{} must be a single line", field));
+        }
+    }
+
+    if let Some(title) = &request.title {
+        if title.trim().is_empty() {
+            problems.push("title must not be empty".to_string());
+        } else if request.move_folder.unwrap_or(false)
+            && sanitize_filename::sanitize(title.trim()).is_empty()
+        {
+            problems.push("title can not be used as a folder name".to_string());
+        }
+    }
+
+    if let Some(origin) = request.origin.as_deref().map(str::trim) {
+        let is_web_url = url::Url::parse(origin)
+            .is_ok_and(|url| url.scheme() == "http" || url.scheme() == "https");
+        if !origin.is_empty() && !is_web_url {
+            problems.push("origin must be an http or https URL".to_string());
+        }
+    }
+
+    problems
+}
+
+/// The readme is written back under the name it already has
+fn readme_name(model_dir: &FsPath) -> &'static str {
+    if model_dir.join("readme.md").is_file() {
+        "readme.md"
+    } else {
+        "README.md"
+    }
+}
+
+/// Writes next to the target and renames, readers never see a partially written file
+async fn write_atomically(path: &FsPath, content: &str) -> anyhow::Result<()> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4()));
+
+    if let Err(e) = fs::write(&temp_path, content).await {
+        let _ = fs::remove_file(&temp_path).await;
+        return Err(e.into());
+    }
+    if let Err(e) = fs::rename(&temp_path, path).await {
+        let _ = fs::remove_file(&temp_path).await;
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// A file of the model folder as it was before the edit, `None` if it did not exist
+struct PreviousFile {
+    name: &'static str,
+    content: Option<String>,
+}
+
+async fn restore_files(model_dir: &FsPath, previous: &[PreviousFile]) {
+    for file in previous {
+        let path = model_dir.join(file.name);
+        let restored = match &file.content {
+            Some(content) => write_atomically(&path, content).await,
+            None => fs::remove_file(&path).await.map_err(Into::into),
+        };
+        if let Err(e) = restored {
+            error!("Failed to restore {}: {:?}", path.display(), e);
+        }
+    }
+}
+
+pub async fn update_metadata(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(slug): Path<String>,
+    if_match: IfMatch,
+    Json(request): Json<UpdateMetadataRequest>,
+) -> Result<Json<DetailedModelResponse>, AppError> {
+    let config = &state.config;
+    let mut connection = state.pool.get().await?;
+
+    let model = crate::slug::resolve_model(&mut connection, &slug).await?;
+    if_match.check_model(&mut connection, model.id).await?;
+
+    let problems = validate(&request);
+    if !problems.is_empty() {
+        return Err(AppError::unprocessable(problems.join(", ")));
+    }
+
+    let library = model.writable_library(config)?;
+    let model_dir = library.path.join(&model.folder_path);
+    let manifest_path = model_dir.join(MANIFEST);
+    let old_manifest = fs::read_to_string(&manifest_path)
+        .await
+        .map_err(AppError::internal)?;
+
+    // unknown fields of the manifest are kept as they are
+    let original: Value = serde_json::from_str(&old_manifest).map_err(AppError::internal)?;
+    let mut manifest = original.clone();
+    let Some(fields) = manifest.as_object_mut() else {
+        return Err(AppError::internal(format!("{} is not an object", MANIFEST)));
+    };
+    let changes = [
+        ("title", &request.title),
+        ("author", &request.author),
+        ("origin", &request.origin),
+        ("license", &request.license),
+    ];
+    for (field, value) in changes {
+        if let Some(value) = value {
+            fields.insert(field.to_string(), Value::String(value.trim().to_string()));
+        }
+    }
+
+    let readme_name = readme_name(&model_dir);
+    let old_readme = fs::read_to_string(model_dir.join(readme_name)).await.ok();
+
+    let mut edits = Vec::new();
+    if manifest != original {
+        let new_manifest = serde_json::to_string_pretty(&manifest).map_err(AppError::internal)?;
+        edits.push((MANIFEST, new_manifest, Some(old_manifest)));
+    }
+    if let Some(readme) = request.readme.clone() {
+        if old_readme.as_ref() != Some(&readme) {
+            edits.push((readme_name, readme, old_readme));
+        }
+    }
+
+    let new_title = request
+        .title
+        .as_deref()
+        .map(str::trim)
+        .filter(|title| *title != model.title);
+    let target_dir = match new_title {
+        Some(title) if request.move_folder.unwrap_or(false) => {
+            let target = model_dir
+                .parent()
+                .unwrap_or(&library.path)
+                .join(sanitize_filename::sanitize(title));
+            if target != model_dir && target.exists() {
+                return Err(AppError::conflict(format!(
+                    "A model in {} already exists",
+                    target.display()
+                )));
+            }
+            Some(target).filter(|target| *target != model_dir)
+        }
+        _ => None,
+    };
+
+    if edits.is_empty() && target_dir.is_none() {
+        let response =
+            DetailedModelResponse::from_model_3d(&model, config, user.id, &mut connection)
+                .await
+                .map_err(AppError::internal)?;
+        return Ok(Json(response));
+    }
+
+    // the previous manifest and readme stay available in the history of the model
+    let revision = if edits.is_empty() {
+        None
+    } else {
+        let changed_files = edits
+            .iter()
+            .map(|(name, _, previous)| {
+                let change = if previous.is_some() {
+                    RevisionChange::Replaced
+                } else {
+                    RevisionChange::Added
+                };
+                (name.to_string(), change)
+            })
+            .collect();
+        let pending = revisions::snapshot(
+            config,
+            model.id,
+            RevisionReason::Update,
+            &model_dir,
+            changed_files,
+        )
+        .await
+        .map_err(|e| {
+            error!("Keeping the metadata of {} failed: {:?}", model.title, e);
+            AppError::internal("Failed to keep the previous metadata")
+        })?;
+        Some(pending)
+    };
+
+    let mut previous = Vec::new();
+    for (name, content, old_content) in edits {
+        if let Err(e) = write_atomically(&model_dir.join(name), &content).await {
+            error!("Writing {} of {} failed: {:?}", name, model.title, e);
+            restore_files(&model_dir, &previous).await;
+            if let Some(revision) = &revision {
+                revision.discard(config).await;
+            }
+            return Err(AppError::internal("Failed to write the metadata"));
+        }
+        previous.push(PreviousFile {
+            name,
+            content: old_content,
+        });
+    }
+
+    if let Some(target_dir) = &target_dir {
+        if let Err(e) = fs::rename(&model_dir, target_dir).await {
+            error!("Moving {} failed: {:?}", model_dir.display(), e);
+            restore_files(&model_dir, &previous).await;
+            if let Some(revision) = &revision {
+                revision.discard(config).await;
+            }
+            return Err(AppError::internal("Failed to move the model folder"));
+        }
+        debug!("Moved {} to {}", model_dir.display(), target_dir.display());
+    }
+
+    let indexed = reindex(
+        config,
+        &mut connection,
+        &model,
+        target_dir.as_deref(),
+        revision.as_ref(),
+        &if_match,
+    )
+    .await;
+
+    let model = match indexed {
+        Ok(model) => model,
+        Err(e) => {
+            error!("Indexing {} failed, rolling back: {:?}", model.title, e);
+            if let Some(target_dir) = &target_dir {
+                if let Err(e) = fs::rename(target_dir, &model_dir).await {
+                    error!("Moving back {} failed: {:?}", target_dir.display(), e);
+                }
+            }
+            restore_files(&model_dir, &previous).await;
+            if let Some(revision) = &revision {
+                revision.discard(config).await;
+            }
+            return Err(match e.downcast::<AppError>() {
+                Ok(e) => e,
+                Err(_) => AppError::internal("Failed to index the metadata"),
+            });
+        }
+    };
+
+    let mut changes: Vec<String> = changes
+        .iter()
+        .filter_map(|(field, value)| {
+            value
+                .as_deref()
+                .map(|value| format!("{} to {:?}", field, value.trim()))
+        })
+        .collect();
+    if request.readme.is_some() {
+        changes.push("readme".to_string());
+    }
+    if let Some(target_dir) = &target_dir {
+        changes.push(format!("moved to {}", target_dir.display()));
+    }
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Update,
+        AuditTarget::model(&model),
+        format!("Changed {}", changes.join(", ")),
+    )
+    .await;
+
+    let response = DetailedModelResponse::from_model_3d(&model, config, user.id, &mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    Ok(Json(response))
+}
+
+/// Picks up the written metadata and a moved folder in one transaction with the check of `if_match`
+async fn reindex<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    model: &Model3D,
+    target_dir: Option<&FsPath>,
+    revision: Option<&PendingRevision>,
+    if_match: &IfMatch,
+) -> anyhow::Result<Model3D>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let library = model.library(config)?;
+    let model_dir: PathBuf = target_dir
+        .map(FsPath::to_path_buf)
+        .unwrap_or_else(|| library.path.join(&model.folder_path));
+    let relative_dir = pathdiff::diff_paths(&model_dir, &library.path)
+        .ok_or_else(|| anyhow::anyhow!("{} is outside the library", model_dir.display()))?;
+    let model_id = model.id;
+
+    connection
+        .transaction::<Model3D, anyhow::Error, _>(|connection| {
+            async move {
+                if_match.check_model(connection, model_id).await?;
+                if let Some(revision) = revision {
+                    revision.record(connection).await?;
+                }
+
+                // point the entry at the moved folder to keep its id, favourite and collections
+                diesel::update(models3d::table.find(model_id))
+                    .set(models3d::folder_path.eq(relative_dir.to_string_lossy().as_ref()))
+                    .execute(connection)
+                    .await?;
+
+                add_or_update_model(config, connection, &model_dir).await?;
+
+                Ok(models3d::table
+                    .find(model_id)
+                    .first::<Model3D>(connection)
+                    .await?)
+            }
+            .scope_boxed()
+        })
+        .await
+}
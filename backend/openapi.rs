@@ -0,0 +1,591 @@
+use axum::{
+    body::Bytes,
+    extract::{Multipart, Path, Query, Request, State},
+    handler::Handler,
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, on, MethodFilter},
+    Extension, Json, Router,
+};
+use axum_extra::extract::CookieJar;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::{Schema, SchemaObject, SingleOrVec};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::future::Future;
+
+use crate::auth::{self, Access, SESSION_COOKIE};
+use crate::error::AppError;
+use crate::stream_dl::ZipArchive;
+use crate::types::ErrorResponse;
+use crate::v2::{IfMatch, Tagged};
+use crate::AppState;
+
+/// The part of an OpenAPI operation the extractors and the response type of a handler describe
+#[derive(Default)]
+pub struct Operation {
+    path_params: Vec<String>,
+    parameters: Vec<Value>,
+    request_body: Option<Value>,
+    responses: Map<String, Value>,
+}
+
+impl Operation {
+    fn for_path(path: &str) -> Self {
+        Operation {
+            path_params: path
+                .split('/')
+                .filter_map(|segment| segment.strip_prefix(':').or(segment.strip_prefix('*')))
+                .map(str::to_string)
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn add_response(&mut self, status: &str, description: &str, content: Option<(&str, Value)>) {
+        let mut response = json!({ "description": description });
+        if let Some((media_type, schema)) = content {
+            response["content"] = json!({ media_type: { "schema": schema } });
+        }
+        self.responses.insert(status.to_string(), response);
+    }
+
+    fn into_json(mut self, operation_id: &str, summary: &str, tag: &str) -> Value {
+        // parameters no extractor described are plain strings
+        for name in &self.path_params {
+            let described = self
+                .parameters
+                .iter()
+                .any(|parameter| parameter["in"] == "path" && parameter["name"] == *name);
+            if !described {
+                self.parameters.push(json!({
+                    "name": name,
+                    "in": "path",
+                    "required": true,
+                    "schema": { "type": "string" },
+                }));
+            }
+        }
+
+        let mut operation = json!({
+            "operationId": operation_id,
+            "summary": summary,
+            "tags": [tag],
+            "responses": self.responses,
+        });
+        if !self.parameters.is_empty() {
+            operation["parameters"] = Value::Array(self.parameters);
+        }
+        if let Some(request_body) = self.request_body {
+            operation["requestBody"] = request_body;
+        }
+        operation
+    }
+}
+
+fn summary(operation_id: &str) -> String {
+    let words = operation_id.replace('_', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+/// Schema in the OpenAPI 3.0 dialect, named types end up in the components
+fn schema_for<T: JsonSchema>(generator: &mut SchemaGenerator) -> Value {
+    let mut schema = generator.subschema_for::<T>();
+    to_openapi(generator, &mut schema)
+}
+
+fn to_openapi(generator: &mut SchemaGenerator, schema: &mut Schema) -> Value {
+    for visitor in generator.visitors_mut() {
+        visitor.visit_schema(schema);
+    }
+    serde_json::to_value(&*schema).unwrap_or_default()
+}
+
+/// The inline schema of `T` if it is an object, e.g. to list its fields as query parameters
+fn object_schema<T: JsonSchema>(generator: &mut SchemaGenerator) -> SchemaObject {
+    match T::json_schema(generator) {
+        Schema::Object(schema) => schema,
+        Schema::Bool(_) => SchemaObject::default(),
+    }
+}
+
+/// What an extractor tells about the request of an operation
+pub trait OperationInput {
+    fn document(_operation: &mut Operation, _generator: &mut SchemaGenerator) {}
+}
+
+impl<T> OperationInput for State<T> {}
+impl<T> OperationInput for Extension<T> {}
+impl OperationInput for CookieJar {}
+impl OperationInput for Request {}
+
+impl<T: JsonSchema> OperationInput for Json<T> {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator) {
+        operation.request_body = Some(json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema_for::<T>(generator) } },
+        }));
+    }
+}
+
+impl OperationInput for IfMatch {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator) {
+        operation.parameters.push(json!({
+            "name": "If-Match",
+            "in": "header",
+            "required": false,
+            "description": "ETag of the version the change is based on",
+            "schema": { "type": "string" },
+        }));
+        let schema = schema_for::<ErrorResponse>(generator);
+        operation.add_response(
+            "412",
+            "The resource changed since it was fetched",
+            Some(("application/json", schema)),
+        );
+    }
+}
+
+impl OperationInput for Multipart {
+    fn document(operation: &mut Operation, _generator: &mut SchemaGenerator) {
+        operation.request_body = Some(json!({
+            "required": true,
+            "content": {
+                "multipart/form-data": {
+                    "schema": {
+                        "type": "object",
+                        "description": "Files to store, a modelpack.json describes the model",
+                        "additionalProperties": { "type": "string", "format": "binary" },
+                    },
+                },
+            },
+        }));
+    }
+}
+
+impl<T: JsonSchema> OperationInput for Query<T> {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator) {
+        let Some(object) = object_schema::<T>(generator).object else {
+            return;
+        };
+        for (name, mut schema) in object.properties {
+            // the description belongs to the parameter, a missing parameter is the null
+            let description = match &mut schema {
+                Schema::Object(schema) => schema
+                    .metadata
+                    .as_mut()
+                    .and_then(|metadata| metadata.description.take()),
+                Schema::Bool(_) => None,
+            };
+            let mut schema = to_openapi(generator, &mut schema);
+            if let Some(schema) = schema.as_object_mut() {
+                schema.remove("nullable");
+            }
+            let mut parameter = json!({
+                "name": name,
+                "in": "query",
+                "required": object.required.contains(&name),
+                "schema": schema,
+            });
+            if let Some(description) = description {
+                parameter["description"] = Value::String(description);
+            }
+            operation.parameters.push(parameter);
+        }
+    }
+}
+
+impl<T: JsonSchema> OperationInput for Path<T> {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator) {
+        let schema = generator.subschema_for::<T>();
+        // a tuple gives the parameters in the order of the path
+        let schemas = match schema {
+            Schema::Object(SchemaObject {
+                array: Some(array), ..
+            }) if operation.path_params.len() > 1 => match array.items {
+                Some(SingleOrVec::Vec(items)) => items,
+                _ => Vec::new(),
+            },
+            schema => vec![schema],
+        };
+        if schemas.len() != operation.path_params.len() {
+            return;
+        }
+        for (name, mut schema) in operation.path_params.clone().into_iter().zip(schemas) {
+            operation.parameters.push(json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": to_openapi(generator, &mut schema),
+            }));
+        }
+    }
+}
+
+/// What the return type of a handler tells about the responses of an operation
+pub trait OperationOutput {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator);
+}
+
+impl<T: JsonSchema> OperationOutput for Json<T> {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator) {
+        let schema = schema_for::<T>(generator);
+        operation.add_response("200", "Success", Some(("application/json", schema)));
+    }
+}
+
+impl OperationOutput for String {
+    fn document(operation: &mut Operation, _generator: &mut SchemaGenerator) {
+        let schema = json!({ "type": "string" });
+        operation.add_response("200", "Success", Some(("text/plain", schema)));
+    }
+}
+
+impl OperationOutput for StatusCode {
+    fn document(operation: &mut Operation, _generator: &mut SchemaGenerator) {
+        operation.add_response("2XX", "Success", None);
+    }
+}
+
+/// A file whose type is only known at runtime, a plain `Response` can not be documented
+pub struct FileBody(pub Response);
+
+impl IntoResponse for FileBody {
+    fn into_response(self) -> Response {
+        self.0
+    }
+}
+
+impl OperationOutput for FileBody {
+    fn document(operation: &mut Operation, _generator: &mut SchemaGenerator) {
+        let schema = json!({ "type": "string", "format": "binary" });
+        operation.add_response("200", "File", Some(("*/*", schema)));
+    }
+}
+
+impl OperationOutput for ZipArchive {
+    fn document(operation: &mut Operation, _generator: &mut SchemaGenerator) {
+        let schema = json!({ "type": "string", "format": "binary" });
+        operation.add_response("200", "ZIP archive", Some(("application/zip", schema)));
+    }
+}
+
+/// The response, or a redirect to where the resource is now, e.g. from a previous slug
+pub enum OrRedirect<T> {
+    Response(T),
+    Redirect(Redirect),
+}
+
+impl<T: IntoResponse> IntoResponse for OrRedirect<T> {
+    fn into_response(self) -> Response {
+        match self {
+            OrRedirect::Response(response) => response.into_response(),
+            OrRedirect::Redirect(redirect) => redirect.into_response(),
+        }
+    }
+}
+
+impl<T: OperationOutput> OperationOutput for OrRedirect<T> {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator) {
+        T::document(operation, generator);
+        operation.add_response(
+            "307",
+            "Moved, the Location header has the current URL",
+            None,
+        );
+    }
+}
+
+/// The status is only known at runtime, e.g. 201 for created resources
+impl<T: OperationOutput> OperationOutput for (StatusCode, T) {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator) {
+        T::document(operation, generator);
+        if let Some(response) = operation.responses.remove("200") {
+            operation.responses.insert("2XX".to_string(), response);
+        }
+    }
+}
+
+impl<T: OperationOutput> OperationOutput for Tagged<T> {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator) {
+        T::document(operation, generator);
+        if let Some(response) = operation.responses.get_mut("200") {
+            response["headers"] = json!({
+                "ETag": {
+                    "description": "Version of the resource for If-Match",
+                    "schema": { "type": "string" },
+                },
+            });
+        }
+    }
+}
+
+impl<T: OperationOutput> OperationOutput for (CookieJar, T) {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator) {
+        T::document(operation, generator);
+    }
+}
+
+impl<T: OperationOutput> OperationOutput for Result<T, AppError> {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator) {
+        T::document(operation, generator);
+        let schema = schema_for::<ErrorResponse>(generator);
+        operation.add_response("default", "Error", Some(("application/json", schema)));
+    }
+}
+
+/// Handlers whose extractors and response type can be documented
+pub trait DocumentedHandler<T> {
+    fn document(operation: &mut Operation, generator: &mut SchemaGenerator);
+}
+
+macro_rules! documented_handler {
+    ($($ty:ident),*) => {
+        impl<F, Fut, $($ty,)*> DocumentedHandler<($($ty,)*)> for F
+        where
+            F: FnOnce($($ty,)*) -> Fut,
+            Fut: Future,
+            Fut::Output: OperationOutput,
+            $($ty: OperationInput,)*
+        {
+            fn document(operation: &mut Operation, generator: &mut SchemaGenerator) {
+                $($ty::document(operation, generator);)*
+                Fut::Output::document(operation, generator);
+            }
+        }
+    };
+}
+
+documented_handler!();
+documented_handler!(T1);
+documented_handler!(T1, T2);
+documented_handler!(T1, T2, T3);
+documented_handler!(T1, T2, T3, T4);
+documented_handler!(T1, T2, T3, T4, T5);
+documented_handler!(T1, T2, T3, T4, T5, T6);
+
+/// Router of the API which documents every route it gets, so the OpenAPI document can not miss one,
+/// and checks the `Access` every route is declared with
+pub struct ApiRouter {
+    router: Router<AppState>,
+    generator: SchemaGenerator,
+    /// Path in OpenAPI syntax -> method -> operation
+    paths: BTreeMap<String, Map<String, Value>>,
+    /// Operations added since the last `protect`
+    unprotected: Vec<(String, String)>,
+    /// Operations added since the last `deprecate`
+    undeprecated: Vec<(String, String)>,
+}
+
+impl Default for ApiRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ApiRouter {
+    pub fn new() -> Self {
+        ApiRouter {
+            router: Router::new(),
+            generator: SchemaSettings::openapi3().into_generator(),
+            paths: BTreeMap::new(),
+            unprotected: Vec::new(),
+            undeprecated: Vec::new(),
+        }
+    }
+
+    pub fn get<H, T, D>(self, path: &str, access: Access, handler: H) -> Self
+    where
+        H: Handler<T, AppState> + DocumentedHandler<D>,
+        T: 'static,
+    {
+        self.route(MethodFilter::GET, "get", path, access, handler)
+    }
+
+    pub fn post<H, T, D>(self, path: &str, access: Access, handler: H) -> Self
+    where
+        H: Handler<T, AppState> + DocumentedHandler<D>,
+        T: 'static,
+    {
+        self.route(MethodFilter::POST, "post", path, access, handler)
+    }
+
+    pub fn patch<H, T, D>(self, path: &str, access: Access, handler: H) -> Self
+    where
+        H: Handler<T, AppState> + DocumentedHandler<D>,
+        T: 'static,
+    {
+        self.route(MethodFilter::PATCH, "patch", path, access, handler)
+    }
+
+    pub fn put<H, T, D>(self, path: &str, access: Access, handler: H) -> Self
+    where
+        H: Handler<T, AppState> + DocumentedHandler<D>,
+        T: 'static,
+    {
+        self.route(MethodFilter::PUT, "put", path, access, handler)
+    }
+
+    pub fn delete<H, T, D>(self, path: &str, access: Access, handler: H) -> Self
+    where
+        H: Handler<T, AppState> + DocumentedHandler<D>,
+        T: 'static,
+    {
+        self.route(MethodFilter::DELETE, "delete", path, access, handler)
+    }
+
+    fn route<H, T, D>(
+        mut self,
+        filter: MethodFilter,
+        method_name: &str,
+        path: &str,
+        access: Access,
+        handler: H,
+    ) -> Self
+    where
+        H: Handler<T, AppState> + DocumentedHandler<D>,
+        T: 'static,
+    {
+        let mut operation = Operation::for_path(path);
+        H::document(&mut operation, &mut self.generator);
+
+        // the name of the handler, e.g. meshvault::share::share_model
+        let name = std::any::type_name::<H>()
+            .rsplit("::")
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let openapi_path = openapi_path(path);
+        let mut segments = openapi_path
+            .split('/')
+            .filter(|segment| !segment.is_empty() && !segment.starts_with('{'))
+            .peekable();
+        // versioned routes share the tags of the first version
+        let version = segments.next_if(|segment| is_version(segment));
+        let tag = segments.next().unwrap_or("api").to_string();
+        let operation_id = match version {
+            Some(version) => format!("{}_{}", version, name),
+            None => name.clone(),
+        };
+        let operation_id = if self.operation_id_taken(&operation_id) {
+            format!("{}_{}", operation_id, method_name)
+        } else {
+            operation_id
+        };
+        self.paths.entry(openapi_path.clone()).or_default().insert(
+            method_name.to_string(),
+            operation.into_json(&operation_id, &summary(&name), &tag),
+        );
+        self.unprotected
+            .push((openapi_path.clone(), method_name.to_string()));
+        self.undeprecated
+            .push((openapi_path, method_name.to_string()));
+
+        let method_router = match access {
+            Access::Public => on(filter, handler),
+            access => on(filter, handler).route_layer(axum::middleware::from_fn(
+                move |request: Request, next: Next| auth::authorize(access, request, next),
+            )),
+        };
+        self.router = self.router.route(path, method_router);
+        self
+    }
+
+    fn operation_id_taken(&self, operation_id: &str) -> bool {
+        self.paths
+            .values()
+            .flat_map(|operations| operations.values())
+            .any(|operation| operation["operationId"] == operation_id)
+    }
+
+    /// Wraps the routes added so far, e.g. with `Router::route_layer`, and documents that they need a login
+    pub fn protect(mut self, layer: impl FnOnce(Router<AppState>) -> Router<AppState>) -> Self {
+        self.router = layer(self.router);
+        for (path, method) in self.unprotected.drain(..) {
+            if let Some(operation) = self
+                .paths
+                .get_mut(&path)
+                .and_then(|operations| operations.get_mut(&method))
+            {
+                operation["security"] = json!([{ "session": [] }, { "token": [] }]);
+            }
+        }
+        self
+    }
+
+    /// Wraps the routes added so far, e.g. to add a header, and documents them as deprecated
+    pub fn deprecate(mut self, layer: impl FnOnce(Router<AppState>) -> Router<AppState>) -> Self {
+        self.router = layer(self.router);
+        for (path, method) in self.undeprecated.drain(..) {
+            if let Some(operation) = self
+                .paths
+                .get_mut(&path)
+                .and_then(|operations| operations.get_mut(&method))
+            {
+                operation["deprecated"] = Value::Bool(true);
+            }
+        }
+        self
+    }
+
+    /// The OpenAPI document of all routes, paths are relative to /api
+    fn document(mut self) -> (Router<AppState>, Value) {
+        let mut schemas = Map::new();
+        for (name, mut schema) in self.generator.take_definitions() {
+            schemas.insert(name, to_openapi(&mut self.generator, &mut schema));
+        }
+        let document = json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "MeshVault API",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "servers": [{ "url": "/api" }],
+            "paths": self.paths,
+            "components": {
+                "schemas": schemas,
+                "securitySchemes": {
+                    "session": { "type": "apiKey", "in": "cookie", "name": SESSION_COOKIE },
+                    "token": { "type": "http", "scheme": "bearer" },
+                },
+            },
+        });
+        (self.router, document)
+    }
+
+    /// The router with the document served at /openapi.json
+    pub fn into_router(self) -> Router<AppState> {
+        let (router, document) = self.document();
+        let document = Bytes::from(document.to_string());
+        router.route(
+            "/openapi.json",
+            get(move || async move { ([(header::CONTENT_TYPE, "application/json")], document) }),
+        )
+    }
+}
+
+/// `v2` of `/v2/models`
+fn is_version(segment: &str) -> bool {
+    segment
+        .strip_prefix('v')
+        .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// `/model/:slug` becomes `/model/{slug}`
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(
+            |segment| match segment.strip_prefix(':').or(segment.strip_prefix('*')) {
+                Some(name) => format!("{{{}}}", name),
+                None => segment.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join("/")
+}
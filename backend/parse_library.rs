@@ -1,7 +1,10 @@
-use crate::schema::{files3d, models3d};
+use crate::authors::author_for_name;
+use crate::prints::relink_prints;
+use crate::schema::{files3d, models3d, prints};
+use crate::slug::{change_slug, unique_slug};
 use crate::types::ModelPackV0_1;
 use crate::types::{File3D, Model3D, NewFile3D, NewModel3D};
-use crate::Config;
+use crate::{revisions, trash, Config};
 use chrono::Local;
 use diesel::prelude::*;
 use diesel::SqliteConnection;
@@ -30,6 +33,14 @@ pub async fn find_modelpack_directories(start_path: PathBuf) -> anyhow::Result<V
 
         while let Ok(Some(entry)) = dir_entries.next_entry().await {
             let pth = entry.path();
+            // hidden folders like the upload staging area are never part of the library
+            if pth
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with('.'))
+            {
+                continue;
+            }
             if pth.file_name() == Some("modelpack.json".as_ref()) && {
                 let parent_dir = pth.parent().unwrap_or_else(|| Path::new(""));
                 parent_dir.join("files").is_dir()
@@ -106,15 +117,19 @@ pub async fn refresh_library(
         Local::now().format("%Y-%m-%d %H:%M:%S")
     );
 
-    let data_dirs = find_modelpack_directories(config.libraries_path.clone())
-        .await
-        .unwrap();
+    let mut data_dirs = Vec::new();
+    for library in config.libraries() {
+        data_dirs.extend(find_modelpack_directories(library.path.clone()).await?);
+    }
     let mut connection = pool.get().await.unwrap();
 
-    // delete models from db which do not exist anymore in the fs
-    let dirs_set: HashSet<PathBuf> = data_dirs
+    // delete models from db which do not exist anymore in the fs or whose library was removed
+    let dirs_set: HashSet<(Option<&str>, PathBuf)> = data_dirs
         .iter()
-        .filter_map(|dir| pathdiff::diff_paths(dir, &config.libraries_path))
+        .filter_map(|dir| {
+            let library = config.library_of(dir)?;
+            Some((library.key(), pathdiff::diff_paths(dir, &library.path)?))
+        })
         .collect();
 
     let possibly_old_models = models3d::dsl::models3d
@@ -123,11 +138,10 @@ pub async fn refresh_library(
         .unwrap();
 
     for model in possibly_old_models {
-        if !dirs_set.contains(&PathBuf::from(&model.folder_path)) {
-            diesel::delete(models3d::dsl::models3d.filter(models3d::dsl::id.eq(model.id)))
-                .execute(&mut connection)
-                .await
-                .unwrap();
+        let folder = (model.library.as_deref(), PathBuf::from(&model.folder_path));
+        if !dirs_set.contains(&folder) {
+            trash::remove_model_rows(&mut connection, model.id).await?;
+            revisions::delete_revisions(&config, &mut connection, model.id).await?;
 
             debug!(
                 "Deleted model from database: {:?} (id: {})",
@@ -183,9 +197,14 @@ where
 {
     // delete file references deleted in fs
     for file in files {
-        let file_pth = file.get_file_path(connection, config).await.clone();
+        // files of models in libraries which are not configured anymore are dropped as well
+        let file_pth = file
+            .get_file_path(connection, config)
+            .await
+            .ok()
+            .filter(|pth| pth.exists());
 
-        if fs::metadata(&file_pth).await.is_ok() {
+        if let Some(file_pth) = file_pth {
             let current_sha = sha256::try_async_digest(file_pth.clone())
                 .await
                 .unwrap()
@@ -195,6 +214,10 @@ where
                 continue;
             }
         }
+        diesel::update(prints::table.filter(prints::file_id.eq(file.id)))
+            .set(prints::file_id.eq(None::<i32>))
+            .execute(connection)
+            .await?;
         diesel::delete(files3d::dsl::files3d.filter(files3d::dsl::id.eq(file.id)))
             .execute(connection)
             .await
@@ -204,7 +227,7 @@ where
     anyhow::Ok(())
 }
 
-async fn read_readme(dir: &std::path::Path) -> anyhow::Result<String> {
+pub async fn read_readme(dir: &std::path::Path) -> anyhow::Result<String> {
     let mut readme_path = dir.to_path_buf();
     readme_path.push("readme.md");
     if let Ok(content) = fs::read_to_string(&readme_path).await {
@@ -230,10 +253,14 @@ pub async fn add_or_update_model<Conn>(
 where
     Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
 {
-    let relative_dir = pathdiff::diff_paths(dir, &config.libraries_path).unwrap();
+    let library = config
+        .library_of(dir)
+        .ok_or_else(|| anyhow::anyhow!("{} is in no library", dir.display()))?;
+    let relative_dir = pathdiff::diff_paths(dir, &library.path).unwrap();
 
     let result: Option<Model3D> = models3d::dsl::models3d
         .filter(models3d::dsl::folder_path.eq(relative_dir.to_str().unwrap()))
+        .filter(models3d::dsl::library.is(library.key()))
         .first::<Model3D>(connection)
         .await
         .ok();
@@ -244,7 +271,8 @@ where
     let model_pack_meta = match get_modelpack_meta(dir).await {
         Ok(meta) => meta,
         Err(_) => {
-            if let Some(existing_model) = result {
+            // nothing is moved to the trash of a read-only library
+            if let Some(existing_model) = result.filter(|_| !library.read_only) {
                 existing_model.delete(config, connection).await?;
             }
             return Err(anyhow::Error::msg("Failed to get model pack meta"));
@@ -263,14 +291,25 @@ where
         readme.clone(),
     )
     .unwrap();
+    let author_id = author_for_name(connection, &model_pack_meta.author).await?;
 
     if let Some(existing_model) = result {
+        // the slug stays stable when the title changes, only empty ones from older versions are replaced
+        if existing_model.name.is_empty() {
+            let slug = unique_slug(connection, &new_object.title, Some(existing_model.id)).await?;
+            change_slug(connection, &existing_model, &slug).await?;
+        }
+
         diesel::update(models3d::dsl::models3d.find(existing_model.id))
             .set((
                 models3d::dsl::title.eq(&new_object.title),
-                models3d::dsl::name.eq(&new_object.name),
                 models3d::dsl::license.eq(&new_object.license),
+                models3d::dsl::license_spdx.eq(&new_object.license_spdx),
+                models3d::dsl::license_non_commercial.eq(new_object.license_non_commercial),
+                models3d::dsl::license_share_alike.eq(new_object.license_share_alike),
+                models3d::dsl::license_attribution.eq(new_object.license_attribution),
                 models3d::dsl::author.eq(&new_object.author),
+                models3d::dsl::author_id.eq(author_id),
                 models3d::dsl::origin.eq(&new_object.origin),
                 models3d::dsl::images.eq(new_object.images),
                 models3d::dsl::description.eq(readme.clone()),
@@ -281,18 +320,22 @@ where
         debug!("Scanning {:?}", new_object.folder_path);
         anyhow::Ok(existing_model)
     } else {
+        let mut new_object = new_object;
+        new_object.name = unique_slug(connection, &new_object.title, None).await?;
+        new_object.author_id = author_id;
+        new_object.library = library.key().map(str::to_string);
+
         diesel::insert_into(models3d::table)
             .values(&new_object)
             .execute(connection)
-            .await
-            .ok();
+            .await?;
         debug!("Created Model {:?}", new_object.folder_path);
 
         let result = models3d::dsl::models3d
             .filter(models3d::dsl::folder_path.eq(relative_dir.to_str().unwrap()))
+            .filter(models3d::dsl::library.is(library.key()))
             .first::<Model3D>(connection)
-            .await
-            .unwrap();
+            .await?;
         anyhow::Ok(result)
     }
 }
@@ -305,8 +348,7 @@ pub async fn load_files_and_preview<Conn>(
 where
     Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
 {
-    let mut model_base_path = config.libraries_path.clone();
-    model_base_path.push(model.folder_path.clone());
+    let model_base_path = model.absolute_path(config)?;
 
     let mut search_dir = model_base_path.clone();
     search_dir.push("files");
@@ -388,11 +430,13 @@ where
             file_hash: Some(hash),
             file_size_bytes: fs::metadata(file_pth).await?.len() as i32,
         };
-        diesel::insert_into(files3d::table)
+        let file = diesel::insert_into(files3d::table)
             .values(&new_file)
-            .execute(connection)
+            .returning(File3D::as_returning())
+            .get_result(connection)
             .await
             .unwrap();
+        relink_prints(connection, model.id, &file).await?;
         debug!("Created Preview {:?}", new_file.file_path)
     }
     anyhow::Ok(())
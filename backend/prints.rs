@@ -0,0 +1,372 @@
+use axum::{
+    extract::{Multipart, Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::path::Path as FsPath;
+use tokio::fs::{self, File};
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, error};
+
+use crate::audit::{self, AuditTarget};
+use crate::auth::CurrentUser;
+use crate::error::AppError;
+use crate::schema::{files3d, models3d, prints};
+use crate::types::{
+    AuditAction, AuditTargetType, File3D, Model3D, NewPrint, Print, PrintRequest, PrintResponse,
+};
+use crate::upload::categorize_file;
+use crate::{AppState, Config};
+
+async fn get_print_and_model<Conn>(
+    connection: &mut Conn,
+    print_id: i32,
+) -> Result<(Print, Model3D), AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let print = prints::table
+        .find(print_id)
+        .first::<Print>(connection)
+        .await
+        .map_err(|_| AppError::not_found(format!("Print {} not found", print_id)))?;
+    let model = models3d::table
+        .find(print.model_id)
+        .first::<Model3D>(connection)
+        .await
+        .map_err(|_| AppError::not_found(format!("Print {} not found", print_id)))?;
+
+    Ok((print, model))
+}
+
+/// Builds the row for a print request, the file has to belong to the model
+async fn new_print<Conn>(
+    connection: &mut Conn,
+    model: &Model3D,
+    request: PrintRequest,
+) -> Result<NewPrint, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let file = files3d::table
+        .filter(files3d::id.eq(request.file_id))
+        .filter(files3d::model_id.eq(model.id))
+        .first::<File3D>(connection)
+        .await
+        .map_err(|_| {
+            AppError::unprocessable(format!(
+                "File {} is not part of {}",
+                request.file_id, model.title
+            ))
+        })?;
+
+    Ok(NewPrint {
+        model_id: model.id,
+        file_id: Some(file.id),
+        file_path: file.file_path,
+        printer: request.printer,
+        material: request.material,
+        settings: request.settings,
+        success: request.success,
+        notes: request.notes,
+        printed_at: request.printed_at.unwrap_or_else(|| Utc::now().naive_utc()),
+    })
+}
+
+/// Points prints at the entry of their file again after a rescan recreated it
+pub async fn relink_prints<Conn>(
+    connection: &mut Conn,
+    model_id: i32,
+    file: &File3D,
+) -> anyhow::Result<()>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    diesel::update(
+        prints::table
+            .filter(prints::model_id.eq(model_id))
+            .filter(prints::file_path.eq(&file.file_path)),
+    )
+    .set(prints::file_id.eq(file.id))
+    .execute(connection)
+    .await?;
+    Ok(())
+}
+
+pub async fn list_prints(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Json<Vec<PrintResponse>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let model = crate::slug::resolve_model(&mut connection, &slug).await?;
+
+    let prints = prints::table
+        .filter(prints::model_id.eq(model.id))
+        .order((prints::printed_at.desc(), prints::id.desc()))
+        .load::<Print>(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+
+    Ok(Json(
+        prints
+            .iter()
+            .map(|print| PrintResponse::from_print(print, &model, &state.config))
+            .collect(),
+    ))
+}
+
+/// Prints are logged under the model they belong to
+async fn record_print<Conn>(
+    connection: &mut Conn,
+    user: &CurrentUser,
+    action: AuditAction,
+    print_id: i32,
+    model: &Model3D,
+) where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let summary = match action {
+        AuditAction::Create => format!("Logged a print of {}", model.title),
+        AuditAction::Delete => format!("Deleted a print of {}", model.title),
+        _ => format!("Changed a print of {}", model.title),
+    };
+    audit::record(
+        connection,
+        user,
+        action,
+        AuditTarget::new(AuditTargetType::Print, print_id, &model.name),
+        summary,
+    )
+    .await;
+}
+
+pub async fn create_print(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(slug): Path<String>,
+    Json(request): Json<PrintRequest>,
+) -> Result<(StatusCode, Json<PrintResponse>), AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let model = crate::slug::resolve_model(&mut connection, &slug).await?;
+    let new_print = new_print(&mut connection, &model, request).await?;
+
+    let print = diesel::insert_into(prints::table)
+        .values(&new_print)
+        .returning(Print::as_returning())
+        .get_result(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    record_print(
+        &mut connection,
+        &user,
+        AuditAction::Create,
+        print.id,
+        &model,
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PrintResponse::from_print(&print, &model, &state.config)),
+    ))
+}
+
+pub async fn get_print(
+    State(state): State<AppState>,
+    Path(print_id): Path<i32>,
+) -> Result<Json<PrintResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+    let (print, model) = get_print_and_model(&mut connection, print_id).await?;
+
+    Ok(Json(PrintResponse::from_print(
+        &print,
+        &model,
+        &state.config,
+    )))
+}
+
+pub async fn update_print(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(print_id): Path<i32>,
+    Json(request): Json<PrintRequest>,
+) -> Result<Json<PrintResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+    let (_, model) = get_print_and_model(&mut connection, print_id).await?;
+    let changes = new_print(&mut connection, &model, request).await?;
+
+    let print = diesel::update(prints::table.find(print_id))
+        .set(&changes)
+        .returning(Print::as_returning())
+        .get_result(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    record_print(
+        &mut connection,
+        &user,
+        AuditAction::Update,
+        print.id,
+        &model,
+    )
+    .await;
+
+    Ok(Json(PrintResponse::from_print(
+        &print,
+        &model,
+        &state.config,
+    )))
+}
+
+pub async fn delete_print(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(print_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let mut connection = state.pool.get().await?;
+    let (print, model) = get_print_and_model(&mut connection, print_id).await?;
+
+    diesel::delete(prints::table.find(print_id))
+        .execute(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    record_print(
+        &mut connection,
+        &user,
+        AuditAction::Delete,
+        print_id,
+        &model,
+    )
+    .await;
+
+    if let Some(photo) = &print.photo {
+        remove_photo(&state.config, &model, photo).await;
+        if let Err(e) = model.scan(&state.config, &mut connection).await {
+            error!("Scan after print deletion failed: {:?}", e);
+        }
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Removes a photo of [`store_photo`], it stays in a library which became read-only
+async fn remove_photo(config: &Config, model: &Model3D, photo: &str) {
+    let Ok(library) = model.writable_library(config) else {
+        return;
+    };
+    let path = library.path.join(&model.folder_path).join(photo);
+    if let Err(e) = fs::remove_file(&path).await {
+        debug!("Failed to remove print photo {}: {}", path.display(), e);
+    }
+}
+
+/// Streams the photo into the images of the model, returns its path relative to the model folder
+async fn store_photo(
+    config: &Config,
+    model: &Model3D,
+    print: &Print,
+    multipart: &mut Multipart,
+) -> Result<String, AppError> {
+    let mut field = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::new(e.status(), e.body_text()))?
+        .ok_or_else(|| AppError::bad_request("No photo uploaded"))?;
+
+    let file_name = sanitize_filename::sanitize(field.file_name().unwrap_or("photo"));
+    if categorize_file(FsPath::new(&file_name)) != "image" {
+        return Err(AppError::new(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            format!("{} is not a supported image", file_name),
+        ));
+    }
+
+    let model_dir = model
+        .writable_library(config)?
+        .path
+        .join(&model.folder_path);
+    let relative_path = format!("images/print-{}-{}", print.id, file_name);
+    let path = model_dir.join(&relative_path);
+    fs::create_dir_all(model_dir.join("images"))
+        .await
+        .map_err(AppError::internal)?;
+    let mut file = File::create(&path).await.map_err(AppError::internal)?;
+
+    let mut size: u64 = 0;
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| AppError::new(e.status(), e.body_text()))?
+    {
+        size += chunk.len() as u64;
+        if size > config.max_upload_file_size() {
+            drop(file);
+            let _ = fs::remove_file(&path).await;
+            return Err(AppError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!(
+                    "{} exceeds the maximum file size of {} MiB",
+                    file_name, config.max_upload_file_size_mb
+                ),
+            ));
+        }
+        file.write_all(&chunk).await.map_err(AppError::internal)?;
+    }
+    file.flush().await.map_err(AppError::internal)?;
+
+    debug!("Stored print photo {}", path.display());
+    Ok(relative_path)
+}
+
+pub async fn upload_print_photo(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(print_id): Path<i32>,
+    mut multipart: Multipart,
+) -> Result<Json<PrintResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+    let (print, model) = get_print_and_model(&mut connection, print_id).await?;
+
+    let photo = store_photo(&state.config, &model, &print, &mut multipart).await?;
+
+    let updated = diesel::update(prints::table.find(print_id))
+        .set(prints::photo.eq(&photo))
+        .returning(Print::as_returning())
+        .get_result(&mut connection)
+        .await;
+    let updated = match updated {
+        Ok(updated) => updated,
+        Err(e) => {
+            remove_photo(&state.config, &model, &photo).await;
+            return Err(AppError::internal(e));
+        }
+    };
+    // a photo with the same name was overwritten in place
+    if let Some(previous) = print.photo.as_ref().filter(|previous| **previous != photo) {
+        remove_photo(&state.config, &model, previous).await;
+    }
+    let print = updated;
+    record_print(
+        &mut connection,
+        &user,
+        AuditAction::Update,
+        print.id,
+        &model,
+    )
+    .await;
+
+    // the photo shows up in the images of the model as well
+    if let Err(e) = model.scan(&state.config, &mut connection).await {
+        error!("Scan after print photo upload failed: {:?}", e);
+    }
+
+    Ok(Json(PrintResponse::from_print(
+        &print,
+        &model,
+        &state.config,
+    )))
+}
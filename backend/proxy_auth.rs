@@ -0,0 +1,225 @@
+use axum::http::HeaderMap;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::net::IpAddr;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+use crate::auth::{find_user, take_over_local_data, validate_username};
+use crate::schema::users;
+use crate::types::{NewUser, Role, User};
+use crate::Config;
+
+/// Stored for accounts the proxy created, no password matches it
+const NO_PASSWORD: &str = "";
+
+/// The user a trusted reverse proxy sent along with a request
+#[derive(Clone, Debug)]
+pub struct ProxyIdentity {
+    pub username: String,
+    pub email: Option<String>,
+    /// None without a groups header, the stored role is kept then
+    pub groups: Option<Vec<String>>,
+}
+
+/// An address or a CIDR range like 10.0.0.0/8
+fn parse_range(range: &str) -> Option<(IpAddr, u32)> {
+    let (address, prefix) = match range.trim().split_once('/') {
+        Some((address, prefix)) => (address, Some(prefix.parse::<u32>().ok()?)),
+        None => (range.trim(), None),
+    };
+    let address = IpAddr::from_str(address).ok()?.to_canonical();
+    let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+    match prefix {
+        Some(prefix) if prefix > max_prefix => None,
+        Some(prefix) => Some((address, prefix)),
+        None => Some((address, max_prefix)),
+    }
+}
+
+fn in_range(ip: IpAddr, (network, prefix): (IpAddr, u32)) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+pub fn is_trusted(config: &Config, peer: IpAddr) -> bool {
+    let peer = peer.to_canonical();
+    config
+        .trusted_proxies
+        .iter()
+        .filter_map(|range| parse_range(range))
+        .any(|range| in_range(peer, range))
+}
+
+/// `group:role` pairs of PROXY_GROUP_ROLES, invalid ones are skipped
+fn group_roles(config: &Config) -> Vec<(&str, Role)> {
+    config
+        .proxy_group_roles
+        .iter()
+        .filter_map(|mapping| {
+            let (group, role) = mapping.rsplit_once(':')?;
+            Some((group.trim(), Role::from_str(role.trim()).ok()?))
+        })
+        .collect()
+}
+
+/// Warns about entries of the proxy settings that are ignored
+pub fn check_config(config: &Config) {
+    for range in &config.trusted_proxies {
+        if parse_range(range).is_none() {
+            warn!(
+                "Ignoring the trusted proxy {:?}, it is no address or CIDR range",
+                range
+            );
+        }
+    }
+    let valid = group_roles(config).len();
+    if valid < config.proxy_group_roles.len() {
+        warn!(
+            "Ignoring {} entries of PROXY_GROUP_ROLES, they have to look like group:role",
+            config.proxy_group_roles.len() - valid
+        );
+    }
+}
+
+/// The highest role of the mapped groups, the default role if none of the groups is mapped
+pub fn role_for_groups(config: &Config, groups: &[String]) -> Role {
+    group_roles(config)
+        .into_iter()
+        .filter(|(group, _)| groups.iter().any(|member_of| member_of == group))
+        .map(|(_, role)| role)
+        .max()
+        .unwrap_or(config.proxy_default_role)
+}
+
+fn header_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)?
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// The identity headers of a request, only if it came from a trusted proxy
+pub fn identity(
+    config: &Config,
+    headers: &HeaderMap,
+    peer: Option<IpAddr>,
+) -> Option<ProxyIdentity> {
+    let peer = peer?;
+    let username = header_value(headers, &config.proxy_user_header)?;
+    if !is_trusted(config, peer) {
+        warn!(
+            "Ignoring the {} header from {}, it is not a trusted proxy",
+            config.proxy_user_header, peer
+        );
+        return None;
+    }
+
+    Some(ProxyIdentity {
+        username,
+        email: header_value(headers, &config.proxy_email_header),
+        groups: header_value(headers, &config.proxy_groups_header).map(|groups| {
+            groups
+                .split(',')
+                .map(|group| group.trim().to_string())
+                .filter(|group| !group.is_empty())
+                .collect()
+        }),
+    })
+}
+
+/// The client behind trusted proxies according to X-Forwarded-For, otherwise the peer itself
+///
+/// Proxies append to the header, so it is read from the right and the first address which is
+/// not a trusted proxy is the client, everything left of it could be made up by the client.
+pub fn client_ip(config: &Config, headers: &HeaderMap, peer: Option<IpAddr>) -> Option<IpAddr> {
+    let mut client = peer?;
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect();
+    for address in forwarded.into_iter().rev() {
+        if !is_trusted(config, client) {
+            break;
+        }
+        match IpAddr::from_str(address.trim()) {
+            Ok(address) => client = address,
+            Err(_) => break,
+        }
+    }
+    Some(client)
+}
+
+/// The account of a proxy user, created on first sight and kept in sync with the groups and e-mail
+pub async fn provision<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    identity: &ProxyIdentity,
+) -> anyhow::Result<User>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let role = identity
+        .groups
+        .as_ref()
+        .map(|groups| role_for_groups(config, groups));
+
+    if let Some(user) = find_user(connection, &identity.username).await? {
+        let role = role.unwrap_or(user.role());
+        let email = identity.email.clone().or(user.email.clone());
+        if role == user.role() && email == user.email {
+            return Ok(user);
+        }
+        if role != user.role() {
+            info!(
+                "The proxy groups make {} {} instead of {}",
+                user.username,
+                role.as_str(),
+                user.role
+            );
+        }
+        return Ok(diesel::update(users::table.find(user.id))
+            .set((users::role.eq(role.as_str()), users::email.eq(email)))
+            .returning(User::as_returning())
+            .get_result(connection)
+            .await?);
+    }
+
+    validate_username(&identity.username).map_err(anyhow::Error::msg)?;
+    let user_count: i64 = users::table.count().get_result(connection).await?;
+    let role = role.unwrap_or(config.proxy_default_role);
+    let user = diesel::insert_into(users::table)
+        .values(&NewUser {
+            username: identity.username.clone(),
+            password_hash: NO_PASSWORD.to_string(),
+            role: role.as_str().to_string(),
+            email: identity.email.clone(),
+        })
+        .returning(User::as_returning())
+        .get_result(connection)
+        .await?;
+    info!(
+        "Created {} {} for the reverse proxy",
+        role.as_str(),
+        user.username
+    );
+
+    if user_count == 0 {
+        take_over_local_data(connection, user.id).await?;
+    }
+    Ok(user)
+}
@@ -0,0 +1,426 @@
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::collections::BTreeMap;
+use std::path::{Path as FsPath, PathBuf};
+use std::str::FromStr;
+use tokio::fs;
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::audit::{self, AuditTarget};
+use crate::error::AppError;
+use crate::schema::{model_revisions, models3d, revision_files};
+use crate::types::{
+    AuditAction, DetailedModelResponse, Model3D, ModelRevision, NewModelRevision, NewRevisionFile,
+    RestoreRevisionParams, RevisionChange, RevisionFile, RevisionFileResponse, RevisionReason,
+    RevisionResponse,
+};
+use crate::Config;
+
+const MANIFEST: &str = "modelpack.json";
+
+/// Files below `dir` keyed by their path relative to it
+fn list_files(dir: &FsPath) -> anyhow::Result<BTreeMap<String, PathBuf>> {
+    let mut files = BTreeMap::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+
+    for entry in walkdir::WalkDir::new(dir) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        if let Some(relative) = pathdiff::diff_paths(entry.path(), dir) {
+            files.insert(
+                relative.to_string_lossy().into_owned(),
+                entry.path().to_path_buf(),
+            );
+        }
+    }
+    Ok(files)
+}
+
+async fn same_content(a: &FsPath, b: &FsPath) -> anyhow::Result<bool> {
+    if fs::metadata(a).await?.len() != fs::metadata(b).await?.len() {
+        return Ok(false);
+    }
+    Ok(sha256::try_async_digest(a).await? == sha256::try_async_digest(b).await?)
+}
+
+async fn copy_file(src: &FsPath, dest: &FsPath) -> anyhow::Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::copy(src, dest).await?;
+    Ok(())
+}
+
+/// Changes from the model folder `old` to its next version `new`
+pub async fn diff_model_dirs(
+    old: &FsPath,
+    new: &FsPath,
+) -> anyhow::Result<Vec<(String, RevisionChange)>> {
+    let old_files = list_files(old)?;
+    let new_files = list_files(new)?;
+    let mut changes = Vec::new();
+
+    for (file_path, new_file) in &new_files {
+        match old_files.get(file_path) {
+            None => changes.push((file_path.clone(), RevisionChange::Added)),
+            Some(old_file) => {
+                if !same_content(old_file, new_file).await? {
+                    changes.push((file_path.clone(), RevisionChange::Replaced));
+                }
+            }
+        }
+    }
+    for file_path in old_files.keys() {
+        if !new_files.contains_key(file_path) {
+            changes.push((file_path.clone(), RevisionChange::Deleted));
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Copy of the files a change is about to overwrite or remove, it only shows up
+/// in the history once it is recorded
+pub struct PendingRevision {
+    model_id: i32,
+    reason: RevisionReason,
+    storage_dir: String,
+    files: Vec<(String, RevisionChange, bool)>,
+}
+
+/// Copies the files of `model_dir` which are replaced or deleted by `changes`
+/// and the manifest into a new revision
+pub async fn snapshot(
+    config: &Config,
+    model_id: i32,
+    reason: RevisionReason,
+    model_dir: &FsPath,
+    changes: Vec<(String, RevisionChange)>,
+) -> anyhow::Result<PendingRevision> {
+    let mut pending = PendingRevision {
+        model_id,
+        reason,
+        storage_dir: format!("{}/{}", model_id, Uuid::new_v4()),
+        files: Vec::new(),
+    };
+
+    if let Err(e) = pending.store_files(config, model_dir, changes).await {
+        pending.discard(config).await;
+        return Err(e);
+    }
+    Ok(pending)
+}
+
+impl PendingRevision {
+    async fn store_files(
+        &mut self,
+        config: &Config,
+        model_dir: &FsPath,
+        changes: Vec<(String, RevisionChange)>,
+    ) -> anyhow::Result<()> {
+        let target = config.revisions_dir.join(&self.storage_dir);
+        let mut has_manifest = false;
+
+        for (file_path, change) in changes {
+            let source = model_dir.join(&file_path);
+            let stored = matches!(change, RevisionChange::Replaced | RevisionChange::Deleted)
+                && source.is_file();
+            if stored {
+                copy_file(&source, &target.join(&file_path)).await?;
+            }
+            has_manifest |= file_path == MANIFEST;
+            self.files.push((file_path, change, stored));
+        }
+
+        let manifest = model_dir.join(MANIFEST);
+        if !has_manifest && manifest.is_file() {
+            copy_file(&manifest, &target.join(MANIFEST)).await?;
+            self.files
+                .push((MANIFEST.to_string(), RevisionChange::Unchanged, true));
+        }
+
+        Ok(())
+    }
+
+    pub async fn record<Conn>(&self, connection: &mut Conn) -> anyhow::Result<()>
+    where
+        Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+    {
+        let revision_id: i32 = diesel::insert_into(model_revisions::table)
+            .values(&NewModelRevision {
+                model_id: self.model_id,
+                reason: self.reason.as_str().to_string(),
+                storage_dir: self.storage_dir.clone(),
+            })
+            .returning(model_revisions::id)
+            .get_result(connection)
+            .await?;
+
+        for (file_path, change, stored) in &self.files {
+            diesel::insert_into(revision_files::table)
+                .values(&NewRevisionFile {
+                    revision_id,
+                    file_path: file_path.clone(),
+                    change_type: change.as_str().to_string(),
+                    stored: *stored,
+                })
+                .execute(connection)
+                .await?;
+        }
+
+        debug!(
+            "Recorded revision {} of model {}",
+            revision_id, self.model_id
+        );
+        Ok(())
+    }
+
+    /// Drops the copied files if the change did not go through
+    pub async fn discard(&self, config: &Config) {
+        let target = config.revisions_dir.join(&self.storage_dir);
+        if let Err(e) = fs::remove_dir_all(&target).await {
+            debug!("Failed to remove revision {}: {}", target.display(), e);
+        }
+    }
+}
+
+pub async fn get_revisions<Conn>(
+    connection: &mut Conn,
+    model_id: i32,
+) -> anyhow::Result<Vec<RevisionResponse>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let revisions = model_revisions::table
+        .filter(model_revisions::model_id.eq(model_id))
+        .order(model_revisions::id.desc())
+        .load::<ModelRevision>(connection)
+        .await?;
+
+    let files = RevisionFile::belonging_to(&revisions)
+        .order(revision_files::file_path.asc())
+        .load::<RevisionFile>(connection)
+        .await?
+        .grouped_by(&revisions);
+
+    revisions
+        .into_iter()
+        .zip(files)
+        .map(|(revision, files)| {
+            Ok(RevisionResponse {
+                id: revision.id,
+                reason: RevisionReason::from_str(&revision.reason)?,
+                date_added: revision.date_added,
+                files: files
+                    .into_iter()
+                    .map(|file| {
+                        Ok(RevisionFileResponse {
+                            change: RevisionChange::from_str(&file.change_type)?,
+                            file_path: file.file_path,
+                            restorable: file.stored,
+                        })
+                    })
+                    .collect::<anyhow::Result<_>>()?,
+            })
+        })
+        .collect()
+}
+
+/// Writes the stored files of a revision back into the model and removes the files it added,
+/// only `file_path` if given. The overwritten and removed versions become a revision of their
+/// own, so a restore can be undone.
+pub async fn restore<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    model: &Model3D,
+    revision: &ModelRevision,
+    file_path: Option<&str>,
+) -> anyhow::Result<()>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let mut stored = list_files(&config.revisions_dir.join(&revision.storage_dir))?;
+    let mut added: Vec<String> = revision_files::table
+        .filter(revision_files::revision_id.eq(revision.id))
+        .filter(revision_files::change_type.eq(RevisionChange::Added.as_str()))
+        .select(revision_files::file_path)
+        .load(connection)
+        .await?;
+    if let Some(file_path) = file_path {
+        stored.retain(|path, _| path == file_path);
+        added.retain(|path| path == file_path);
+        if stored.is_empty() && added.is_empty() {
+            anyhow::bail!("{} is not part of revision {}", file_path, revision.id);
+        }
+    }
+
+    let model_dir = model.absolute_path(config)?;
+    let mut changes = Vec::new();
+    for (file_path, source) in &stored {
+        let current = model_dir.join(file_path);
+        if !current.is_file() {
+            changes.push((file_path.clone(), RevisionChange::Added));
+        } else if !same_content(&current, source).await? {
+            changes.push((file_path.clone(), RevisionChange::Replaced));
+        }
+    }
+    for file_path in &added {
+        if model_dir.join(file_path).is_file() {
+            changes.push((file_path.clone(), RevisionChange::Deleted));
+        }
+    }
+    if changes.is_empty() {
+        debug!("Revision {} matches the current files", revision.id);
+        return Ok(());
+    }
+
+    let pending = snapshot(
+        config,
+        model.id,
+        RevisionReason::Restore,
+        &model_dir,
+        changes.clone(),
+    )
+    .await?;
+    if let Err(e) = pending.record(connection).await {
+        pending.discard(config).await;
+        return Err(e);
+    }
+
+    for (file_path, change) in changes {
+        let current = model_dir.join(&file_path);
+        if change == RevisionChange::Deleted {
+            fs::remove_file(&current).await?;
+            debug!("Removed {} added by revision {}", file_path, revision.id);
+        } else {
+            copy_file(&stored[&file_path], &current).await?;
+            debug!("Restored {} from revision {}", file_path, revision.id);
+        }
+    }
+
+    model.scan(config, connection).await
+}
+
+/// Removes the history of a model including the stored files
+pub async fn delete_revisions<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    model_id: i32,
+) -> anyhow::Result<()>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let revision_ids = model_revisions::table
+        .filter(model_revisions::model_id.eq(model_id))
+        .select(model_revisions::id)
+        .load::<i32>(connection)
+        .await?;
+
+    diesel::delete(revision_files::table.filter(revision_files::revision_id.eq_any(&revision_ids)))
+        .execute(connection)
+        .await?;
+    diesel::delete(model_revisions::table.filter(model_revisions::id.eq_any(&revision_ids)))
+        .execute(connection)
+        .await?;
+
+    let storage = config.revisions_dir.join(model_id.to_string());
+    if storage.exists() {
+        fs::remove_dir_all(&storage).await?;
+    }
+    Ok(())
+}
+
+pub async fn list_revisions(
+    State(state): State<crate::AppState>,
+    Path(slug): Path<String>,
+) -> Result<Json<Vec<RevisionResponse>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let model = crate::slug::resolve_model(&mut connection, &slug).await?;
+
+    get_revisions(&mut connection, model.id)
+        .await
+        .map(Json)
+        .map_err(|e| AppError::internal(e.to_string()))
+}
+
+pub async fn restore_revision(
+    State(state): State<crate::AppState>,
+    Extension(user): Extension<crate::auth::CurrentUser>,
+    Path((slug, revision_id)): Path<(String, i32)>,
+    Query(params): Query<RestoreRevisionParams>,
+) -> Result<Json<DetailedModelResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let model = crate::slug::resolve_model(&mut connection, &slug).await?;
+    model.writable_library(&state.config)?;
+
+    let revision = model_revisions::table
+        .filter(model_revisions::id.eq(revision_id))
+        .filter(model_revisions::model_id.eq(model.id))
+        .first::<ModelRevision>(&mut connection)
+        .await
+        .map_err(|_| AppError::not_found(format!("Revision {} not found", revision_id)))?;
+
+    if let Some(file_path) = &params.file {
+        let stored = revision_files::table
+            .filter(revision_files::revision_id.eq(revision.id))
+            .filter(revision_files::file_path.eq(file_path))
+            .filter(revision_files::stored.eq(true))
+            .count()
+            .get_result::<i64>(&mut connection)
+            .await
+            .unwrap_or(0);
+        if stored == 0 {
+            return Err(AppError::not_found(format!(
+                "{} is not part of revision {}",
+                file_path, revision.id
+            )));
+        }
+    }
+
+    if let Err(e) = restore(
+        &state.config,
+        &mut connection,
+        &model,
+        &revision,
+        params.file.as_deref(),
+    )
+    .await
+    {
+        error!("Restoring revision {} failed: {:?}", revision.id, e);
+        return Err(AppError::internal("Failed to restore revision"));
+    }
+
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Restore,
+        AuditTarget::model(&model),
+        match &params.file {
+            Some(file_path) => format!("Restored {} from revision {}", file_path, revision.id),
+            None => format!("Restored revision {}", revision.id),
+        },
+    )
+    .await;
+
+    let model = models3d::table
+        .find(model.id)
+        .first::<Model3D>(&mut connection)
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?;
+    let response =
+        DetailedModelResponse::from_model_3d(&model, &state.config, user.id, &mut connection)
+            .await
+            .map_err(|e| AppError::internal(e.to_string()))?;
+    Ok(Json(response))
+}
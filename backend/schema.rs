@@ -1,10 +1,67 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_tokens (id) {
+        id -> Integer,
+        user_id -> Integer,
+        name -> Text,
+        token_prefix -> Text,
+        token_hash -> Text,
+        scopes -> Text,
+        date_added -> Nullable<Timestamp>,
+        last_used_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    audit_log (id) {
+        id -> Integer,
+        user_id -> Integer,
+        username -> Text,
+        action -> Text,
+        target_type -> Text,
+        target_id -> Nullable<Integer>,
+        target_name -> Nullable<Text>,
+        summary -> Text,
+        ip_address -> Nullable<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    author_aliases (id) {
+        id -> Integer,
+        author_id -> Integer,
+        alias -> Text,
+        alias_key -> Text,
+    }
+}
+
+diesel::table! {
+    authors (id) {
+        id -> Integer,
+        name -> Text,
+        profile_url -> Nullable<Text>,
+        date_added -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     collections (id) {
         id -> Integer,
         name -> Text,
         date_added -> Nullable<Timestamp>,
+        owner_id -> Nullable<Integer>,
+        visibility -> Text,
+    }
+}
+
+diesel::table! {
+    favourites (id) {
+        id -> Integer,
+        user_id -> Integer,
+        model_id -> Integer,
+        date_added -> Nullable<Timestamp>,
     }
 }
 
@@ -29,6 +86,16 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    model_revisions (id) {
+        id -> Integer,
+        model_id -> Integer,
+        reason -> Text,
+        storage_dir -> Text,
+        date_added -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     models3d (id) {
         id -> Integer,
@@ -41,12 +108,131 @@ diesel::table! {
         date_added -> Nullable<Timestamp>,
         images -> Text,
         description -> Text,
-        favourite -> Bool,
+        license_spdx -> Nullable<Text>,
+        license_non_commercial -> Bool,
+        license_share_alike -> Bool,
+        license_attribution -> Bool,
+        author_id -> Nullable<Integer>,
+        library -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    prints (id) {
+        id -> Integer,
+        model_id -> Integer,
+        file_id -> Nullable<Integer>,
+        file_path -> Text,
+        printer -> Nullable<Text>,
+        material -> Nullable<Text>,
+        settings -> Nullable<Text>,
+        success -> Bool,
+        notes -> Text,
+        photo -> Nullable<Text>,
+        printed_at -> Timestamp,
+        date_added -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    revision_files (id) {
+        id -> Integer,
+        revision_id -> Integer,
+        file_path -> Text,
+        change_type -> Text,
+        stored -> Bool,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        id -> Integer,
+        user_id -> Integer,
+        token_hash -> Text,
+        date_added -> Nullable<Timestamp>,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    share_links (id) {
+        id -> Integer,
+        model_id -> Nullable<Integer>,
+        collection_id -> Nullable<Integer>,
+        token_prefix -> Text,
+        token_hash -> Text,
+        created_by -> Integer,
+        date_added -> Nullable<Timestamp>,
+        expires_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    slug_history (id) {
+        id -> Integer,
+        model_id -> Integer,
+        slug -> Text,
+        date_added -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    trash (id) {
+        id -> Integer,
+        kind -> Text,
+        model_id -> Integer,
+        title -> Text,
+        original_path -> Text,
+        trash_path -> Text,
+        metadata -> Nullable<Text>,
+        date_deleted -> Timestamp,
+        library -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    users (id) {
+        id -> Integer,
+        username -> Text,
+        password_hash -> Text,
+        date_added -> Nullable<Timestamp>,
+        role -> Text,
+        email -> Nullable<Text>,
     }
 }
 
+diesel::joinable!(api_tokens -> users (user_id));
+diesel::joinable!(author_aliases -> authors (author_id));
+diesel::joinable!(favourites -> models3d (model_id));
 diesel::joinable!(files3d -> models3d (model_id));
 diesel::joinable!(model_collections -> collections (collection_id));
 diesel::joinable!(model_collections -> models3d (model_id));
+diesel::joinable!(model_revisions -> models3d (model_id));
+diesel::joinable!(models3d -> authors (author_id));
+diesel::joinable!(prints -> files3d (file_id));
+diesel::joinable!(prints -> models3d (model_id));
+diesel::joinable!(revision_files -> model_revisions (revision_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(share_links -> collections (collection_id));
+diesel::joinable!(share_links -> models3d (model_id));
+diesel::joinable!(slug_history -> models3d (model_id));
 
-diesel::allow_tables_to_appear_in_same_query!(collections, files3d, model_collections, models3d,);
+diesel::allow_tables_to_appear_in_same_query!(
+    api_tokens,
+    audit_log,
+    author_aliases,
+    authors,
+    collections,
+    favourites,
+    files3d,
+    model_collections,
+    model_revisions,
+    models3d,
+    prints,
+    revision_files,
+    sessions,
+    share_links,
+    slug_history,
+    trash,
+    users,
+);
@@ -0,0 +1,460 @@
+use axum::{
+    body::Body,
+    extract::{Path, Request, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::path::{Component, PathBuf};
+use tower_http::services::ServeFile;
+use tracing::{error, info};
+
+use crate::audit::{self, AuditTarget};
+use crate::auth::{generate_token, hash_token, CurrentUser};
+use crate::error::AppError;
+use crate::openapi::FileBody;
+use crate::schema::{collections, files3d, models3d, share_links};
+use crate::slug::{self, SlugMatch};
+use crate::stream_dl::{self, ZipArchive};
+use crate::types::{
+    AuditAction, AuditTargetType, Collection, CreateShareLinkRequest, CreatedShareLinkResponse,
+    DetailedCollectionResponse, DetailedModelResponse, Model3D, NewShareLink, ShareLink,
+    ShareLinkResponse, SharedResponse,
+};
+use crate::{AppState, Config};
+
+const DEFAULT_EXPIRES_IN_DAYS: i32 = 7;
+const MAX_EXPIRES_IN_DAYS: i32 = 365;
+/// Characters of a token kept in the clear to tell links apart
+const DISPLAY_PREFIX_LENGTH: usize = 8;
+
+/// Expired, revoked and made up tokens all look the same
+fn not_found() -> AppError {
+    AppError::not_found("Share link not found or expired")
+}
+
+fn shared_path(token: &str) -> String {
+    format!("/api/shared/{}", token)
+}
+
+async fn create_link<Conn>(
+    connection: &mut Conn,
+    user: &CurrentUser,
+    model_id: Option<i32>,
+    collection_id: Option<i32>,
+    request: CreateShareLinkRequest,
+) -> Result<(StatusCode, Json<CreatedShareLinkResponse>), AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let expires_in_days = request.expires_in_days.unwrap_or(DEFAULT_EXPIRES_IN_DAYS);
+    if !(1..=MAX_EXPIRES_IN_DAYS).contains(&expires_in_days) {
+        return Err(AppError::unprocessable(format!(
+            "expires_in_days must be between 1 and {}",
+            MAX_EXPIRES_IN_DAYS
+        )));
+    }
+
+    let now = Utc::now().naive_utc();
+    diesel::delete(share_links::table.filter(share_links::expires_at.le(now)))
+        .execute(connection)
+        .await
+        .map_err(AppError::internal)?;
+
+    let token = generate_token();
+    let share_link = diesel::insert_into(share_links::table)
+        .values(&NewShareLink {
+            model_id,
+            collection_id,
+            token_prefix: token[..DISPLAY_PREFIX_LENGTH].to_string(),
+            token_hash: hash_token(&token),
+            created_by: user.id,
+            expires_at: now + Duration::days(expires_in_days as i64),
+        })
+        .returning(ShareLink::as_returning())
+        .get_result(connection)
+        .await
+        .map_err(AppError::internal)?;
+    let shared = match (model_id, collection_id) {
+        (Some(id), _) => format!("model {}", id),
+        (_, id) => format!("collection {}", id.unwrap_or_default()),
+    };
+    info!(
+        "{} shared {} until {}",
+        user.username, shared, share_link.expires_at
+    );
+    audit::record(
+        connection,
+        user,
+        AuditAction::Create,
+        AuditTarget::new(
+            AuditTargetType::ShareLink,
+            share_link.id,
+            &share_link.token_prefix,
+        ),
+        format!("Shared {} until {}", shared, share_link.expires_at),
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedShareLinkResponse {
+            url: shared_path(&token),
+            token,
+            share_link: ShareLinkResponse::from(&share_link),
+        }),
+    ))
+}
+
+pub async fn share_model(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(slug): Path<String>,
+    Json(request): Json<CreateShareLinkRequest>,
+) -> Result<(StatusCode, Json<CreatedShareLinkResponse>), AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let model = match slug::find_model_by_slug(&mut connection, &slug)
+        .await
+        .map_err(AppError::internal)?
+    {
+        Some(SlugMatch::Current(model)) | Some(SlugMatch::Previous(model)) => model,
+        None => return Err(AppError::not_found("Model not found")),
+    };
+    create_link(&mut connection, &user, Some(model.id), None, request).await
+}
+
+pub async fn share_collection(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(id): Path<i32>,
+    Json(request): Json<CreateShareLinkRequest>,
+) -> Result<(StatusCode, Json<CreatedShareLinkResponse>), AppError> {
+    let mut connection = state.pool.get().await?;
+
+    // private collections of others stay hidden
+    let collection = collections::table
+        .find(id)
+        .first::<Collection>(&mut connection)
+        .await
+        .optional()
+        .map_err(AppError::internal)?
+        .filter(|collection| collection.can_view(&user))
+        .ok_or_else(|| AppError::not_found("Collection not found"))?;
+    create_link(&mut connection, &user, None, Some(collection.id), request).await
+}
+
+/// Share links created by the current user, admins get the links of all users
+pub async fn list_share_links(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+) -> Result<Json<Vec<ShareLinkResponse>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let mut query = share_links::table
+        .filter(share_links::expires_at.gt(Utc::now().naive_utc()))
+        .order(share_links::date_added.desc())
+        .into_boxed();
+    if !user.is_admin() {
+        query = query.filter(share_links::created_by.eq(user.id));
+    }
+    let links = query
+        .load::<ShareLink>(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+
+    Ok(Json(links.iter().map(ShareLinkResponse::from).collect()))
+}
+
+/// Deletes a share link, it stops working immediately
+pub async fn revoke_share_link(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(link_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let link = share_links::table
+        .find(link_id)
+        .first::<ShareLink>(&mut connection)
+        .await
+        .optional()
+        .map_err(AppError::internal)?
+        .filter(|link| link.created_by == user.id || user.is_admin())
+        .ok_or_else(|| AppError::not_found(format!("Share link {} not found", link_id)))?;
+
+    diesel::delete(share_links::table.find(link.id))
+        .execute(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    info!(
+        "{} revoked the share link {}",
+        user.username, link.token_prefix
+    );
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Delete,
+        AuditTarget::new(AuditTargetType::ShareLink, link.id, &link.token_prefix),
+        "Revoked the share link",
+    )
+    .await;
+
+    Ok(StatusCode::OK)
+}
+
+/// The link of a token unless it expired or was revoked
+async fn valid_link<Conn>(connection: &mut Conn, token: &str) -> Result<ShareLink, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    share_links::table
+        .filter(share_links::token_hash.eq(hash_token(token)))
+        .filter(share_links::expires_at.gt(Utc::now().naive_utc()))
+        .first::<ShareLink>(connection)
+        .await
+        .optional()
+        .map_err(AppError::internal)?
+        .ok_or_else(not_found)
+}
+
+/// Models a link grants access to, a shared collection includes models added later on
+async fn shared_models<Conn>(
+    connection: &mut Conn,
+    link: &ShareLink,
+) -> anyhow::Result<Vec<Model3D>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    if let Some(model_id) = link.model_id {
+        return Ok(models3d::table
+            .find(model_id)
+            .load::<Model3D>(connection)
+            .await?);
+    }
+    let collection = collections::table
+        .find(link.collection_id.unwrap_or_default())
+        .first::<Collection>(connection)
+        .await
+        .optional()?;
+    match collection {
+        Some(collection) => collection.get_models(connection).await,
+        None => Ok(Vec::new()),
+    }
+}
+
+async fn shared_model<Conn>(
+    connection: &mut Conn,
+    link: &ShareLink,
+    slug: &str,
+) -> Result<Model3D, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    shared_models(connection, link)
+        .await
+        .map_err(AppError::internal)?
+        .into_iter()
+        .find(|model| model.name == slug)
+        .ok_or_else(|| AppError::not_found("Model not found"))
+}
+
+/// Points a file or preview URL at the share routes, the regular ones need a login,
+/// files are addressed by the library name followed by the path inside the library
+fn shared_url(config: &Config, token: &str, url: &str) -> String {
+    let library_file = config.libraries().iter().find_map(|library| {
+        url.strip_prefix(&format!("{}/", library.asset_prefix))
+            .map(|path| (&library.name, path))
+    });
+    if let Some((library, path)) = library_file {
+        format!("{}/files/{}/{}", shared_path(token), library, path)
+    } else if let Some(path) = url.strip_prefix(&format!("{}/", config.cache_prefix)) {
+        format!("{}/previews/{}", shared_path(token), path)
+    } else {
+        url.to_string()
+    }
+}
+
+async fn shared_model_response<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    token: &str,
+    model: &Model3D,
+) -> Result<DetailedModelResponse, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let mut response =
+        DetailedModelResponse::from_model_3d(model, config, CurrentUser::PUBLIC_ID, connection)
+            .await
+            .map_err(AppError::internal)?;
+    for image in response.images.iter_mut() {
+        *image = shared_url(config, token, image);
+    }
+    for file in response.files.iter_mut() {
+        file.file_path = shared_url(config, token, &file.file_path);
+        file.preview_image = file
+            .preview_image
+            .as_ref()
+            .map(|preview| shared_url(config, token, preview));
+    }
+    // the print log belongs to the team, not to the model
+    response.prints.clear();
+    Ok(response)
+}
+
+/// The shared model or collection, reachable without a login
+pub async fn get_shared(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Json<SharedResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+    let link = valid_link(&mut connection, &token).await?;
+
+    let mut shared = SharedResponse {
+        expires_at: link.expires_at,
+        model: None,
+        collection: None,
+    };
+    if link.model_id.is_some() {
+        let model = shared_models(&mut connection, &link)
+            .await
+            .map_err(AppError::internal)?
+            .pop()
+            .ok_or_else(not_found)?;
+        shared.model =
+            Some(shared_model_response(&state.config, &mut connection, &token, &model).await?);
+    } else {
+        let collection = collections::table
+            .find(link.collection_id.unwrap_or_default())
+            .first::<Collection>(&mut connection)
+            .await
+            .optional()
+            .map_err(AppError::internal)?
+            .ok_or_else(not_found)?;
+        let mut response = DetailedCollectionResponse::from_collection(
+            &collection,
+            &state.config,
+            CurrentUser::PUBLIC_ID,
+            &mut connection,
+        )
+        .await
+        .map_err(AppError::internal)?;
+        response.owner_id = None;
+        for model in response.models.iter_mut() {
+            for image in model.images.iter_mut() {
+                *image = shared_url(&state.config, &token, image);
+            }
+        }
+        shared.collection = Some(response);
+    }
+
+    Ok(Json(shared))
+}
+
+pub async fn get_shared_model(
+    State(state): State<AppState>,
+    Path((token, slug)): Path<(String, String)>,
+) -> Result<Json<DetailedModelResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+    let link = valid_link(&mut connection, &token).await?;
+
+    let model = shared_model(&mut connection, &link, &slug).await?;
+    Ok(Json(
+        shared_model_response(&state.config, &mut connection, &token, &model).await?,
+    ))
+}
+
+pub async fn download_shared_model(
+    State(state): State<AppState>,
+    Path((token, slug)): Path<(String, String)>,
+) -> Result<ZipArchive, AppError> {
+    let mut connection = state.pool.get().await?;
+    let link = valid_link(&mut connection, &token).await?;
+
+    let model = shared_model(&mut connection, &link, &slug).await?;
+    let library = model.library(&state.config).map_err(|_| not_found())?;
+    stream_dl::zip_folder_stream(&library.path, std::path::Path::new(&model.folder_path)).await
+}
+
+/// Rejects `..` and absolute paths, a link must not reach outside of the shared folders
+fn is_plain_relative(path: &str) -> bool {
+    std::path::Path::new(path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+}
+
+async fn serve_file(path: PathBuf, request: Request) -> Result<FileBody, AppError> {
+    match ServeFile::new(path).try_call(request).await {
+        Ok(response) => Ok(FileBody(response.map(Body::new))),
+        Err(e) => {
+            error!("Failed to serve a shared file: {:?}", e);
+            Err(AppError::internal(e))
+        }
+    }
+}
+
+/// A file inside the folder of a shared model
+pub async fn get_shared_file(
+    State(state): State<AppState>,
+    Path((token, path)): Path<(String, String)>,
+    request: Request,
+) -> Result<FileBody, AppError> {
+    if !is_plain_relative(&path) {
+        return Err(not_found());
+    }
+    let mut connection = state.pool.get().await?;
+    let link = valid_link(&mut connection, &token).await?;
+
+    let (library_name, path) = path.split_once('/').ok_or_else(not_found)?;
+    let library = state
+        .config
+        .library_named(library_name)
+        .ok_or_else(not_found)?;
+
+    let shared = shared_models(&mut connection, &link)
+        .await
+        .map_err(AppError::internal)?
+        .into_iter()
+        .any(|model| {
+            model.library.as_deref() == library.key()
+                && path.starts_with(&format!("{}/", model.folder_path))
+        });
+    if !shared {
+        return Err(not_found());
+    }
+    serve_file(library.path.join(path), request).await
+}
+
+/// A rendered preview of a file of a shared model
+pub async fn get_shared_preview(
+    State(state): State<AppState>,
+    Path((token, preview)): Path<(String, String)>,
+    request: Request,
+) -> Result<FileBody, AppError> {
+    if !is_plain_relative(&preview) {
+        return Err(not_found());
+    }
+    let mut connection = state.pool.get().await?;
+    let link = valid_link(&mut connection, &token).await?;
+
+    let model_ids: Vec<i32> = shared_models(&mut connection, &link)
+        .await
+        .map_err(AppError::internal)?
+        .iter()
+        .map(|model| model.id)
+        .collect();
+    let previews: i64 = files3d::table
+        .filter(files3d::model_id.eq_any(model_ids))
+        .filter(files3d::preview_image.eq(&preview))
+        .count()
+        .get_result(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    if previews == 0 {
+        return Err(not_found());
+    }
+    serve_file(state.config.preview_cache_dir.join(&preview), request).await
+}
@@ -0,0 +1,254 @@
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use diesel::prelude::*;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use tracing::debug;
+
+use crate::audit::{self, AuditTarget};
+use crate::auth::CurrentUser;
+use crate::error::AppError;
+use crate::schema::{models3d, slug_history};
+use crate::types::{AuditAction, Model3D, NewSlugHistory, UpdateSlugRequest, UpdateSlugResponse};
+
+/// Used when a title consists only of characters without a transliteration
+const FALLBACK_SLUG: &str = "model";
+
+/// Turns a title into a URL slug, non-Latin scripts are transliterated first
+///
+/// Slugs of only digits get a prefix, as numbers address models by id.
+pub fn slugify(title: &str) -> String {
+    let slug = str_slug::slug(deunicode::deunicode(title));
+    if slug.is_empty() {
+        FALLBACK_SLUG.to_string()
+    } else if slug.chars().all(|c| c.is_ascii_digit()) {
+        format!("{}-{}", FALLBACK_SLUG, slug)
+    } else {
+        slug
+    }
+}
+
+/// Whether `slug` is the current or a previous slug of any model other than `model_id`
+pub async fn slug_is_taken<Conn>(
+    connection: &mut Conn,
+    slug: &str,
+    model_id: Option<i32>,
+) -> anyhow::Result<bool>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let exclude_id = model_id.unwrap_or(-1);
+
+    let current: i64 = models3d::table
+        .filter(models3d::name.eq(slug))
+        .filter(models3d::id.ne(exclude_id))
+        .count()
+        .get_result(connection)
+        .await?;
+    if current > 0 {
+        return Ok(true);
+    }
+
+    // entries of deleted models are left behind as sqlite does not enforce the cascade
+    let previous: i64 = slug_history::table
+        .inner_join(models3d::table)
+        .filter(slug_history::slug.eq(slug))
+        .filter(slug_history::model_id.ne(exclude_id))
+        .count()
+        .get_result(connection)
+        .await?;
+
+    Ok(previous > 0)
+}
+
+/// Slug for `title` which is not used by any other model, collisions get a numeric suffix
+pub async fn unique_slug<Conn>(
+    connection: &mut Conn,
+    title: &str,
+    model_id: Option<i32>,
+) -> anyhow::Result<String>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let base = slugify(title);
+    let mut candidate = base.clone();
+    let mut suffix = 2;
+
+    while slug_is_taken(connection, &candidate, model_id).await? {
+        candidate = format!("{}-{}", base, suffix);
+        suffix += 1;
+    }
+
+    Ok(candidate)
+}
+
+pub enum SlugMatch {
+    Current(Model3D),
+    /// The slug was used by the model before it got renamed
+    Previous(Model3D),
+}
+
+impl SlugMatch {
+    pub fn into_model(self) -> Model3D {
+        match self {
+            SlugMatch::Current(model) | SlugMatch::Previous(model) => model,
+        }
+    }
+}
+
+pub async fn find_model_by_slug<Conn>(
+    connection: &mut Conn,
+    slug: &str,
+) -> anyhow::Result<Option<SlugMatch>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let current = models3d::table
+        .filter(models3d::name.eq(slug))
+        .first::<Model3D>(connection)
+        .await
+        .optional()?;
+    if let Some(model) = current {
+        return Ok(Some(SlugMatch::Current(model)));
+    }
+
+    let previous = slug_history::table
+        .inner_join(models3d::table)
+        .filter(slug_history::slug.eq(slug))
+        .select(Model3D::as_select())
+        .first::<Model3D>(connection)
+        .await
+        .optional()?;
+
+    Ok(previous.map(SlugMatch::Previous))
+}
+
+/// Looks up a model by its current or any previous slug
+pub async fn resolve_model<Conn>(connection: &mut Conn, slug: &str) -> Result<Model3D, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    find_model_by_slug(connection, slug)
+        .await?
+        .map(SlugMatch::into_model)
+        .ok_or_else(|| AppError::not_found(format!("No model with slug {}", slug)))
+}
+
+/// Looks up a model by its id or by a slug, `slugify` never gives a slug of only digits
+pub async fn resolve_model_ref<Conn>(connection: &mut Conn, key: &str) -> Result<Model3D, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    if let Ok(id) = key.parse::<i32>() {
+        let model = models3d::table
+            .find(id)
+            .first::<Model3D>(connection)
+            .await
+            .optional()?;
+        if let Some(model) = model {
+            return Ok(model);
+        }
+    }
+    resolve_model(connection, key).await
+}
+
+/// Renames the slug of a model and keeps the old one in the history so existing links redirect
+pub async fn change_slug<Conn>(
+    connection: &mut Conn,
+    model: &Model3D,
+    new_slug: &str,
+) -> anyhow::Result<()>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    if model.name == new_slug {
+        return Ok(());
+    }
+    if slug_is_taken(connection, new_slug, Some(model.id)).await? {
+        anyhow::bail!("The slug {} is already in use", new_slug);
+    }
+
+    let model_id = model.id;
+    let old_slug = model.name.clone();
+    let new_slug = new_slug.to_string();
+
+    connection
+        .transaction::<_, anyhow::Error, _>(|conn| {
+            async move {
+                // drops stale entries of deleted models and a previous slug which is reused now
+                diesel::delete(
+                    slug_history::table.filter(
+                        slug_history::slug.eq_any(vec![old_slug.clone(), new_slug.clone()]),
+                    ),
+                )
+                .execute(conn)
+                .await?;
+
+                if !old_slug.is_empty() {
+                    diesel::insert_into(slug_history::table)
+                        .values(&NewSlugHistory {
+                            model_id,
+                            slug: old_slug.clone(),
+                        })
+                        .execute(conn)
+                        .await?;
+                }
+
+                diesel::update(models3d::table.find(model_id))
+                    .set(models3d::name.eq(&new_slug))
+                    .execute(conn)
+                    .await?;
+
+                debug!("Changed slug {:?} to {:?}", old_slug, new_slug);
+                anyhow::Ok(())
+            }
+            .scope_boxed()
+        })
+        .await
+}
+
+pub async fn handle_change_slug(
+    State(state): State<crate::AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(slug): Path<String>,
+    Json(request): Json<UpdateSlugRequest>,
+) -> Result<Json<UpdateSlugResponse>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let model = resolve_model(&mut connection, &slug).await?;
+
+    let new_slug = match request.slug {
+        Some(requested) => {
+            let requested = slugify(&requested);
+            let taken = slug_is_taken(&mut connection, &requested, Some(model.id))
+                .await
+                .map_err(|e| AppError::internal(e.to_string()))?;
+            if taken {
+                return Err(AppError::conflict(format!(
+                    "The slug {} is already in use",
+                    requested
+                )));
+            }
+            requested
+        }
+        None => unique_slug(&mut connection, &model.title, Some(model.id))
+            .await
+            .map_err(|e| AppError::internal(e.to_string()))?,
+    };
+
+    change_slug(&mut connection, &model, &new_slug)
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Update,
+        AuditTarget::model(&model),
+        format!("Changed the slug from {} to {}", model.name, new_slug),
+    )
+    .await;
+
+    Ok(Json(UpdateSlugResponse { slug: new_slug }))
+}
@@ -1,16 +1,19 @@
 use axum::{
     body::{Body, Bytes},
-    http::{header, HeaderValue, Response},
-    response::IntoResponse,
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+};
+use std::{
+    io::Write,
+    path::{Component, Path, PathBuf},
 };
-use std::{io::Write, path::PathBuf};
 use tokio::fs::File as TokioFile;
 use tokio::io::AsyncReadExt;
 use tracing::{debug, error};
 use zip::write::SimpleFileOptions;
 use zip::ZipWriter;
 
-use crate::Config;
+use crate::error::AppError;
 
 fn collect_files_to_compress(dir: &PathBuf) -> anyhow::Result<Vec<PathBuf>> {
     let mut files = Vec::new();
@@ -26,25 +29,39 @@ fn collect_files_to_compress(dir: &PathBuf) -> anyhow::Result<Vec<PathBuf>> {
     Ok(files)
 }
 
-pub async fn zip_folder_stream(folder_path: PathBuf, config: &Config) -> impl IntoResponse {
-    let files = match collect_files_to_compress(&folder_path) {
-        Ok(files) => files,
-        Err(err) => {
-            return Response::builder()
-                .status(500)
-                .body(format!("Error: {}", err).into())
-                .unwrap();
-        }
-    };
+/// A folder streamed as a ZIP archive
+pub struct ZipArchive(Response);
+
+impl IntoResponse for ZipArchive {
+    fn into_response(self) -> Response {
+        self.0
+    }
+}
+
+/// `folder` is relative to `library_root` and may neither leave it nor be hidden, paths inside the ZIP are relative to `library_root`
+pub async fn zip_folder_stream(library_root: &Path, folder: &Path) -> Result<ZipArchive, AppError> {
+    let is_inside = !folder.as_os_str().is_empty()
+        && folder
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        && !crate::libraries::is_hidden_path(folder);
+    let folder_path = library_root.join(folder);
+    if !is_inside || !folder_path.is_dir() {
+        return Err(AppError::not_found(format!(
+            "There is no folder {}",
+            folder.display()
+        )));
+    }
+    let files = collect_files_to_compress(&folder_path)?;
 
-    let lib_dir = config.libraries_path.clone();
+    let lib_dir = library_root.to_path_buf();
 
     let stream = async_stream::stream! {
         let mut buffer = Vec::new(); // use https://doc.rust-lang.org/std/io/struct.BorrowedBuf.html in the future
         let mut append_zip = ZipWriter::new(std::io::Cursor::new(&mut buffer));
 
         for file_path in files {
-            debug!("{:?}", file_path.to_str().unwrap());
+            debug!("{}", file_path.display());
 
 
             if let Ok(mut file) = TokioFile::open(&file_path).await {
@@ -54,10 +71,13 @@ pub async fn zip_folder_stream(folder_path: PathBuf, config: &Config) -> impl In
                     continue;
                 }
 
-                let zip_path = pathdiff::diff_paths(&file_path, &lib_dir).unwrap();
+                let Some(zip_path) = pathdiff::diff_paths(&file_path, &lib_dir) else {
+                    error!("{} is outside of the library", file_path.display());
+                    continue;
+                };
 
                 let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
-                if let Err(err) = append_zip.start_file(zip_path.to_str().unwrap(), options) {
+                if let Err(err) = append_zip.start_file(zip_path.to_string_lossy(), options) {
                     error!("Error adding file to zip {}: {}", file_path.display(), err);
                     continue;
                 }
@@ -76,17 +96,19 @@ pub async fn zip_folder_stream(folder_path: PathBuf, config: &Config) -> impl In
         yield Ok::<_, std::io::Error>(Bytes::from(buffer));
     };
 
+    let file_name = folder_path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "download".to_string());
+    let content_disposition =
+        HeaderValue::from_str(&format!("attachment; filename=\"{}.zip\"", file_name))
+            .map_err(|_| AppError::bad_request(format!("{} is no valid file name", file_name)))?;
+
     let stream_body = Body::from_stream(stream);
     Response::builder()
         .header(header::CONTENT_TYPE, "application/zip")
-        .header(
-            header::CONTENT_DISPOSITION,
-            HeaderValue::from_str(&format!(
-                "attachment; filename=\"{}.zip\"",
-                folder_path.file_name().unwrap().to_string_lossy()
-            ))
-            .unwrap(),
-        )
+        .header(header::CONTENT_DISPOSITION, content_disposition)
         .body(stream_body)
-        .unwrap()
+        .map(ZipArchive)
+        .map_err(AppError::internal)
 }
@@ -0,0 +1,177 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use tracing::info;
+
+use crate::audit::{self, AuditTarget};
+use crate::auth::{generate_token, hash_token, CurrentUser};
+use crate::error::AppError;
+use crate::schema::{api_tokens, users};
+use crate::types::{
+    ApiToken, ApiTokenResponse, AuditAction, AuditTargetType, CreateApiTokenRequest,
+    CreatedApiTokenResponse, NewApiToken, TokenScope, User,
+};
+use crate::AppState;
+
+/// Tokens start with it, so leaked ones are easy to find e.g. by secret scanners
+const TOKEN_PREFIX: &str = "mv_";
+/// Characters of a token kept in the clear to tell tokens apart
+const DISPLAY_PREFIX_LENGTH: usize = TOKEN_PREFIX.len() + 8;
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Every scope allows reading, the admin scope grants everything
+pub fn has_scope(scopes: &[TokenScope], required: TokenScope) -> bool {
+    match required {
+        TokenScope::Read => !scopes.is_empty(),
+        _ => scopes.contains(&TokenScope::Admin) || scopes.contains(&required),
+    }
+}
+
+/// User and scopes of a bearer token, records when the token was used
+pub async fn authenticate<Conn>(
+    connection: &mut Conn,
+    token: &str,
+) -> anyhow::Result<Option<(User, Vec<TokenScope>)>>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let found = api_tokens::table
+        .inner_join(users::table)
+        .filter(api_tokens::token_hash.eq(hash_token(token)))
+        .select((ApiToken::as_select(), User::as_select()))
+        .first::<(ApiToken, User)>(connection)
+        .await
+        .optional()?;
+    let Some((api_token, user)) = found else {
+        return Ok(None);
+    };
+
+    diesel::update(api_tokens::table.find(api_token.id))
+        .set(api_tokens::last_used_at.eq(Utc::now().naive_utc()))
+        .execute(connection)
+        .await?;
+
+    Ok(Some((user, TokenScope::parse_list(&api_token.scopes))))
+}
+
+/// Tokens of the current user, admins get the tokens of all users
+pub async fn list_tokens(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+) -> Result<Json<Vec<ApiTokenResponse>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let mut query = api_tokens::table
+        .order(api_tokens::date_added.desc())
+        .into_boxed();
+    if !user.is_admin() {
+        query = query.filter(api_tokens::user_id.eq(user.id));
+    }
+    let tokens = query
+        .load::<ApiToken>(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+
+    Ok(Json(tokens.iter().map(ApiTokenResponse::from).collect()))
+}
+
+pub async fn create_token(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Json(request): Json<CreateApiTokenRequest>,
+) -> Result<(StatusCode, Json<CreatedApiTokenResponse>), AppError> {
+    let name = request.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        return Err(AppError::unprocessable(format!(
+            "name must be between 1 and {} characters",
+            MAX_NAME_LENGTH
+        )));
+    }
+    if request.scopes.is_empty() {
+        return Err(AppError::unprocessable("at least one scope is required"));
+    }
+    if request.scopes.contains(&TokenScope::Admin) {
+        user.require_admin()?;
+    }
+
+    let mut scopes: Vec<&str> = request.scopes.iter().map(TokenScope::as_str).collect();
+    scopes.sort();
+    scopes.dedup();
+
+    let token = format!("{}{}", TOKEN_PREFIX, generate_token());
+    let mut connection = state.pool.get().await?;
+    let api_token = diesel::insert_into(api_tokens::table)
+        .values(&NewApiToken {
+            user_id: user.id,
+            name: name.to_string(),
+            token_prefix: token[..DISPLAY_PREFIX_LENGTH].to_string(),
+            token_hash: hash_token(&token),
+            scopes: scopes.join(","),
+        })
+        .returning(ApiToken::as_returning())
+        .get_result(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    info!(
+        "{} created the API token {:?} with {}",
+        user.username, api_token.name, api_token.scopes
+    );
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Create,
+        AuditTarget::new(AuditTargetType::ApiToken, api_token.id, &api_token.name),
+        format!("Created a token with the scopes {}", api_token.scopes),
+    )
+    .await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreatedApiTokenResponse {
+            token,
+            api_token: ApiTokenResponse::from(&api_token),
+        }),
+    ))
+}
+
+/// Deletes a token, it stops working immediately
+pub async fn revoke_token(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(token_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let api_token = api_tokens::table
+        .find(token_id)
+        .first::<ApiToken>(&mut connection)
+        .await
+        .optional()
+        .map_err(AppError::internal)?
+        .filter(|api_token| api_token.user_id == user.id || user.is_admin())
+        .ok_or_else(|| AppError::not_found(format!("API token {} not found", token_id)))?;
+
+    diesel::delete(api_tokens::table.find(api_token.id))
+        .execute(&mut connection)
+        .await
+        .map_err(AppError::internal)?;
+    info!(
+        "{} revoked the API token {:?}",
+        user.username, api_token.name
+    );
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Delete,
+        AuditTarget::new(AuditTargetType::ApiToken, api_token.id, &api_token.name),
+        format!("Revoked the token {}", api_token.token_prefix),
+    )
+    .await;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,590 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::SqliteConnection;
+use diesel_async::pooled_connection::bb8::Pool;
+use diesel_async::scoped_futures::ScopedFutureExt;
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use std::str::FromStr;
+use tokio::fs;
+use tracing::{debug, error, info};
+use uuid::Uuid;
+
+use crate::audit::{self, AuditTarget};
+use crate::auth::CurrentUser;
+use crate::error::AppError;
+use crate::libraries::Library;
+use crate::schema::{
+    collections, favourites, files3d, model_collections, models3d, prints, share_links,
+    slug_history, trash,
+};
+use crate::slug::{slug_is_taken, unique_slug};
+use crate::types::{
+    AuditAction, AuditTargetType, File3D, Model3D, ModelReference, NewFavourite,
+    NewModelCollection, NewTrashEntry, TrashEntry, TrashEntryResponse, TrashKind, TrashedModel,
+};
+use crate::v2::IfMatch;
+use crate::{revisions, Config};
+
+/// Removes the database rows of a model, its revisions and slug history are left to the caller
+pub async fn remove_model_rows<Conn>(connection: &mut Conn, model_id: i32) -> anyhow::Result<()>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    diesel::delete(model_collections::table.filter(model_collections::model_id.eq(model_id)))
+        .execute(connection)
+        .await?;
+    diesel::delete(favourites::table.filter(favourites::model_id.eq(model_id)))
+        .execute(connection)
+        .await?;
+    diesel::delete(share_links::table.filter(share_links::model_id.eq(model_id)))
+        .execute(connection)
+        .await?;
+    // the files get new ids on restore, so the prints lose their file
+    diesel::update(prints::table.filter(prints::model_id.eq(model_id)))
+        .set(prints::file_id.eq(None::<i32>))
+        .execute(connection)
+        .await?;
+    diesel::delete(files3d::table.filter(files3d::model_id.eq(model_id)))
+        .execute(connection)
+        .await?;
+    diesel::delete(models3d::table.find(model_id))
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
+/// Moves the folder of a model into the trash and keeps its favourite and collections,
+/// `if_match` is checked again in the transaction that removes the model
+pub async fn move_model_to_trash<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    model: &Model3D,
+    if_match: &IfMatch,
+) -> anyhow::Result<()>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let library = model.library(config)?;
+    let source = model.absolute_path(config)?;
+    if !source.exists() {
+        debug!("{} is already gone, nothing to trash", source.display());
+        if_match.check_model(connection, model.id).await?;
+        remove_model_rows(connection, model.id).await?;
+        return revisions::delete_revisions(config, connection, model.id).await;
+    }
+
+    let collection_ids = model_collections::table
+        .filter(model_collections::model_id.eq(model.id))
+        .select(model_collections::collection_id)
+        .load::<i32>(connection)
+        .await?;
+    let favourite_user_ids = favourites::table
+        .filter(favourites::model_id.eq(model.id))
+        .select(favourites::user_id)
+        .load::<i32>(connection)
+        .await?;
+    let metadata = serde_json::to_string(&TrashedModel {
+        model: model.clone(),
+        collection_ids,
+        favourite_user_ids,
+    })?;
+
+    let trash_path = Uuid::new_v4().to_string();
+    let target = library.trash_dir().join(&trash_path);
+    fs::create_dir_all(library.trash_dir()).await?;
+    fs::rename(&source, &target).await?;
+
+    let entry = NewTrashEntry {
+        kind: TrashKind::Model.as_str().to_string(),
+        model_id: model.id,
+        title: model.title.clone(),
+        original_path: model.folder_path.clone(),
+        trash_path,
+        metadata: Some(metadata),
+        library: model.library.clone(),
+    };
+    let model_id = model.id;
+    let result = connection
+        .transaction::<_, anyhow::Error, _>(|conn| {
+            async move {
+                if_match.check_model(conn, model_id).await?;
+                diesel::insert_into(trash::table)
+                    .values(&entry)
+                    .execute(conn)
+                    .await?;
+                remove_model_rows(conn, model_id).await
+            }
+            .scope_boxed()
+        })
+        .await;
+
+    if let Err(e) = result {
+        fs::rename(&target, &source).await?;
+        return Err(e);
+    }
+
+    debug!("Moved {} to the trash", source.display());
+    Ok(())
+}
+
+/// Moves a single file of a model into the trash, the caller rescans the model
+pub async fn move_file_to_trash<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    model: &Model3D,
+    file: &File3D,
+) -> anyhow::Result<()>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let library = model.library(config)?;
+    let source = model.absolute_path(config)?.join(&file.file_path);
+    let trash_path = Uuid::new_v4().to_string();
+    let target = library.trash_dir().join(&trash_path).join(&file.file_path);
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::rename(&source, &target).await?;
+
+    let entry = NewTrashEntry {
+        kind: TrashKind::File.as_str().to_string(),
+        model_id: model.id,
+        title: file.get_file_name().await.unwrap_or_default(),
+        original_path: file.file_path.clone(),
+        trash_path,
+        metadata: None,
+        library: model.library.clone(),
+    };
+    if let Err(e) = diesel::insert_into(trash::table)
+        .values(&entry)
+        .execute(connection)
+        .await
+    {
+        fs::rename(&target, &source).await?;
+        return Err(e.into());
+    }
+
+    debug!("Moved {} to the trash", source.display());
+    Ok(())
+}
+
+/// Why an entry can not be restored right now
+pub enum RestoreError {
+    Conflict(String),
+    Failed(anyhow::Error),
+}
+
+/// The library an entry was deleted from, it has to be configured and writable
+fn writable_library_of<'a>(
+    config: &'a Config,
+    entry: &TrashEntry,
+) -> Result<&'a Library, RestoreError> {
+    let library = config.library(entry.library.as_deref()).ok_or_else(|| {
+        RestoreError::Conflict(format!(
+            "The library {} is not configured",
+            entry.library.as_deref().unwrap_or_default()
+        ))
+    })?;
+    if library.read_only {
+        return Err(RestoreError::Conflict(format!(
+            "The library {} is read-only",
+            library.name
+        )));
+    }
+    Ok(library)
+}
+
+impl From<anyhow::Error> for RestoreError {
+    fn from(e: anyhow::Error) -> Self {
+        RestoreError::Failed(e)
+    }
+}
+
+async fn restore_model<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    entry: &TrashEntry,
+) -> Result<Model3D, RestoreError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let trashed: TrashedModel = serde_json::from_str(entry.metadata.as_deref().unwrap_or(""))
+        .map_err(anyhow::Error::from)?;
+
+    let library = writable_library_of(config, entry)?;
+    let target = library.path.join(&entry.original_path);
+    if target.exists() {
+        return Err(RestoreError::Conflict(format!(
+            "{} already exists in the library {}",
+            entry.original_path, library.name
+        )));
+    }
+
+    let mut model = trashed.model;
+    // the slug may have been taken by another model in the meantime
+    if slug_is_taken(connection, &model.name, Some(model.id)).await? {
+        model.name = unique_slug(connection, &model.title, Some(model.id)).await?;
+    }
+
+    let source = library.trash_dir().join(&entry.trash_path);
+    fs::rename(&source, &target)
+        .await
+        .map_err(anyhow::Error::from)?;
+
+    let entry_id = entry.id;
+    let collection_ids = trashed.collection_ids;
+    let favourite_user_ids = trashed.favourite_user_ids;
+    let result = connection
+        .transaction::<_, anyhow::Error, _>(|conn| {
+            let model = &model;
+            async move {
+                diesel::insert_into(models3d::table)
+                    .values(model)
+                    .execute(conn)
+                    .await?;
+
+                let existing_collections = collections::table
+                    .filter(collections::id.eq_any(&collection_ids))
+                    .select(collections::id)
+                    .load::<i32>(conn)
+                    .await?;
+                for collection_id in existing_collections {
+                    diesel::insert_into(model_collections::table)
+                        .values(&NewModelCollection {
+                            model_id: model.id,
+                            collection_id,
+                        })
+                        .execute(conn)
+                        .await?;
+                }
+                for user_id in favourite_user_ids {
+                    diesel::insert_into(favourites::table)
+                        .values(&NewFavourite {
+                            user_id,
+                            model_id: model.id,
+                        })
+                        .execute(conn)
+                        .await?;
+                }
+
+                diesel::delete(trash::table.find(entry_id))
+                    .execute(conn)
+                    .await?;
+                Ok(())
+            }
+            .scope_boxed()
+        })
+        .await;
+
+    if let Err(e) = result {
+        fs::rename(&target, &source)
+            .await
+            .map_err(anyhow::Error::from)?;
+        return Err(e.into());
+    }
+
+    model.scan(config, connection).await?;
+    Ok(model)
+}
+
+async fn restore_file<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    entry: &TrashEntry,
+) -> Result<Model3D, RestoreError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let model = models3d::table
+        .find(entry.model_id)
+        .first::<Model3D>(connection)
+        .await
+        .optional()
+        .map_err(anyhow::Error::from)?
+        .ok_or_else(|| {
+            RestoreError::Conflict(
+                "The model of this file is deleted, restore the model first".to_string(),
+            )
+        })?;
+
+    let library = writable_library_of(config, entry)?;
+    let target = model.absolute_path(config)?.join(&entry.original_path);
+    if target.exists() {
+        return Err(RestoreError::Conflict(format!(
+            "{} already exists in {}",
+            entry.original_path, model.title
+        )));
+    }
+
+    let source = library
+        .trash_dir()
+        .join(&entry.trash_path)
+        .join(&entry.original_path);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(anyhow::Error::from)?;
+    }
+    fs::rename(&source, &target)
+        .await
+        .map_err(anyhow::Error::from)?;
+
+    diesel::delete(trash::table.find(entry.id))
+        .execute(connection)
+        .await
+        .map_err(anyhow::Error::from)?;
+    remove_trash_folder(config, entry).await;
+
+    model.scan(config, connection).await?;
+    Ok(model)
+}
+
+/// Puts the content of an entry back into the library, returns the affected model
+pub async fn restore<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    entry: &TrashEntry,
+) -> Result<Model3D, RestoreError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    match TrashKind::from_str(&entry.kind)? {
+        TrashKind::Model => restore_model(config, connection, entry).await,
+        TrashKind::File => restore_file(config, connection, entry).await,
+    }
+}
+
+async fn remove_trash_folder(config: &Config, entry: &TrashEntry) {
+    let Some(library) = config.library(entry.library.as_deref()) else {
+        debug!(
+            "The library of {} is not configured, its files stay",
+            entry.title
+        );
+        return;
+    };
+    let folder = library.trash_dir().join(&entry.trash_path);
+    if let Err(e) = fs::remove_dir_all(&folder).await {
+        debug!("Failed to remove {}: {}", folder.display(), e);
+    }
+}
+
+/// Deletes an entry for good, for models this includes the revisions, slug history and deleted files
+pub async fn purge<Conn>(
+    config: &Config,
+    connection: &mut Conn,
+    entry: &TrashEntry,
+) -> anyhow::Result<()>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    if TrashKind::from_str(&entry.kind)? == TrashKind::Model {
+        revisions::delete_revisions(config, connection, entry.model_id).await?;
+        diesel::delete(slug_history::table.filter(slug_history::model_id.eq(entry.model_id)))
+            .execute(connection)
+            .await?;
+        diesel::delete(prints::table.filter(prints::model_id.eq(entry.model_id)))
+            .execute(connection)
+            .await?;
+
+        // deleted files can not be restored without their model
+        let files = trash::table
+            .filter(trash::kind.eq(TrashKind::File.as_str()))
+            .filter(trash::model_id.eq(entry.model_id))
+            .load::<TrashEntry>(connection)
+            .await?;
+        for file in &files {
+            diesel::delete(trash::table.find(file.id))
+                .execute(connection)
+                .await?;
+            remove_trash_folder(config, file).await;
+        }
+    }
+
+    diesel::delete(trash::table.find(entry.id))
+        .execute(connection)
+        .await?;
+    remove_trash_folder(config, entry).await;
+
+    debug!("Purged {} from the trash", entry.title);
+    Ok(())
+}
+
+fn purge_after(config: &Config, date_deleted: NaiveDateTime) -> Option<NaiveDateTime> {
+    (config.trash_retention_days > 0)
+        .then(|| date_deleted + Duration::days(config.trash_retention_days as i64))
+}
+
+/// Purges every entry older than the configured retention
+pub async fn purge_expired<Conn>(config: &Config, connection: &mut Conn) -> anyhow::Result<usize>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    if config.trash_retention_days == 0 {
+        return Ok(0);
+    }
+
+    let cutoff = Utc::now().naive_utc() - Duration::days(config.trash_retention_days as i64);
+    let expired = trash::table
+        .filter(trash::date_deleted.lt(cutoff))
+        .load::<TrashEntry>(connection)
+        .await?;
+
+    for entry in &expired {
+        purge(config, connection, entry).await?;
+    }
+    Ok(expired.len())
+}
+
+/// Purges expired entries once an hour
+pub async fn run_purge_task(pool: Pool<SyncConnectionWrapper<SqliteConnection>>, config: Config) {
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+    loop {
+        interval.tick().await;
+
+        let mut connection = match pool.get().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Trash purge could not get a connection: {}", e);
+                continue;
+            }
+        };
+        match purge_expired(&config, &mut connection).await {
+            Ok(0) => {}
+            Ok(purged) => info!("Purged {} expired entries from the trash", purged),
+            Err(e) => error!("Trash purge failed: {:?}", e),
+        }
+    }
+}
+
+async fn get_entry<Conn>(connection: &mut Conn, entry_id: i32) -> Result<TrashEntry, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    trash::table
+        .find(entry_id)
+        .first::<TrashEntry>(connection)
+        .await
+        .map_err(|_| AppError::not_found(format!("Trash entry {} not found", entry_id)))
+}
+
+pub async fn list_trash(
+    State(state): State<crate::AppState>,
+) -> Result<Json<Vec<TrashEntryResponse>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let entries = trash::table
+        .order(trash::date_deleted.desc())
+        .load::<TrashEntry>(&mut connection)
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?;
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            Ok(TrashEntryResponse {
+                id: entry.id,
+                kind: TrashKind::from_str(&entry.kind)
+                    .map_err(|e| AppError::internal(e.to_string()))?,
+                model_id: entry.model_id,
+                title: entry.title,
+                original_path: entry.original_path,
+                library: entry
+                    .library
+                    .unwrap_or_else(|| state.config.main_library().name.clone()),
+                date_deleted: entry.date_deleted,
+                purge_after: purge_after(&state.config, entry.date_deleted),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Json)
+}
+
+pub async fn restore_trash_entry(
+    State(state): State<crate::AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(entry_id): Path<i32>,
+) -> Result<Json<ModelReference>, AppError> {
+    let mut connection = state.pool.get().await?;
+    let entry = get_entry(&mut connection, entry_id).await?;
+
+    match restore(&state.config, &mut connection, &entry).await {
+        Ok(model) => {
+            audit::record(
+                &mut connection,
+                &user,
+                AuditAction::Restore,
+                AuditTarget::model(&model),
+                format!("Restored {} from the trash", entry.original_path),
+            )
+            .await;
+            Ok(Json(ModelReference {
+                id: model.id,
+                title: model.title,
+                name: model.name,
+            }))
+        }
+        Err(RestoreError::Conflict(message)) => Err(AppError::conflict(message)),
+        Err(RestoreError::Failed(e)) => {
+            error!("Restoring {} failed: {:?}", entry.title, e);
+            Err(AppError::internal(format!(
+                "Failed to restore {}",
+                entry.title
+            )))
+        }
+    }
+}
+
+pub async fn purge_trash_entry(
+    State(state): State<crate::AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(entry_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let mut connection = state.pool.get().await?;
+    let entry = get_entry(&mut connection, entry_id).await?;
+
+    purge(&state.config, &mut connection, &entry)
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?;
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Purge,
+        AuditTarget::new(AuditTargetType::Trash, entry.id, &entry.title),
+        format!("Permanently deleted {}", entry.original_path),
+    )
+    .await;
+    Ok(StatusCode::OK)
+}
+
+pub async fn empty_trash(
+    State(state): State<crate::AppState>,
+    Extension(user): Extension<CurrentUser>,
+) -> Result<StatusCode, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let entries = trash::table
+        .load::<TrashEntry>(&mut connection)
+        .await
+        .map_err(|e| AppError::internal(e.to_string()))?;
+    for entry in &entries {
+        purge(&state.config, &mut connection, entry)
+            .await
+            .map_err(|e| AppError::internal(e.to_string()))?;
+    }
+    audit::record(
+        &mut connection,
+        &user,
+        AuditAction::Purge,
+        AuditTarget::all(AuditTargetType::Trash),
+        format!("Emptied the trash of {} entries", entries.len()),
+    )
+    .await;
+    Ok(StatusCode::OK)
+}
@@ -1,18 +1,25 @@
 use std::path::PathBuf;
 
+use crate::auth::CurrentUser;
 use crate::parse_library::{add_or_update_model, clean_file_system, load_files_and_preview};
-use crate::schema::{collections, files3d, model_collections, models3d};
+use crate::schema::{
+    api_tokens, audit_log, author_aliases, authors, collections, favourites, files3d,
+    model_collections, model_revisions, models3d, prints, revision_files, sessions, share_links,
+    slug_history, trash, users,
+};
 use crate::Config;
 use anyhow::{Error, Result};
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use diesel_async::{AsyncConnection, RunQueryDsl};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use tracing::{debug, error};
 use typeshare::typeshare;
 
-use crate::convert;
+use crate::v2::IfMatch;
+use crate::{convert, revisions};
 
 fn comma_separated_to_pathbuf_vec(input: &str) -> Vec<PathBuf> {
     if input.trim().is_empty() {
@@ -59,15 +66,50 @@ impl FromStr for FileType {
 }
 
 #[typeshare]
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct UploadResponse {
     pub success: bool,
     pub slug: String,
     pub message: String,
+    pub files: Vec<FileValidationReport>,
 }
 
 #[typeshare]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FileValidationStatus {
+    Ok,
+    Warning,
+    Failed,
+    /// The file type can not be parsed, e.g. images or Fusion 360 files
+    Unchecked,
+    Ignored,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct FileValidationReport {
+    pub file_name: String,
+    pub category: String,
+    pub status: FileValidationStatus,
+    pub triangle_count: Option<i32>,
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+    pub preview: bool,
+    /// Models which already contain an identical file
+    pub duplicate_of: Vec<ModelReference>,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+pub struct UploadParams {
+    /// Reject the upload if any file fails validation
+    pub strict: Option<bool>,
+    /// Library of a new model, the main library if omitted
+    pub library: Option<String>,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
 pub struct ModelPackV0_1 {
     pub version: String,
     pub title: String,
@@ -77,7 +119,7 @@ pub struct ModelPackV0_1 {
 }
 
 #[typeshare]
-#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Selectable)]
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Selectable, Insertable, Clone)]
 #[diesel(table_name = models3d)]
 pub struct Model3D {
     pub id: i32,
@@ -90,7 +132,19 @@ pub struct Model3D {
     pub date_added: Option<NaiveDateTime>,
     pub images: String,
     pub description: String,
-    pub favourite: bool,
+    #[serde(default)]
+    pub license_spdx: Option<String>,
+    #[serde(default)]
+    pub license_non_commercial: bool,
+    #[serde(default)]
+    pub license_share_alike: bool,
+    #[serde(default)]
+    pub license_attribution: bool,
+    #[serde(default)]
+    pub author_id: Option<i32>,
+    /// Name of the library, None for the main library
+    #[serde(default)]
+    pub library: Option<String>,
 }
 
 impl Model3D {
@@ -98,6 +152,16 @@ impl Model3D {
         comma_separated_to_pathbuf_vec(&self.images)
     }
 
+    /// `None` if the license text could not be mapped to a known license
+    pub fn license_info(&self) -> Option<LicenseInfo> {
+        self.license_spdx.as_ref().map(|spdx| LicenseInfo {
+            spdx: spdx.clone(),
+            non_commercial: self.license_non_commercial,
+            share_alike: self.license_share_alike,
+            attribution: self.license_attribution,
+        })
+    }
+
     pub async fn get_files3d<Conn>(&self, connection: &mut Conn) -> Result<Vec<File3D>, Error>
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
@@ -105,23 +169,37 @@ impl Model3D {
         let files = files3d::dsl::files3d
             .filter(files3d::dsl::model_id.eq(self.id))
             .load::<File3D>(connection)
-            .await
-            .unwrap();
+            .await?;
 
         Ok(files)
     }
 
-    pub fn absolute_path(&self, config: &Config) -> PathBuf {
-        let mut path = config.libraries_path.clone();
-        path.push(&self.folder_path);
-        path
+    pub fn absolute_path(&self, config: &Config) -> anyhow::Result<PathBuf> {
+        Ok(self.library(config)?.path.join(&self.folder_path))
+    }
+
+    /// URL of a file relative to the model folder
+    pub fn asset_url(&self, config: &Config, relative_path: impl std::fmt::Display) -> String {
+        format!(
+            "{}/{}/{}",
+            config.library_asset_prefix_of(self.library.as_deref()),
+            self.folder_path,
+            relative_path
+        )
+    }
+
+    /// Name of the library, also if it is not configured anymore
+    pub fn library_name(&self, config: &Config) -> String {
+        self.library
+            .clone()
+            .unwrap_or_else(|| config.main_library().name.clone())
     }
 
     pub async fn scan<Conn>(&self, config: &Config, connection: &mut Conn) -> anyhow::Result<()>
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
     {
-        add_or_update_model(config, connection, &self.absolute_path(config)).await?;
+        add_or_update_model(config, connection, &self.absolute_path(config)?).await?;
         let files = self.get_files3d(connection).await?;
         clean_file_system(config, connection, files).await?;
         load_files_and_preview(config, connection, self).await?;
@@ -133,35 +211,86 @@ impl Model3D {
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
     {
-        fs_extra::dir::remove(self.absolute_path(config));
-        debug!("Deleted {}", self.absolute_path(config).display());
-        diesel::delete(models3d::dsl::models3d.filter(models3d::dsl::id.eq(self.id)))
-            .execute(connection)
-            .await
-            .unwrap();
+        crate::trash::move_model_to_trash(config, connection, self, &IfMatch::default()).await?;
+        debug!("Deleted {} {}", self.title, self.folder_path);
 
         anyhow::Ok(())
     }
+
+    pub async fn is_favourite_of<Conn>(&self, user_id: i32, connection: &mut Conn) -> Result<bool>
+    where
+        Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+    {
+        let count: i64 = favourites::table
+            .filter(favourites::user_id.eq(user_id))
+            .filter(favourites::model_id.eq(self.id))
+            .count()
+            .get_result(connection)
+            .await?;
+        Ok(count > 0)
+    }
+
+    pub async fn set_favourite<Conn>(
+        &self,
+        user_id: i32,
+        favourite: bool,
+        connection: &mut Conn,
+    ) -> Result<()>
+    where
+        Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+    {
+        if favourite {
+            diesel::insert_into(favourites::table)
+                .values(&NewFavourite {
+                    user_id,
+                    model_id: self.id,
+                })
+                .on_conflict_do_nothing()
+                .execute(connection)
+                .await?;
+        } else {
+            diesel::delete(
+                favourites::table
+                    .filter(favourites::user_id.eq(user_id))
+                    .filter(favourites::model_id.eq(self.id)),
+            )
+            .execute(connection)
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = favourites)]
+pub struct NewFavourite {
+    pub user_id: i32,
+    pub model_id: i32,
 }
 
 #[typeshare]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ModelResponse {
     pub id: i32,
     pub title: String,
     pub name: String,
     pub license: Option<String>,
+    pub license_info: Option<LicenseInfo>,
     pub author: Option<String>,
+    pub author_id: Option<i32>,
     pub origin: Option<String>,
     pub images: Vec<String>,
     pub description: String,
     pub favourite: bool,
+    pub library: String,
 }
 
 impl ModelResponse {
+    /// `favourite` is whether the user with `user_id` liked the model
     pub async fn from_model_3d<Conn>(
         model: &Model3D,
         config: &Config,
+        user_id: i32,
         connection: &mut Conn,
     ) -> Result<ModelResponse, Error>
     where
@@ -169,14 +298,7 @@ impl ModelResponse {
     {
         let mut images: Vec<String> = comma_separated_to_pathbuf_vec(&model.images)
             .iter()
-            .map(|p| {
-                format!(
-                    "{}/{}/{}",
-                    config.asset_prefix,
-                    model.folder_path,
-                    p.to_string_lossy()
-                )
-            })
+            .map(|p| model.asset_url(config, p.to_string_lossy()))
             .collect();
 
         let files = model.get_files3d(connection).await?;
@@ -192,27 +314,34 @@ impl ModelResponse {
             title: model.title.clone(),
             name: model.name.clone(),
             license: model.license.clone(),
+            license_info: model.license_info(),
             author: model.author.clone(),
+            author_id: model.author_id,
             origin: model.origin.clone(),
             images,
             description: model.description.clone(),
-            favourite: model.favourite,
+            favourite: model.is_favourite_of(user_id, connection).await?,
+            library: model.library_name(config),
         })
     }
 }
 
 #[typeshare]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct ModelResponseList {
     pub models: Vec<ModelResponse>,
-    pub licenses: Vec<String>,
+    /// Number of models matching the filters on all pages
+    pub total: i32,
+    pub facets: ModelFacets,
 }
 
 impl ModelResponseList {
     pub async fn from_model_3d<Conn>(
         model: Vec<Model3D>,
-        licenses: Vec<String>,
+        total: i32,
+        facets: ModelFacets,
         config: &Config,
+        user_id: i32,
         connection: &mut Conn,
     ) -> Result<ModelResponseList, Error>
     where
@@ -221,13 +350,16 @@ impl ModelResponseList {
         let mut models: Vec<ModelResponse> = Vec::new();
 
         for m in model {
-            let model_response = ModelResponse::from_model_3d(&m, config, connection)
-                .await
-                .unwrap();
+            let model_response =
+                ModelResponse::from_model_3d(&m, config, user_id, connection).await?;
             models.push(model_response);
         }
 
-        let response = ModelResponseList { models, licenses };
+        let response = ModelResponseList {
+            models,
+            total,
+            facets,
+        };
         Ok(response)
     }
 }
@@ -244,7 +376,12 @@ pub struct NewModel3D {
     pub origin: Option<String>,
     pub images: String,
     pub description: String,
-    pub favourite: bool,
+    pub license_spdx: Option<String>,
+    pub license_non_commercial: bool,
+    pub license_share_alike: bool,
+    pub license_attribution: bool,
+    pub author_id: Option<i32>,
+    pub library: Option<String>,
 }
 
 impl NewModel3D {
@@ -254,16 +391,22 @@ impl NewModel3D {
         image_paths: Vec<PathBuf>,
         readme: String,
     ) -> Result<Self, Error> {
+        let license_info = crate::licenses::normalize_license(&pack.license);
         Ok(Self {
             title: pack.title.clone(),
-            name: str_slug::slug(pack.title.clone()),
+            name: crate::slug::slugify(&pack.title),
             license: Some(pack.license.clone()),
             author: Some(pack.author.clone()),
             folder_path: folder_path.clone().into_os_string().into_string().unwrap(),
             origin: Some(pack.origin.clone()),
             images: pathbuf_vec_to_comma_separated(image_paths),
             description: readme,
-            favourite: false,
+            license_spdx: license_info.as_ref().map(|info| info.spdx.clone()),
+            license_non_commercial: license_info.as_ref().is_some_and(|i| i.non_commercial),
+            license_share_alike: license_info.as_ref().is_some_and(|i| i.share_alike),
+            license_attribution: license_info.as_ref().is_some_and(|i| i.attribution),
+            author_id: None,
+            library: None,
         })
     }
 
@@ -340,12 +483,12 @@ impl File3D {
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
     {
-        let src_file = self.get_file_path(connection, config).await;
+        let src_file = self.get_file_path(connection, config).await?;
         match self.clone().file_type() {
             FileType::STL => convert::astl_convert_to_bstl(&src_file),
-            FileType::STEP => convert::save_as_stl(&convert::load_step(&src_file).unwrap()),
-            FileType::IGES => convert::save_as_stl(&convert::load_iges(&src_file).unwrap()),
-            FileType::OBJ => convert::save_as_stl(&convert::load_obj(&src_file).unwrap()),
+            FileType::STEP => convert::save_as_stl(&convert::load_step(&src_file)?),
+            FileType::IGES => convert::save_as_stl(&convert::load_iges(&src_file)?),
+            FileType::OBJ => convert::save_as_stl(&convert::load_obj(&src_file)?),
             _ => Err(anyhow::format_err!("unsupported file")),
         }
     }
@@ -358,12 +501,12 @@ impl File3D {
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
     {
-        let src_file = self.get_file_path(connection, config).await;
+        let src_file = self.get_file_path(connection, config).await?;
         match self.clone().file_type() {
-            FileType::STL => convert::save_as_threemf(&convert::load_stl(&src_file).unwrap()),
-            FileType::STEP => convert::save_as_threemf(&convert::load_step(&src_file).unwrap()),
-            FileType::IGES => convert::save_as_threemf(&convert::load_iges(&src_file).unwrap()),
-            FileType::OBJ => convert::save_as_threemf(&convert::load_obj(&src_file).unwrap()),
+            FileType::STL => convert::save_as_threemf(&convert::load_stl(&src_file)?),
+            FileType::STEP => convert::save_as_threemf(&convert::load_step(&src_file)?),
+            FileType::IGES => convert::save_as_threemf(&convert::load_iges(&src_file)?),
+            FileType::OBJ => convert::save_as_threemf(&convert::load_obj(&src_file)?),
             _ => Err(anyhow::format_err!("unsupported file")),
         }
     }
@@ -376,7 +519,7 @@ impl File3D {
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
     {
-        let src_file = self.get_file_path(connection, config).await;
+        let src_file = self.get_file_path(connection, config).await?;
         match self.clone().file_type() {
             FileType::STEP => convert::step_to_iges(&src_file),
             _ => Err(anyhow::format_err!("unsupported file")),
@@ -391,7 +534,7 @@ impl File3D {
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
     {
-        let src_file = self.get_file_path(connection, config).await;
+        let src_file = self.get_file_path(connection, config).await?;
         match self.clone().file_type() {
             FileType::IGES => convert::iges_to_step(&src_file),
             _ => Err(anyhow::format_err!("unsupported file")),
@@ -405,27 +548,26 @@ impl File3D {
             .map(|name| name.to_string())
     }
 
-    pub async fn get_file_path<Conn>(&self, connection: &mut Conn, config: &Config) -> PathBuf
+    pub async fn get_file_path<Conn>(
+        &self,
+        connection: &mut Conn,
+        config: &Config,
+    ) -> anyhow::Result<PathBuf>
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
     {
-        let mut file_pth = config.libraries_path.clone();
-        file_pth.push(self.get_model(connection).await.unwrap().folder_path);
-        file_pth.push(&self.file_path);
-
-        file_pth
+        let model = self.get_model(connection).await?;
+        Ok(model.absolute_path(config)?.join(&self.file_path))
     }
 
     pub async fn get_url_file_path<Conn>(&self, connection: &mut Conn, config: &Config) -> String
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
     {
-        format!(
-            "{}/{}/{}",
-            config.asset_prefix,
-            self.get_model(connection).await.unwrap().folder_path,
-            &self.file_path
-        )
+        self.get_model(connection)
+            .await
+            .unwrap()
+            .asset_url(config, &self.file_path)
     }
 
     pub fn get_url_preview_path(&self, config: &Config) -> Option<String> {
@@ -438,22 +580,41 @@ impl File3D {
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
     {
-        let file = self.get_file_path(connection, config).await;
+        let file = self.get_file_path(connection, config).await?;
         debug!("File path obtained: {:?}", file.display());
 
-        match tokio::fs::remove_file(&file).await {
-            Ok(_) => {
-                debug!("File deleted successfully.");
-            }
-            Err(e) => {
-                debug!("Failed to delete file: {}", e);
-            }
+        let model = self.get_model(connection).await?;
+        let revision = revisions::snapshot(
+            config,
+            model.id,
+            RevisionReason::FileDeleted,
+            &model.absolute_path(config)?,
+            vec![(self.file_path.clone(), RevisionChange::Deleted)],
+        )
+        .await?;
+
+        if let Err(e) = crate::trash::move_file_to_trash(config, connection, &model, self).await {
+            revision.discard(config).await;
+            return Err(e);
+        }
+        debug!("File moved to the trash.");
+        // the file is in the trash already, a missing revision only shortens the history
+        if let Err(e) = revision.record(connection).await {
+            error!(
+                "Recording the deletion of {} failed: {:?}",
+                file.display(),
+                e
+            );
+            revision.discard(config).await;
         }
+        // the prints stay in the log without their file
+        diesel::update(prints::table.filter(prints::file_id.eq(self.id)))
+            .set(prints::file_id.eq(None::<i32>))
+            .execute(connection)
+            .await?;
 
         debug!("Delete file {:?}", file.display());
-
-        let model = self.get_model(connection).await.unwrap();
-        debug!("Model obtained, starting scan.");
+        debug!("Starting scan.");
 
         match model.scan(config, connection).await {
             Ok(_) => {
@@ -479,7 +640,7 @@ pub struct NewFile3D {
 }
 
 #[typeshare]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DetailedFileResponse {
     pub id: i32,
     pub name: String,
@@ -504,7 +665,7 @@ impl DetailedFileResponse {
 
         Self {
             id: file.id,
-            name: file.get_file_name().await.unwrap(),
+            name: file.get_file_name().await.unwrap_or_default(),
             model_id: file.model_id,
             file_path: url_file_path,
             preview_image: file.get_url_preview_path(config),
@@ -520,25 +681,31 @@ impl DetailedFileResponse {
 }
 
 #[typeshare]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DetailedModelResponse {
     pub id: i32,
     pub title: String,
     pub name: String,
     pub license: Option<String>,
+    pub license_info: Option<LicenseInfo>,
     pub package_name: String,
     pub author: Option<String>,
+    pub author_id: Option<i32>,
     pub origin: Option<String>,
     pub images: Vec<String>,
     pub files: Vec<DetailedFileResponse>,
     pub description: String,
     pub favourite: bool,
+    /// Newest print first
+    pub prints: Vec<PrintResponse>,
+    pub library: String,
 }
 
 impl DetailedModelResponse {
     pub async fn from_model_3d<Conn>(
         model: &Model3D,
         config: &Config,
+        user_id: i32,
         connection: &mut Conn,
     ) -> Result<Self, Error>
     where
@@ -546,14 +713,7 @@ impl DetailedModelResponse {
     {
         let mut images: Vec<String> = comma_separated_to_pathbuf_vec(&model.images)
             .iter()
-            .map(|p| {
-                format!(
-                    "{}/{}/{}",
-                    config.asset_prefix,
-                    model.folder_path,
-                    p.to_string_lossy()
-                )
-            })
+            .map(|p| model.asset_url(config, p.to_string_lossy()))
             .collect();
 
         let files = model.get_files3d(connection).await?;
@@ -571,30 +731,53 @@ impl DetailedModelResponse {
             detailed_files.push(detailed_file);
         }
 
+        let prints = prints::table
+            .filter(prints::model_id.eq(model.id))
+            .order((prints::printed_at.desc(), prints::id.desc()))
+            .load::<Print>(connection)
+            .await?
+            .iter()
+            .map(|print| PrintResponse::from_print(print, model, config))
+            .collect();
+
         Ok(Self {
             id: model.id,
             title: model.title.clone(),
             name: model.name.clone(),
             package_name: model.folder_path.clone(),
             license: model.license.clone(),
+            license_info: model.license_info(),
             author: model.author.clone(),
+            author_id: model.author_id,
             origin: model.origin.clone(),
             images,
             files: detailed_files,
             description: model.description.clone(),
-            favourite: model.favourite,
+            favourite: model.is_favourite_of(user_id, connection).await?,
+            prints,
+            library: model.library_name(config),
         })
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, JsonSchema)]
 pub struct ListModelParams {
     pub q: Option<String>,
+    /// Substring of the author text
     pub author: Option<String>,
+    pub author_id: Option<i32>,
     pub licenses: Option<String>,
+    /// Comma separated file extensions, models with at least one file of these types
+    pub file_types: Option<String>,
+    /// Only models in this collection
+    pub collection: Option<i32>,
     pub page: Option<i64>,
     pub page_size: Option<i64>,
     pub favourite: Option<bool>,
+    /// Only models with at least one logged print, or none if false
+    pub printed: Option<bool>,
+    /// Name of a library
+    pub library: Option<String>,
 }
 
 impl Default for ListModelParams {
@@ -602,10 +785,15 @@ impl Default for ListModelParams {
         Self {
             q: None,
             author: None,
+            author_id: None,
             licenses: None,
+            file_types: None,
+            collection: None,
             page: None,
             page_size: None,
             favourite: None,
+            printed: None,
+            library: None,
         }
     }
 }
@@ -619,9 +807,96 @@ pub struct Collection {
     pub id: i32,
     pub name: String,
     pub date_added: Option<NaiveDateTime>,
+    /// `None` for collections made before accounts existed
+    pub owner_id: Option<i32>,
+    pub visibility: String,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionVisibility {
+    /// Only the owner sees the collection
+    Private,
+    /// Every user sees the collection and can add or remove models
+    Shared,
+    /// Every user and visitors of a read-only instance see the collection, only the owner changes it
+    Public,
+}
+
+impl CollectionVisibility {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CollectionVisibility::Private => "private",
+            CollectionVisibility::Shared => "shared",
+            CollectionVisibility::Public => "public",
+        }
+    }
+}
+
+impl FromStr for CollectionVisibility {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "private" => Ok(CollectionVisibility::Private),
+            "shared" => Ok(CollectionVisibility::Shared),
+            "public" => Ok(CollectionVisibility::Public),
+            _ => Err(anyhow::format_err!("unknown collection visibility {}", s)),
+        }
+    }
+}
+
+/// Collections `user` may see, the query version of [`Collection::can_view`]
+pub fn visible_collections(
+    user: &CurrentUser,
+) -> collections::BoxedQuery<'static, diesel::sqlite::Sqlite> {
+    if user.id == CurrentUser::PUBLIC_ID {
+        return collections::table
+            .filter(collections::visibility.eq(CollectionVisibility::Public.as_str()))
+            .into_boxed();
+    }
+    if user.is_admin() {
+        return collections::table.into_boxed();
+    }
+    collections::table
+        .filter(
+            collections::owner_id
+                .eq(user.id)
+                .or(collections::visibility.ne(CollectionVisibility::Private.as_str())),
+        )
+        .into_boxed()
 }
 
 impl Collection {
+    pub fn visibility(&self) -> CollectionVisibility {
+        CollectionVisibility::from_str(&self.visibility).unwrap_or(CollectionVisibility::Private)
+    }
+
+    /// Collections from before accounts have no owner, only admins manage them
+    fn is_owned_by(&self, user: &CurrentUser) -> bool {
+        self.owner_id == Some(user.id)
+    }
+
+    /// Owners and admins see every collection, everyone else only those that are not private,
+    /// keep in line with [`visible_collections`]
+    pub fn can_view(&self, user: &CurrentUser) -> bool {
+        if user.id == CurrentUser::PUBLIC_ID {
+            return self.visibility() == CollectionVisibility::Public;
+        }
+        self.can_manage(user) || self.visibility() != CollectionVisibility::Private
+    }
+
+    /// Adding and removing models
+    pub fn can_edit_models(&self, user: &CurrentUser) -> bool {
+        self.can_manage(user) || self.visibility() == CollectionVisibility::Shared
+    }
+
+    /// Renaming, changing the visibility and deleting
+    pub fn can_manage(&self, user: &CurrentUser) -> bool {
+        self.is_owned_by(user) || user.is_admin()
+    }
+
     pub async fn get_models<Conn>(&self, connection: &mut Conn) -> Result<Vec<Model3D>, Error>
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
@@ -644,6 +919,9 @@ impl Collection {
     where
         Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
     {
+        diesel::delete(share_links::table.filter(share_links::collection_id.eq(self.id)))
+            .execute(connection)
+            .await?;
         diesel::delete(collections::table.filter(collections::id.eq(self.id)))
             .execute(connection)
             .await?;
@@ -652,18 +930,29 @@ impl Collection {
     }
 }
 
-#[typeshare]
-#[derive(Debug, Serialize, Deserialize, Insertable)]
+#[derive(Debug, Insertable)]
 #[diesel(table_name = collections)]
 pub struct NewCollection {
     pub name: String,
+    pub owner_id: Option<i32>,
+    pub visibility: String,
 }
 
 #[typeshare]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct CreateCollectionRequest {
+    pub name: String,
+    /// Private if not given
+    pub visibility: Option<CollectionVisibility>,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CollectionResponse {
     pub id: i32,
     pub name: String,
+    pub owner_id: Option<i32>,
+    pub visibility: CollectionVisibility,
     pub model_count: i32,
     pub preview_images: Vec<String>,
 }
@@ -672,6 +961,7 @@ impl CollectionResponse {
     pub async fn from_collection<Conn>(
         collection: &Collection,
         config: &Config,
+        user_id: i32,
         connection: &mut Conn,
     ) -> Result<CollectionResponse, Error>
     where
@@ -700,9 +990,8 @@ impl CollectionResponse {
                 .first::<Model3D>(connection)
                 .await
             {
-                let model_response = ModelResponse::from_model_3d(&model, config, connection)
-                    .await
-                    .unwrap();
+                let model_response =
+                    ModelResponse::from_model_3d(&model, config, user_id, connection).await?;
                 if !model_response.images.is_empty() {
                     preview_images.push(model_response.images[0].clone());
                 }
@@ -715,6 +1004,8 @@ impl CollectionResponse {
         Ok(CollectionResponse {
             id: collection.id,
             name: collection.name.clone(),
+            owner_id: collection.owner_id,
+            visibility: collection.visibility(),
             model_count,
             preview_images,
         })
@@ -722,10 +1013,12 @@ impl CollectionResponse {
 }
 
 #[typeshare]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct DetailedCollectionResponse {
     pub id: i32,
     pub name: String,
+    pub owner_id: Option<i32>,
+    pub visibility: CollectionVisibility,
     pub models: Vec<ModelResponse>,
 }
 
@@ -733,6 +1026,7 @@ impl DetailedCollectionResponse {
     pub async fn from_collection<Conn>(
         collection: &Collection,
         config: &Config,
+        user_id: i32,
         connection: &mut Conn,
     ) -> Result<Self, Error>
     where
@@ -742,15 +1036,16 @@ impl DetailedCollectionResponse {
         let mut model_responses: Vec<ModelResponse> = Vec::new();
 
         for model in models {
-            let model_response = ModelResponse::from_model_3d(&model, config, connection)
-                .await
-                .unwrap();
+            let model_response =
+                ModelResponse::from_model_3d(&model, config, user_id, connection).await?;
             model_responses.push(model_response);
         }
 
         Ok(Self {
             id: collection.id,
             name: collection.name.clone(),
+            owner_id: collection.owner_id,
+            visibility: collection.visibility(),
             models: model_responses,
         })
     }
@@ -776,14 +1071,1231 @@ pub struct NewModelCollection {
 }
 
 #[typeshare]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct UpdateCollectionRequest {
     pub name: String,
+    pub visibility: Option<CollectionVisibility>,
 }
 
+/// Changes of a collection in the v2 API, missing fields stay as they are
 #[typeshare]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PatchCollectionRequest {
+    pub name: Option<String>,
+    pub visibility: Option<CollectionVisibility>,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct AddModelToCollectionRequest {
     pub model_id: i32,
     pub collection_id: i32,
 }
+
+// ============ Adoption Types ============
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AdoptionCandidate {
+    pub library: String,
+    pub folder_path: String,
+    pub suggestion: ModelPackV0_1,
+    /// Left out of the suggestion, a plain " by " in a folder name is as often part of the title
+    pub author_guess: Option<AuthorGuess>,
+    pub model_files: Vec<String>,
+    pub image_files: Vec<String>,
+    pub has_readme: bool,
+    pub has_license: bool,
+}
+
+/// Title and author of a folder name like "Benchy by Creative Tools"
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AuthorGuess {
+    pub title: String,
+    pub author: String,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AdoptFolderRequest {
+    /// The main library if omitted
+    pub library: Option<String>,
+    pub folder_path: String,
+    /// Overrides the suggested metadata, the suggestion is used if omitted
+    pub pack: Option<ModelPackV0_1>,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AdoptRequest {
+    #[serde(default)]
+    pub folders: Vec<AdoptFolderRequest>,
+    /// Adopt every candidate found in the library with its suggested metadata
+    #[serde(default)]
+    pub all: bool,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct AdoptionResult {
+    pub library: String,
+    pub folder_path: String,
+    pub success: bool,
+    pub slug: Option<String>,
+    pub message: String,
+}
+
+// ============ Duplicate Types ============
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
+pub struct ModelReference {
+    pub id: i32,
+    pub title: String,
+    pub name: String,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DuplicateFile {
+    pub file_id: i32,
+    pub file_path: String,
+    pub model: ModelReference,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DuplicateFileGroup {
+    pub file_hash: String,
+    pub file_size: String,
+    pub files: Vec<DuplicateFile>,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DuplicateModelGroup {
+    /// Every model is similar to at least one other model of the group
+    pub models: Vec<ModelReference>,
+    /// Distinct files found in at least two models of the group
+    pub shared_files: i32,
+    /// Lowest similarity of the pairs joining the group, the shared files of a pair relative to
+    /// all distinct files of both models, 1.0 means identical
+    pub similarity: f64,
+    /// All models of the group contain the same files
+    pub identical: bool,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct DuplicatesResponse {
+    pub files: Vec<DuplicateFileGroup>,
+    pub models: Vec<DuplicateModelGroup>,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+pub struct DuplicatesParams {
+    /// Minimum similarity for models to be reported, defaults to 0.8
+    pub min_similarity: Option<f64>,
+}
+
+// ============ Slug Types ============
+
+#[derive(Debug, Serialize, Deserialize, Insertable)]
+#[diesel(table_name = slug_history)]
+pub struct NewSlugHistory {
+    pub model_id: i32,
+    pub slug: String,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateSlugRequest {
+    /// New slug, a fresh one is generated from the title if omitted
+    pub slug: Option<String>,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct UpdateSlugResponse {
+    pub slug: String,
+}
+
+// ============ Revision Types ============
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionReason {
+    Update,
+    FileDeleted,
+    Restore,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionChange {
+    Added,
+    Replaced,
+    Deleted,
+    /// Kept for reference, e.g. the manifest which is part of every revision
+    Unchanged,
+}
+
+impl RevisionReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RevisionReason::Update => "update",
+            RevisionReason::FileDeleted => "file_deleted",
+            RevisionReason::Restore => "restore",
+        }
+    }
+}
+
+impl FromStr for RevisionReason {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "update" => Ok(RevisionReason::Update),
+            "file_deleted" => Ok(RevisionReason::FileDeleted),
+            "restore" => Ok(RevisionReason::Restore),
+            _ => Err(anyhow::format_err!("unknown revision reason {}", s)),
+        }
+    }
+}
+
+impl RevisionChange {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RevisionChange::Added => "added",
+            RevisionChange::Replaced => "replaced",
+            RevisionChange::Deleted => "deleted",
+            RevisionChange::Unchanged => "unchanged",
+        }
+    }
+}
+
+impl FromStr for RevisionChange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "added" => Ok(RevisionChange::Added),
+            "replaced" => Ok(RevisionChange::Replaced),
+            "deleted" => Ok(RevisionChange::Deleted),
+            "unchanged" => Ok(RevisionChange::Unchanged),
+            _ => Err(anyhow::format_err!("unknown revision change {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Queryable, Identifiable, Selectable, Associations)]
+#[diesel(belongs_to(Model3D, foreign_key = model_id))]
+#[diesel(table_name = model_revisions)]
+pub struct ModelRevision {
+    pub id: i32,
+    pub model_id: i32,
+    pub reason: String,
+    /// Relative to the revisions directory
+    pub storage_dir: String,
+    pub date_added: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = model_revisions)]
+pub struct NewModelRevision {
+    pub model_id: i32,
+    pub reason: String,
+    pub storage_dir: String,
+}
+
+#[derive(Debug, Queryable, Identifiable, Selectable, Associations)]
+#[diesel(belongs_to(ModelRevision, foreign_key = revision_id))]
+#[diesel(table_name = revision_files)]
+pub struct RevisionFile {
+    pub id: i32,
+    pub revision_id: i32,
+    pub file_path: String,
+    pub change_type: String,
+    /// Whether the previous content is kept and can be restored
+    pub stored: bool,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = revision_files)]
+pub struct NewRevisionFile {
+    pub revision_id: i32,
+    pub file_path: String,
+    pub change_type: String,
+    pub stored: bool,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RevisionFileResponse {
+    pub file_path: String,
+    pub change: RevisionChange,
+    pub restorable: bool,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RevisionResponse {
+    pub id: i32,
+    pub reason: RevisionReason,
+    pub date_added: Option<NaiveDateTime>,
+    pub files: Vec<RevisionFileResponse>,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+pub struct RestoreRevisionParams {
+    /// Restore only this file of the revision, relative to the model folder
+    pub file: Option<String>,
+}
+
+// ============ Trash Types ============
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TrashKind {
+    Model,
+    File,
+}
+
+impl TrashKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TrashKind::Model => "model",
+            TrashKind::File => "file",
+        }
+    }
+}
+
+impl FromStr for TrashKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "model" => Ok(TrashKind::Model),
+            "file" => Ok(TrashKind::File),
+            _ => Err(anyhow::format_err!("unknown trash kind {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Queryable, Identifiable, Selectable)]
+#[diesel(table_name = trash)]
+pub struct TrashEntry {
+    pub id: i32,
+    pub kind: String,
+    pub model_id: i32,
+    pub title: String,
+    /// Model folder relative to the library or file path relative to the model folder
+    pub original_path: String,
+    /// Relative to the trash folder
+    pub trash_path: String,
+    /// [`TrashedModel`] as JSON for models
+    pub metadata: Option<String>,
+    pub date_deleted: NaiveDateTime,
+    /// Library whose trash folder holds the entry, None for the main library
+    pub library: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = trash)]
+pub struct NewTrashEntry {
+    pub kind: String,
+    pub model_id: i32,
+    pub title: String,
+    pub original_path: String,
+    pub trash_path: String,
+    pub metadata: Option<String>,
+    pub library: Option<String>,
+}
+
+/// Database state of a deleted model which is brought back on restore
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrashedModel {
+    pub model: Model3D,
+    pub collection_ids: Vec<i32>,
+    /// Users who liked the model
+    #[serde(default)]
+    pub favourite_user_ids: Vec<i32>,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TrashEntryResponse {
+    pub id: i32,
+    pub kind: TrashKind,
+    pub model_id: i32,
+    pub title: String,
+    pub original_path: String,
+    pub library: String,
+    pub date_deleted: NaiveDateTime,
+    /// When the entry is purged automatically, never if retention is disabled
+    pub purge_after: Option<NaiveDateTime>,
+}
+
+// ============ Print Types ============
+
+#[derive(Debug, Queryable, Identifiable, Selectable, Associations)]
+#[diesel(belongs_to(Model3D, foreign_key = model_id))]
+#[diesel(table_name = prints)]
+pub struct Print {
+    pub id: i32,
+    pub model_id: i32,
+    pub file_id: Option<i32>,
+    /// Relative to the model folder
+    pub file_path: String,
+    pub printer: Option<String>,
+    pub material: Option<String>,
+    pub settings: Option<String>,
+    pub success: bool,
+    pub notes: String,
+    /// Image relative to the model folder
+    pub photo: Option<String>,
+    pub printed_at: NaiveDateTime,
+    pub date_added: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[diesel(table_name = prints)]
+#[diesel(treat_none_as_null = true)]
+pub struct NewPrint {
+    pub model_id: i32,
+    pub file_id: Option<i32>,
+    pub file_path: String,
+    pub printer: Option<String>,
+    pub material: Option<String>,
+    pub settings: Option<String>,
+    pub success: bool,
+    pub notes: String,
+    pub printed_at: NaiveDateTime,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PrintRequest {
+    pub file_id: i32,
+    pub printer: Option<String>,
+    pub material: Option<String>,
+    /// Free text, e.g. layer height, infill and temperatures
+    pub settings: Option<String>,
+    pub success: bool,
+    #[serde(default)]
+    pub notes: String,
+    /// Defaults to now
+    pub printed_at: Option<NaiveDateTime>,
+}
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PrintResponse {
+    pub id: i32,
+    pub model_id: i32,
+    /// Missing if the file was deleted
+    pub file_id: Option<i32>,
+    pub file_name: String,
+    pub printer: Option<String>,
+    pub material: Option<String>,
+    pub settings: Option<String>,
+    pub success: bool,
+    pub notes: String,
+    pub photo: Option<String>,
+    pub printed_at: NaiveDateTime,
+}
+
+impl PrintResponse {
+    pub fn from_print(print: &Print, model: &Model3D, config: &Config) -> Self {
+        Self {
+            id: print.id,
+            model_id: print.model_id,
+            file_id: print.file_id,
+            file_name: std::path::Path::new(&print.file_path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| print.file_path.clone()),
+            printer: print.printer.clone(),
+            material: print.material.clone(),
+            settings: print.settings.clone(),
+            success: print.success,
+            notes: print.notes.clone(),
+            photo: print
+                .photo
+                .as_ref()
+                .map(|photo| model.asset_url(config, photo)),
+            printed_at: print.printed_at,
+        }
+    }
+}
+
+// ============ Metadata Types ============
+
+/// Fields which are left out stay unchanged
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
+pub struct UpdateMetadataRequest {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub origin: Option<String>,
+    pub license: Option<String>,
+    /// Content of README.md
+    pub readme: Option<String>,
+    /// Rename the folder after a title change, it is kept by default
+    pub move_folder: Option<bool>,
+}
+
+// ============ License Types ============
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct LicenseInfo {
+    pub spdx: String,
+    pub non_commercial: bool,
+    pub share_alike: bool,
+    pub attribution: bool,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct LicenseFacet {
+    /// SPDX identifier, or the original text if the license is unknown
+    pub license: String,
+    pub known: bool,
+    pub count: i32,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct AuthorFacet {
+    pub id: i32,
+    pub name: String,
+    pub count: i32,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct FileTypeFacet {
+    /// Lowercase file extension, e.g. "step"
+    pub file_type: String,
+    /// Models with at least one file of this type
+    pub count: i32,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct FavouriteFacet {
+    pub favourite: i32,
+    pub other: i32,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct CollectionFacet {
+    pub id: i32,
+    pub name: String,
+    pub count: i32,
+}
+
+/// Filter options of the model list, each counted under all filters except its own
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct ModelFacets {
+    pub licenses: Vec<LicenseFacet>,
+    pub authors: Vec<AuthorFacet>,
+    pub file_types: Vec<FileTypeFacet>,
+    pub favourite: FavouriteFacet,
+    pub collections: Vec<CollectionFacet>,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct UnknownLicenseGroup {
+    /// Original license text, empty if the ModelPack has none
+    pub license: String,
+    pub models: Vec<ModelReference>,
+}
+
+// ============ Author Types ============
+
+#[typeshare]
+#[derive(Debug, Serialize, Deserialize, Queryable, Identifiable, Selectable, Clone)]
+#[diesel(table_name = authors)]
+pub struct Author {
+    pub id: i32,
+    pub name: String,
+    pub profile_url: Option<String>,
+    pub date_added: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = authors)]
+pub struct NewAuthor {
+    pub name: String,
+}
+
+#[derive(Debug, Queryable, Identifiable, Selectable, Associations, Clone)]
+#[diesel(belongs_to(Author, foreign_key = author_id))]
+#[diesel(table_name = author_aliases)]
+pub struct AuthorAlias {
+    pub id: i32,
+    pub author_id: i32,
+    pub alias: String,
+    pub alias_key: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = author_aliases)]
+pub struct NewAuthorAlias {
+    pub author_id: i32,
+    pub alias: String,
+    pub alias_key: String,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct AuthorResponse {
+    pub id: i32,
+    pub name: String,
+    pub profile_url: Option<String>,
+    /// Spellings found in the ModelPacks
+    pub aliases: Vec<String>,
+    pub model_count: i32,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct DetailedAuthorResponse {
+    pub id: i32,
+    pub name: String,
+    pub profile_url: Option<String>,
+    pub aliases: Vec<String>,
+    pub models: Vec<ModelResponse>,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct UpdateAuthorRequest {
+    pub name: Option<String>,
+    /// An empty string removes the profile URL
+    pub profile_url: Option<String>,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct MergeAuthorRequest {
+    /// Author which keeps the models and aliases
+    pub into: i32,
+}
+
+// ============ User Types ============
+
+/// Each role may do everything the roles before it may do
+#[typeshare]
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Browsing, downloading and personal favourites
+    Viewer,
+    /// Uploading new models, logging prints and keeping collections
+    Contributor,
+    /// Changing and deleting models, files and authors, refreshing the library
+    Editor,
+    /// Managing users and emptying the trash
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Contributor => "contributor",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "contributor" => Ok(Role::Contributor),
+            "editor" => Ok(Role::Editor),
+            "admin" => Ok(Role::Admin),
+            _ => Err(anyhow::format_err!("unknown role {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Queryable, Identifiable, Selectable, Clone)]
+#[diesel(table_name = users)]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+    pub password_hash: String,
+    pub date_added: Option<NaiveDateTime>,
+    pub role: String,
+    pub email: Option<String>,
+}
+
+impl User {
+    /// Unknown roles in the database grant nothing beyond viewing
+    pub fn role(&self) -> Role {
+        Role::from_str(&self.role).unwrap_or(Role::Viewer)
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = users)]
+pub struct NewUser {
+    pub username: String,
+    pub password_hash: String,
+    pub role: String,
+    pub email: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = sessions)]
+pub struct NewSession {
+    pub user_id: i32,
+    pub token_hash: String,
+    pub expires_at: NaiveDateTime,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct UserResponse {
+    pub id: i32,
+    pub username: String,
+    pub role: Role,
+    pub email: Option<String>,
+    pub date_added: Option<NaiveDateTime>,
+}
+
+impl From<&User> for UserResponse {
+    fn from(user: &User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username.clone(),
+            role: user.role(),
+            email: user.email.clone(),
+            date_added: user.date_added,
+        }
+    }
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct CreateUserRequest {
+    pub username: String,
+    pub password: String,
+    /// Contributor if not given
+    pub role: Option<Role>,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct SetRoleRequest {
+    pub role: Role,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct ChangePasswordRequest {
+    /// Required unless an admin changes the password of another user
+    pub current_password: Option<String>,
+    pub new_password: String,
+}
+
+// ============ API Token Types ============
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    Read,
+    /// Uploading models and updating their files, includes library refreshes
+    Upload,
+    WriteMetadata,
+    /// Managing users and tokens, only effective for tokens of admins
+    Admin,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::Read => "read",
+            TokenScope::Upload => "upload",
+            TokenScope::WriteMetadata => "write_metadata",
+            TokenScope::Admin => "admin",
+        }
+    }
+
+    /// Scopes are stored comma separated, unknown ones are skipped
+    pub fn parse_list(scopes: &str) -> Vec<TokenScope> {
+        scopes
+            .split(',')
+            .filter_map(|scope| TokenScope::from_str(scope.trim()).ok())
+            .collect()
+    }
+}
+
+impl FromStr for TokenScope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "read" => Ok(TokenScope::Read),
+            "upload" => Ok(TokenScope::Upload),
+            "write_metadata" => Ok(TokenScope::WriteMetadata),
+            "admin" => Ok(TokenScope::Admin),
+            _ => Err(anyhow::format_err!("unknown token scope {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Queryable, Identifiable, Selectable, Clone)]
+#[diesel(table_name = api_tokens)]
+pub struct ApiToken {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub token_prefix: String,
+    pub token_hash: String,
+    pub scopes: String,
+    pub date_added: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = api_tokens)]
+pub struct NewApiToken {
+    pub user_id: i32,
+    pub name: String,
+    pub token_prefix: String,
+    pub token_hash: String,
+    pub scopes: String,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct ApiTokenResponse {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    /// Start of the token to tell tokens apart, the full token is only shown on creation
+    pub token_prefix: String,
+    pub scopes: Vec<TokenScope>,
+    pub date_added: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+impl From<&ApiToken> for ApiTokenResponse {
+    fn from(token: &ApiToken) -> Self {
+        Self {
+            id: token.id,
+            user_id: token.user_id,
+            name: token.name.clone(),
+            token_prefix: token.token_prefix.clone(),
+            scopes: TokenScope::parse_list(&token.scopes),
+            date_added: token.date_added,
+            last_used_at: token.last_used_at,
+        }
+    }
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    pub scopes: Vec<TokenScope>,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct CreatedApiTokenResponse {
+    /// Sent as `Authorization: Bearer <token>`, it can not be retrieved again
+    pub token: String,
+    pub api_token: ApiTokenResponse,
+}
+
+#[derive(Debug, Queryable, Identifiable, Selectable, Clone)]
+#[diesel(table_name = share_links)]
+pub struct ShareLink {
+    pub id: i32,
+    pub model_id: Option<i32>,
+    pub collection_id: Option<i32>,
+    pub token_prefix: String,
+    pub token_hash: String,
+    pub created_by: i32,
+    pub date_added: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = share_links)]
+pub struct NewShareLink {
+    pub model_id: Option<i32>,
+    pub collection_id: Option<i32>,
+    pub token_prefix: String,
+    pub token_hash: String,
+    pub created_by: i32,
+    pub expires_at: NaiveDateTime,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct ShareLinkResponse {
+    pub id: i32,
+    /// Exactly one of `model_id` and `collection_id` is set
+    pub model_id: Option<i32>,
+    pub collection_id: Option<i32>,
+    /// Start of the token to tell links apart, the full token is only shown on creation
+    pub token_prefix: String,
+    pub created_by: i32,
+    pub date_added: Option<NaiveDateTime>,
+    pub expires_at: NaiveDateTime,
+}
+
+impl From<&ShareLink> for ShareLinkResponse {
+    fn from(link: &ShareLink) -> Self {
+        Self {
+            id: link.id,
+            model_id: link.model_id,
+            collection_id: link.collection_id,
+            token_prefix: link.token_prefix.clone(),
+            created_by: link.created_by,
+            date_added: link.date_added,
+            expires_at: link.expires_at,
+        }
+    }
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct CreateShareLinkRequest {
+    /// Defaults to 7 days
+    pub expires_in_days: Option<i32>,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct CreatedShareLinkResponse {
+    /// Part of the shared URLs, it can not be retrieved again
+    pub token: String,
+    /// API path of the shared model or collection
+    pub url: String,
+    pub share_link: ShareLinkResponse,
+}
+
+/// What a share link grants access to, file and preview URLs point to the share routes
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct SharedResponse {
+    pub expires_at: NaiveDateTime,
+    pub model: Option<DetailedModelResponse>,
+    pub collection: Option<DetailedCollectionResponse>,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Upload,
+    Refresh,
+    Favourite,
+    Unfavourite,
+    AddModel,
+    RemoveModel,
+    Restore,
+    Purge,
+    Adopt,
+    Merge,
+}
+
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Upload => "upload",
+            AuditAction::Refresh => "refresh",
+            AuditAction::Favourite => "favourite",
+            AuditAction::Unfavourite => "unfavourite",
+            AuditAction::AddModel => "add_model",
+            AuditAction::RemoveModel => "remove_model",
+            AuditAction::Restore => "restore",
+            AuditAction::Purge => "purge",
+            AuditAction::Adopt => "adopt",
+            AuditAction::Merge => "merge",
+        }
+    }
+}
+
+impl FromStr for AuditAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "create" => Ok(AuditAction::Create),
+            "update" => Ok(AuditAction::Update),
+            "delete" => Ok(AuditAction::Delete),
+            "upload" => Ok(AuditAction::Upload),
+            "refresh" => Ok(AuditAction::Refresh),
+            "favourite" => Ok(AuditAction::Favourite),
+            "unfavourite" => Ok(AuditAction::Unfavourite),
+            "add_model" => Ok(AuditAction::AddModel),
+            "remove_model" => Ok(AuditAction::RemoveModel),
+            "restore" => Ok(AuditAction::Restore),
+            "purge" => Ok(AuditAction::Purge),
+            "adopt" => Ok(AuditAction::Adopt),
+            "merge" => Ok(AuditAction::Merge),
+            _ => Err(anyhow::format_err!("unknown audit action {}", s)),
+        }
+    }
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditTargetType {
+    Library,
+    Model,
+    File,
+    Collection,
+    Author,
+    Print,
+    Trash,
+    User,
+    ApiToken,
+    ShareLink,
+}
+
+impl AuditTargetType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditTargetType::Library => "library",
+            AuditTargetType::Model => "model",
+            AuditTargetType::File => "file",
+            AuditTargetType::Collection => "collection",
+            AuditTargetType::Author => "author",
+            AuditTargetType::Print => "print",
+            AuditTargetType::Trash => "trash",
+            AuditTargetType::User => "user",
+            AuditTargetType::ApiToken => "api_token",
+            AuditTargetType::ShareLink => "share_link",
+        }
+    }
+}
+
+impl FromStr for AuditTargetType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "library" => Ok(AuditTargetType::Library),
+            "model" => Ok(AuditTargetType::Model),
+            "file" => Ok(AuditTargetType::File),
+            "collection" => Ok(AuditTargetType::Collection),
+            "author" => Ok(AuditTargetType::Author),
+            "print" => Ok(AuditTargetType::Print),
+            "trash" => Ok(AuditTargetType::Trash),
+            "user" => Ok(AuditTargetType::User),
+            "api_token" => Ok(AuditTargetType::ApiToken),
+            "share_link" => Ok(AuditTargetType::ShareLink),
+            _ => Err(anyhow::format_err!("unknown audit target type {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Queryable, Identifiable, Selectable, Clone)]
+#[diesel(table_name = audit_log)]
+pub struct AuditEntry {
+    pub id: i32,
+    pub user_id: i32,
+    pub username: String,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i32>,
+    pub target_name: Option<String>,
+    pub summary: String,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = audit_log)]
+pub struct NewAuditEntry {
+    pub user_id: i32,
+    pub username: String,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<i32>,
+    pub target_name: Option<String>,
+    pub summary: String,
+    pub ip_address: Option<String>,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct AuditEntryResponse {
+    pub id: i32,
+    pub user_id: i32,
+    /// Name of the user at the time of the action
+    pub username: String,
+    pub action: AuditAction,
+    pub target_type: AuditTargetType,
+    pub target_id: Option<i32>,
+    pub target_name: Option<String>,
+    pub summary: String,
+    pub ip_address: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+impl TryFrom<&AuditEntry> for AuditEntryResponse {
+    type Error = Error;
+
+    fn try_from(entry: &AuditEntry) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: entry.id,
+            user_id: entry.user_id,
+            username: entry.username.clone(),
+            action: AuditAction::from_str(&entry.action)?,
+            target_type: AuditTargetType::from_str(&entry.target_type)?,
+            target_id: entry.target_id,
+            target_name: entry.target_name.clone(),
+            summary: entry.summary.clone(),
+            ip_address: entry.ip_address.clone(),
+            created_at: entry.created_at,
+        })
+    }
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct AuditLogResponse {
+    /// Newest entry first
+    pub entries: Vec<AuditEntryResponse>,
+    /// Number of entries matching the filters on all pages
+    pub total: i32,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+pub struct AuditParams {
+    pub user_id: Option<i32>,
+    pub action: Option<AuditAction>,
+    pub target_type: Option<AuditTargetType>,
+    pub target_id: Option<i32>,
+    /// Substring of the target name or the summary
+    pub q: Option<String>,
+    /// Only entries at or after this time, e.g. 2026-01-31T00:00:00
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+    pub page: Option<i64>,
+    pub page_size: Option<i64>,
+}
+
+// ============ Library Types ============
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct LibraryResponse {
+    pub name: String,
+    pub read_only: bool,
+    /// New models may be uploaded into it
+    pub uploads: bool,
+    /// URL prefix of its files
+    pub asset_prefix: String,
+    /// The library at LIBRARIES_PATH
+    pub main: bool,
+    pub model_count: i32,
+}
+
+#[derive(Deserialize, Default, JsonSchema)]
+pub struct DownloadParams {
+    /// The main library if omitted
+    pub library: Option<String>,
+}
+
+// ============ Bulk Types ============
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkAction {
+    /// Moves the models to the trash
+    Delete,
+    Favourite,
+    Unfavourite,
+    AddToCollection,
+    RemoveFromCollection,
+    SetLicense,
+    SetAuthor,
+    /// Rescans the model folders
+    Rescan,
+}
+
+/// Models to change, either by id or by the filters of the model list
+#[derive(Deserialize, JsonSchema)]
+pub struct BulkRequest {
+    pub ids: Option<Vec<i32>>,
+    /// Every model the list shows with these filters, page and page_size are ignored, at least one filter is required
+    pub filter: Option<ListModelParams>,
+    /// For add_to_collection and remove_from_collection
+    pub collection_id: Option<i32>,
+    /// For set_license
+    pub license: Option<String>,
+    /// For set_author
+    pub author: Option<String>,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct BulkItemResult {
+    pub id: i32,
+    pub title: Option<String>,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct BulkResponse {
+    pub action: BulkAction,
+    pub succeeded: i32,
+    pub failed: i32,
+    /// In the order of the ids, or of the model list for a filter
+    pub results: Vec<BulkItemResult>,
+}
+
+// ============ Error Types ============
+
+/// Body of every error response of the API
+#[typeshare]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+pub struct ErrorResponse {
+    pub status: u16,
+    /// Reason phrase of the status, e.g. "Not Found"
+    pub error: String,
+    pub message: String,
+}
@@ -1,7 +1,12 @@
 use anyhow::{Context, Ok as anyOk, Result};
-use axum::{extract::Multipart, extract::State, http::StatusCode, Json};
+use axum::{
+    extract::{Multipart, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use diesel_async::scoped_futures::ScopedFutureExt;
 use diesel_async::{AsyncConnection, RunQueryDsl};
-use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 use std::collections::VecDeque;
 use std::path::{Path, PathBuf};
 use tokio::fs::{self, File};
@@ -11,9 +16,17 @@ use uuid::Uuid;
 
 use diesel::prelude::*;
 
+use crate::audit::{self, AuditTarget};
+use crate::auth::CurrentUser;
+use crate::error::AppError;
+use crate::libraries::{self, Library};
 use crate::parse_library::{self, add_or_update_model};
 use crate::schema::models3d;
-use crate::types::Model3D;
+use crate::types::{
+    AuditAction, FileValidationStatus, Model3D, RevisionReason, UploadParams, UploadResponse,
+};
+use crate::v2::IfMatch;
+use crate::{duplicates, revisions, validation};
 
 use crate::Config;
 
@@ -23,11 +36,23 @@ fn cleanup_temp_dir(temp_dir: &PathBuf) {
         .unwrap_or_else(|e| debug!("Failed to remove temp dir: {}", e));
 }
 
-const MESH_FILE_FORMATS: &[&str] = &["obj", "stl", "3mf"];
-const CAD_FILE_FORMATS: &[&str] = &["step", "stp", "f3d", "scad", "igs", "iges"];
-const IMAGE_FILE_FORMATS: &[&str] = &["jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp"];
+fn upload_error(temp_dir: &PathBuf, status: StatusCode, message: impl Into<String>) -> AppError {
+    cleanup_temp_dir(temp_dir);
+    AppError::new(status, message)
+}
+
+/// A file of the multipart request, hashed while it was written to the upload cache
+pub struct UploadedFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub hash: String,
+}
+
+pub const MESH_FILE_FORMATS: &[&str] = &["obj", "stl", "3mf"];
+pub const CAD_FILE_FORMATS: &[&str] = &["step", "stp", "f3d", "scad", "igs", "iges"];
+pub const IMAGE_FILE_FORMATS: &[&str] = &["jpg", "jpeg", "png", "gif", "bmp", "tiff", "webp"];
 
-fn categorize_file(file_name: &PathBuf) -> &str {
+pub fn categorize_file(file_name: &Path) -> &str {
     if let Some(ext) = file_name.extension().and_then(|s| s.to_str()) {
         let ext = ext.to_ascii_lowercase();
         if MESH_FILE_FORMATS.contains(&ext.as_str()) {
@@ -44,6 +69,68 @@ fn categorize_file(file_name: &PathBuf) -> &str {
     }
 }
 
+fn extension_is_allowed(config: &Config, file_name: &Path) -> bool {
+    file_name
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            config
+                .upload_allowed_extensions
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(ext))
+        })
+}
+
+/// Builds the complete new version of a model in `staged_path`,
+/// the uploaded files are merged over a copy of the previous version
+async fn stage_model(
+    old_path: Option<&Path>,
+    uploaded_structure: &Path,
+    staged_path: &Path,
+) -> Result<()> {
+    fs::create_dir_all(staged_path).await?;
+    if let Some(old_path) = old_path {
+        merge_directories(old_path, staged_path, true).await?;
+    }
+    merge_directories(uploaded_structure, staged_path, true).await?;
+    anyOk(())
+}
+
+/// Replaces a model folder by renames only, the previous folder is kept until the swap is dropped
+struct Swap {
+    final_path: PathBuf,
+    staged_path: PathBuf,
+    /// current location of the model and where it is kept during the swap
+    previous: Option<(PathBuf, PathBuf)>,
+}
+
+impl Swap {
+    async fn apply(&self) -> Result<()> {
+        if let Some((old_path, backup_path)) = &self.previous {
+            fs::rename(old_path, backup_path)
+                .await
+                .with_context(|| format!("Failed to move {:?} aside", old_path))?;
+        }
+
+        if let Err(e) = fs::rename(&self.staged_path, &self.final_path).await {
+            if let Some((old_path, backup_path)) = &self.previous {
+                fs::rename(backup_path, old_path).await?;
+            }
+            return Err(e).with_context(|| format!("Failed to move {:?}", self.final_path));
+        }
+
+        anyOk(())
+    }
+
+    async fn revert(&self) -> Result<()> {
+        fs::rename(&self.final_path, &self.staged_path).await?;
+        if let Some((old_path, backup_path)) = &self.previous {
+            fs::rename(backup_path, old_path).await?;
+        }
+        anyOk(())
+    }
+}
+
 async fn merge_directories(src: &Path, dest: &Path, overwrite: bool) -> Result<()> {
     if !src.is_dir() || !dest.is_dir() {
         anyhow::bail!("Both paths must be directories");
@@ -81,47 +168,96 @@ async fn merge_directories(src: &Path, dest: &Path, overwrite: bool) -> Result<(
     anyOk(())
 }
 
+/// Records a successful upload in the audit log
+async fn record_upload<Conn>(
+    connection: &mut Conn,
+    user: &CurrentUser,
+    response: &UploadResponse,
+    kind: &str,
+) where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    if !response.success {
+        return;
+    }
+    let files = response.files.len();
+    if let Ok(model) = crate::slug::resolve_model(connection, &response.slug).await {
+        audit::record(
+            connection,
+            user,
+            AuditAction::Upload,
+            AuditTarget::model(&model),
+            format!("Uploaded {} files as {}", files, kind),
+        )
+        .await;
+    }
+}
+
 pub async fn handle_upload(
     State(state): State<crate::AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Query(params): Query<UploadParams>,
     multipart: Multipart,
-) -> Result<Json<Value>, StatusCode> {
-    let mut connection = state.pool.get().await.unwrap();
-    Ok(
-        handle_upload_internally(&mut connection, &state.config.clone(), multipart, None)
-            .await
-            .unwrap(),
+) -> Result<(StatusCode, Json<UploadResponse>), AppError> {
+    let library = libraries::requested_library(&state.config, params.library.as_deref())?;
+    library.ensure_uploads()?;
+
+    let mut connection = state.pool.get().await?;
+    let result = handle_upload_internally(
+        &mut connection,
+        &state.config,
+        library,
+        multipart,
+        None,
+        &IfMatch::default(),
+        params.strict.unwrap_or(false),
     )
+    .await;
+    if let Ok((_, Json(response))) = &result {
+        record_upload(&mut connection, &user, response, "a new model").await;
+    }
+    result
 }
 
 pub async fn handle_upload_update(
     State(state): State<crate::AppState>,
+    Extension(user): Extension<CurrentUser>,
     axum::extract::Path(slug): axum::extract::Path<String>,
+    if_match: IfMatch,
+    Query(params): Query<UploadParams>,
     multipart: Multipart,
-) -> Result<Json<Value>, StatusCode> {
-    let mut connection = state.pool.get().await.unwrap();
+) -> Result<(StatusCode, Json<UploadResponse>), AppError> {
+    let mut connection = state.pool.get().await?;
 
-    let result = models3d::dsl::models3d
-        .filter(models3d::dsl::name.eq(slug))
-        .first::<Model3D>(&mut connection)
-        .await
-        .unwrap();
+    let result = crate::slug::resolve_model(&mut connection, &slug).await?;
+    let library = result.writable_library(&state.config)?;
+    if_match.check_model(&mut connection, result.id).await?;
 
-    Ok(handle_upload_internally(
+    let result = handle_upload_internally(
         &mut connection,
-        &state.config.clone(),
+        &state.config,
+        library,
         multipart,
         Some(result),
+        &if_match,
+        params.strict.unwrap_or(false),
     )
-    .await
-    .unwrap())
+    .await;
+    if let Ok((_, Json(response))) = &result {
+        record_upload(&mut connection, &user, response, "a new version").await;
+    }
+    result
 }
 
 pub async fn handle_upload_internally<Conn>(
     mut connection: &mut Conn,
     config: &Config,
+    library: &Library,
     mut multipart: Multipart,
     existing_model: Option<Model3D>,
-) -> Result<Json<Value>, StatusCode>
+    if_match: &IfMatch,
+    strict: bool,
+) -> Result<(StatusCode, Json<UploadResponse>), AppError>
 where
     Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
 {
@@ -130,8 +266,11 @@ where
 
     if !Path::new(&temp_dir).exists() {
         fs::create_dir_all(&temp_dir).await.map_err(|_| {
-            cleanup_temp_dir(&temp_dir);
-            StatusCode::INTERNAL_SERVER_ERROR
+            upload_error(
+                &temp_dir,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to stage upload",
+            )
         })?;
     }
 
@@ -139,52 +278,163 @@ where
     let mut cad_files = Vec::new();
     let mut image_files = Vec::new();
     let mut uploaded_files = Vec::new();
+    let mut request_size: u64 = 0;
 
-    while let Some(field) = multipart.next_field().await.map_err(|_| {
-        cleanup_temp_dir(&temp_dir);
-        StatusCode::BAD_REQUEST
-    })? {
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| upload_error(&temp_dir, e.status(), e.body_text()))?
+    {
         let file_name = field
             .file_name()
             .map(|f| f.to_string())
             .unwrap_or_else(|| "unknown".to_string());
 
-        let data = field.bytes().await.map_err(|_| {
-            cleanup_temp_dir(&temp_dir);
-            StatusCode::BAD_REQUEST
-        })?;
-
         let file_path = temp_dir
             .clone()
             .join(sanitize_filename::sanitize(&file_name));
 
+        if !extension_is_allowed(config, &file_path) {
+            return Err(upload_error(
+                &temp_dir,
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                format!("File type of {} is not accepted", file_name),
+            ));
+        }
+
         let mut file = File::create(&file_path).await.map_err(|_| {
-            cleanup_temp_dir(&temp_dir);
-            StatusCode::INTERNAL_SERVER_ERROR
+            upload_error(
+                &temp_dir,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to store upload",
+            )
         })?;
 
-        file.write_all(&data).await.map_err(|_| {
-            cleanup_temp_dir(&temp_dir);
-            StatusCode::INTERNAL_SERVER_ERROR
+        let mut hasher = Sha256::new();
+        let mut file_size: u64 = 0;
+
+        while let Some(chunk) = field
+            .chunk()
+            .await
+            .map_err(|e| upload_error(&temp_dir, e.status(), e.body_text()))?
+        {
+            file_size += chunk.len() as u64;
+            request_size += chunk.len() as u64;
+
+            if file_size > config.max_upload_file_size() {
+                return Err(upload_error(
+                    &temp_dir,
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!(
+                        "{} exceeds the maximum file size of {} MiB",
+                        file_name, config.max_upload_file_size_mb
+                    ),
+                ));
+            }
+            if request_size > config.max_upload_request_size() {
+                return Err(upload_error(
+                    &temp_dir,
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    format!(
+                        "Upload exceeds the maximum request size of {} MiB",
+                        config.max_upload_request_size_mb
+                    ),
+                ));
+            }
+
+            hasher.update(&chunk);
+            file.write_all(&chunk).await.map_err(|_| {
+                upload_error(
+                    &temp_dir,
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to store upload",
+                )
+            })?;
+        }
+
+        file.flush().await.map_err(|_| {
+            upload_error(
+                &temp_dir,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to store upload",
+            )
         })?;
 
+        let uploaded_file = UploadedFile {
+            path: file_path.clone(),
+            size: file_size,
+            hash: format!("{:x}", hasher.finalize()),
+        };
+        debug!(
+            "Received {} ({} bytes, sha256 {})",
+            uploaded_file.path.display(),
+            uploaded_file.size,
+            uploaded_file.hash
+        );
+
         match categorize_file(&file_path) {
             "mesh" => mesh_files.push(file_path.clone()),
             "cad" => cad_files.push(file_path.clone()),
             "image" => image_files.push(file_path.clone()),
-            _ => {
-                info!("File {} is ignored", file_path.display());
-                uploaded_files.push(file_path.clone())
+            _ => info!("File {} is ignored", file_path.display()),
+        }
+        uploaded_files.push(uploaded_file);
+    }
+
+    let mut reports = Vec::new();
+    for uploaded_file in &uploaded_files {
+        reports.push(validation::validate_uploaded_file(uploaded_file.path.clone()).await);
+    }
+
+    let hashes = uploaded_files
+        .iter()
+        .map(|file| file.hash.clone())
+        .collect();
+    let existing_model_id = existing_model.as_ref().map(|model| model.id);
+    match duplicates::models_with_hashes(connection, hashes, existing_model_id).await {
+        Ok(duplicates) => {
+            for (report, uploaded_file) in reports.iter_mut().zip(&uploaded_files) {
+                let Some(models) = duplicates.get(&uploaded_file.hash) else {
+                    continue;
+                };
+                let titles: Vec<&str> = models.iter().map(|model| model.title.as_str()).collect();
+                report.warnings.push(format!(
+                    "An identical file already exists in {}",
+                    titles.join(", ")
+                ));
+                if report.status == FileValidationStatus::Ok {
+                    report.status = FileValidationStatus::Warning;
+                }
+                report.duplicate_of = models.clone();
             }
         }
+        Err(e) => error!("Failed to look up duplicates: {:?}", e),
+    }
+
+    let failed = reports
+        .iter()
+        .filter(|report| report.status == FileValidationStatus::Failed)
+        .count();
+    if strict && failed > 0 {
+        cleanup_temp_dir(&temp_dir);
+        let response = UploadResponse {
+            success: false,
+            slug: existing_model.map(|model| model.name).unwrap_or_default(),
+            message: format!("{} files failed validation", failed),
+            files: reports,
+        };
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(response)));
     }
 
     // Parse modelpack.json
     let modelpack_meta = parse_library::get_modelpack_meta(&temp_dir)
         .await
         .map_err(|_| {
-            cleanup_temp_dir(&temp_dir);
-            StatusCode::BAD_REQUEST
+            upload_error(
+                &temp_dir,
+                StatusCode::BAD_REQUEST,
+                "modelpack.json is missing or invalid",
+            )
         })?;
 
     let final_folder_name = sanitize_filename::sanitize(modelpack_meta.title);
@@ -197,8 +447,11 @@ where
     fs::create_dir_all(&tmp_final_structure)
         .await
         .map_err(|_| {
-            cleanup_temp_dir(&temp_dir);
-            StatusCode::INTERNAL_SERVER_ERROR
+            upload_error(
+                &temp_dir,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to stage upload",
+            )
         })?;
 
     // Move modelpack.json into the final structure
@@ -207,33 +460,49 @@ where
     fs::copy(&modelpack_json_path, &new_modelpack_json_path)
         .await
         .map_err(|_| {
-            cleanup_temp_dir(&temp_dir);
-            StatusCode::INTERNAL_SERVER_ERROR
+            upload_error(
+                &temp_dir,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to stage upload",
+            )
         })?;
 
-    // copy readme if exists
-    let readme_path = temp_dir.join("README.md");
+    // copy readme if exists, `readme.md` is accepted as well
+    let readme_path = uploaded_files
+        .iter()
+        .map(|uploaded_file| &uploaded_file.path)
+        .find(|path| {
+            path.file_name()
+                .is_some_and(|name| name.eq_ignore_ascii_case("README.md"))
+        });
     let new_readme_path = tmp_final_structure.join("README.md");
 
-    if readme_path.exists() {
-        fs::copy(&readme_path, &new_readme_path)
-            .await
-            .map_err(|_| {
-                cleanup_temp_dir(&temp_dir);
-                StatusCode::INTERNAL_SERVER_ERROR
-            })?;
+    if let Some(readme_path) = readme_path {
+        fs::copy(readme_path, &new_readme_path).await.map_err(|_| {
+            upload_error(
+                &temp_dir,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to stage upload",
+            )
+        })?;
     }
 
     // create final structure within tmp dir
     let images_dir = tmp_final_structure.join("images");
     let files_dir = tmp_final_structure.join("files");
     fs::create_dir_all(&images_dir).await.map_err(|_| {
-        cleanup_temp_dir(&temp_dir);
-        StatusCode::INTERNAL_SERVER_ERROR
+        upload_error(
+            &temp_dir,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to stage upload",
+        )
     })?;
     fs::create_dir_all(&files_dir).await.map_err(|_| {
-        cleanup_temp_dir(&temp_dir);
-        StatusCode::INTERNAL_SERVER_ERROR
+        upload_error(
+            &temp_dir,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Failed to stage upload",
+        )
     })?;
 
     let mut all_files = Vec::new();
@@ -244,71 +513,171 @@ where
     // Move files
     for (src, dest) in all_files {
         fs_extra::move_items(&[src], dest, &fs_extra::dir::CopyOptions::new()).map_err(|_| {
-            cleanup_temp_dir(&temp_dir);
-            StatusCode::INTERNAL_SERVER_ERROR
+            upload_error(
+                &temp_dir,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to stage upload",
+            )
         })?;
     }
 
-    let libraries_path = config.libraries_path.clone();
-    let final_path = libraries_path.join(&final_folder_name);
+    let final_path = library.path.join(&final_folder_name);
+    let old_path = existing_model
+        .as_ref()
+        .map(|model| model.absolute_path(config))
+        .transpose()
+        .map_err(|e| upload_error(&temp_dir, StatusCode::CONFLICT, e.to_string()))?;
+
+    if final_path.exists() && old_path.as_ref() != Some(&final_path) {
+        return Err(upload_error(
+            &temp_dir,
+            StatusCode::CONFLICT,
+            format!("A model in {} already exists", final_folder_name),
+        ));
+    }
 
-    let mut merge = false;
+    // Stage the complete new version next to the library, so it can be swapped in by renaming
+    let staging_dir = library.staging_dir().join(Uuid::new_v4().to_string());
+    let staged_path = staging_dir.join("new");
+    let backup_path = staging_dir.join("previous");
 
-    // rename existing model folder
-    if let Some(model_to_move) = existing_model {
-        merge = true;
-        let old_absolute_path = model_to_move.absolute_path(config);
-        if old_absolute_path != final_path {
-            debug!(
-                "Going to move {} to {}",
-                old_absolute_path.display(),
-                final_path.display()
-            );
-            tokio::fs::rename(old_absolute_path, &final_path)
-                .await
-                .map_err(|e| {
-                    error!("Rename operation failed: {:?}", e);
-                    cleanup_temp_dir(&temp_dir);
-                    StatusCode::INTERNAL_SERVER_ERROR
-                });
+    let staged = stage_model(old_path.as_deref(), &tmp_final_structure, &staged_path).await;
+    cleanup_temp_dir(&temp_dir);
+    if let Err(e) = staged {
+        error!("Staging {} failed: {:?}", final_folder_name, e);
+        cleanup_temp_dir(&staging_dir);
+        return Err(AppError::internal("Failed to stage upload"));
+    }
 
-            model_to_move.delete(config, &mut connection).await;
-        } else {
-            debug!("No Model rename detected");
+    // keep what the update replaces, it only becomes a revision once the update went through
+    let revision = match (&existing_model, &old_path) {
+        (Some(model), Some(old_path)) => {
+            let pending = async {
+                let changes = revisions::diff_model_dirs(old_path, &staged_path).await?;
+                if changes.is_empty() {
+                    return anyOk(None);
+                }
+                revisions::snapshot(config, model.id, RevisionReason::Update, old_path, changes)
+                    .await
+                    .map(Some)
+            }
+            .await;
+            match pending {
+                Ok(pending) => pending,
+                Err(e) => {
+                    error!(
+                        "Keeping the previous version of {} failed: {:?}",
+                        final_folder_name, e
+                    );
+                    cleanup_temp_dir(&staging_dir);
+                    return Err(AppError::internal("Failed to keep the previous version"));
+                }
+            }
         }
-    }
+        _ => None,
+    };
 
-    fs_extra::move_items(
-        &[tmp_final_structure],
-        libraries_path.clone(),
-        &fs_extra::dir::CopyOptions {
-            overwrite: merge,
-            copy_inside: merge,
-            ..fs_extra::dir::CopyOptions::new()
-        },
-    )
-    .map_err(|e| {
-        error!("Final move operation failed: {:?}, merge: {}", e, merge);
-        cleanup_temp_dir(&temp_dir);
-        StatusCode::INTERNAL_SERVER_ERROR
-    })?;
+    let swap = Swap {
+        final_path: final_path.clone(),
+        staged_path,
+        previous: old_path.map(|old_path| (old_path, backup_path)),
+    };
 
-    cleanup_temp_dir(&temp_dir);
+    if let Err(e) = swap.apply().await {
+        error!("Swapping in {} failed: {:?}", final_folder_name, e);
+        cleanup_temp_dir(&staging_dir);
+        if let Some(revision) = &revision {
+            revision.discard(config).await;
+        }
+        return Err(AppError::internal("Failed to move upload into the library"));
+    }
 
-    let model = add_or_update_model(config, &mut connection, &final_path)
-        .await
-        .unwrap();
-    model.scan(config, &mut connection).await;
+    let relative_final_path = final_folder_name.clone();
+    let indexed = connection
+        .transaction::<Model3D, anyhow::Error, _>(|connection| {
+            let existing_model = existing_model.as_ref();
+            let final_path = &final_path;
+            let revision = revision.as_ref();
+            async move {
+                if let Some(model) = existing_model {
+                    if_match.check_model(connection, model.id).await?;
+                }
+                if let Some(revision) = revision {
+                    revision.record(connection).await?;
+                }
+
+                // point the existing entry at the new folder to keep its id, favourite and collections
+                if let Some(model) = existing_model {
+                    diesel::update(models3d::dsl::models3d.find(model.id))
+                        .set(models3d::dsl::folder_path.eq(&relative_final_path))
+                        .execute(connection)
+                        .await?;
+                }
+
+                let model = add_or_update_model(config, connection, final_path).await?;
+                Ok(models3d::dsl::models3d
+                    .find(model.id)
+                    .first::<Model3D>(connection)
+                    .await?)
+            }
+            .scope_boxed()
+        })
+        .await;
+
+    let model = match indexed {
+        Ok(model) => model,
+        Err(e) => {
+            error!(
+                "Indexing {} failed, rolling back: {:?}",
+                final_folder_name, e
+            );
+            if let Err(e) = swap.revert().await {
+                error!("Rollback of {} failed: {:?}", final_folder_name, e);
+            }
+            if let Some(revision) = &revision {
+                revision.discard(config).await;
+            }
+            cleanup_temp_dir(&staging_dir);
+            return Err(match e.downcast::<AppError>() {
+                Ok(e) => e,
+                Err(_) => AppError::internal("Failed to index upload"),
+            });
+        }
+    };
+
+    // the previous version is only dropped once the new one is indexed
+    cleanup_temp_dir(&staging_dir);
     debug!("Indexed {}", final_folder_name);
 
-    let response = crate::types::UploadResponse {
+    // previews take long and must not hold the write lock, a failed scan is redone by the next refresh
+    if let Err(e) = model.scan(config, connection).await {
+        error!("Scanning {} failed: {:?}", final_folder_name, e);
+    }
+
+    let mut previews = Vec::new();
+    for file in model.get_files3d(connection).await.unwrap_or_default() {
+        if file.preview_image.is_some() {
+            previews.extend(file.get_file_name().await);
+        }
+    }
+    for report in reports.iter_mut() {
+        report.preview = previews.contains(&report.file_name);
+    }
+
+    let mut message = format!(
+        "Successfully uploaded {} files",
+        image_files.len() + mesh_files.len() + cad_files.len()
+    );
+    if failed > 0 {
+        message = format!("{}, {} failed validation", message, failed);
+    }
+
+    let response = UploadResponse {
         success: true,
         slug: model.name,
-        message: format!(
-            "Successfully uploaded {} files",
-            image_files.len() + mesh_files.len() + cad_files.len()
-        ),
+        message,
+        files: reports,
     };
 
-    Ok(Json(json!(response)))
+    Ok((StatusCode::OK, Json(response)))
 }
@@ -0,0 +1,425 @@
+use axum::{
+    extract::{FromRequestParts, Multipart, Path, Query, Request, State},
+    http::{header, request::Parts, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use diesel::prelude::*;
+use diesel_async::{AsyncConnection, RunQueryDsl};
+use serde::Serialize;
+
+use crate::audit::{self, AuditTarget};
+use crate::auth::CurrentUser;
+use crate::error::AppError;
+use crate::schema::{collections, files3d, models3d};
+use crate::stream_dl::{self, ZipArchive};
+use crate::types::{
+    AddModelToCollectionRequest, AuditAction, Collection, CollectionResponse,
+    CreateShareLinkRequest, CreatedShareLinkResponse, DetailedCollectionResponse,
+    DetailedModelResponse, File3D, Model3D, PatchCollectionRequest, PrintRequest, PrintResponse,
+    RestoreRevisionParams, RevisionResponse, UpdateMetadataRequest, UpdateSlugRequest,
+    UpdateSlugResponse, UploadParams, UploadResponse,
+};
+use crate::{metadata, prints, revisions, share, slug, upload, AppState};
+
+/// Condition of a request from its `If-Match` headers, without one every version matches
+#[derive(Default)]
+pub struct IfMatch(Option<String>);
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, AppError> {
+        let mut tags = Vec::new();
+        for value in parts.headers.get_all(header::IF_MATCH) {
+            let value = value
+                .to_str()
+                .map_err(|_| AppError::bad_request("The If-Match header is not valid"))?;
+            tags.push(value.to_string());
+        }
+        Ok(IfMatch((!tags.is_empty()).then(|| tags.join(","))))
+    }
+}
+
+impl IfMatch {
+    /// Strong comparison, so weak tags never match, `*` matches every existing resource
+    pub fn check(&self, etag: &str) -> Result<(), AppError> {
+        let Some(condition) = &self.0 else {
+            return Ok(());
+        };
+        if condition
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag == etag)
+        {
+            Ok(())
+        } else {
+            Err(AppError::new(
+                StatusCode::PRECONDITION_FAILED,
+                "The resource changed since it was fetched",
+            ))
+        }
+    }
+
+    /// Has to run on the connection and in the transaction that writes the change,
+    /// otherwise another request can change the model in between
+    pub async fn check_model<Conn>(
+        &self,
+        connection: &mut Conn,
+        model_id: i32,
+    ) -> Result<(), AppError>
+    where
+        Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+    {
+        if self.0.is_none() {
+            return Ok(());
+        }
+        let model = models3d::table
+            .find(model_id)
+            .first::<Model3D>(connection)
+            .await?;
+        self.check(&model_etag(connection, &model).await?)
+    }
+
+    pub fn check_collection(&self, collection: &Collection) -> Result<(), AppError> {
+        self.check(&collection_etag(collection)?)
+    }
+}
+
+/// A response with the `ETag` of the resource it contains
+pub struct Tagged<T> {
+    pub etag: String,
+    pub body: T,
+}
+
+impl<T: IntoResponse> IntoResponse for Tagged<T> {
+    fn into_response(self) -> Response {
+        match HeaderValue::from_str(&self.etag) {
+            Ok(etag) => ([(header::ETAG, etag)], self.body).into_response(),
+            Err(e) => AppError::internal(e).into_response(),
+        }
+    }
+}
+
+fn entity_tag(state: &impl Serialize) -> Result<String, AppError> {
+    let bytes = serde_json::to_vec(state).map_err(AppError::internal)?;
+    Ok(format!("\"{}\"", &sha256::digest(bytes.as_slice())[..32]))
+}
+
+/// Changes with the stored metadata and the files of the model
+async fn model_etag<Conn>(connection: &mut Conn, model: &Model3D) -> Result<String, AppError>
+where
+    Conn: AsyncConnection<Backend = diesel::sqlite::Sqlite>,
+{
+    let files = files3d::table
+        .filter(files3d::model_id.eq(model.id))
+        .order(files3d::id)
+        .load::<File3D>(connection)
+        .await?;
+    entity_tag(&(model, files))
+}
+
+/// Changes with the name, visibility and owner, not with the models in it
+fn collection_etag(collection: &Collection) -> Result<String, AppError> {
+    entity_tag(collection)
+}
+
+async fn find_model(state: &AppState, key: &str) -> Result<Model3D, AppError> {
+    let mut connection = state.pool.get().await?;
+    slug::resolve_model_ref(&mut connection, key).await
+}
+
+/// Adds the `Deprecation` header to the responses of the v1 routes
+pub async fn deprecated(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .insert("deprecation", HeaderValue::from_static("true"));
+    response
+}
+
+// ============ Models ============
+
+pub async fn get_model(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(key): Path<String>,
+) -> Result<Tagged<Json<DetailedModelResponse>>, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let model = slug::resolve_model_ref(&mut connection, &key).await?;
+    let etag = model_etag(&mut connection, &model).await?;
+    let response =
+        DetailedModelResponse::from_model_3d(&model, &state.config, user.id, &mut connection)
+            .await?;
+    Ok(Tagged {
+        etag,
+        body: Json(response),
+    })
+}
+
+pub async fn update_model(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(key): Path<String>,
+    if_match: IfMatch,
+    Json(request): Json<UpdateMetadataRequest>,
+) -> Result<Tagged<Json<DetailedModelResponse>>, AppError> {
+    let model = find_model(&state, &key).await?;
+    let Json(response) = metadata::update_metadata(
+        State(state.clone()),
+        Extension(user),
+        Path(model.name),
+        if_match,
+        Json(request),
+    )
+    .await?;
+
+    let mut connection = state.pool.get().await?;
+    let model = models3d::table
+        .find(response.id)
+        .first::<Model3D>(&mut connection)
+        .await?;
+    Ok(Tagged {
+        etag: model_etag(&mut connection, &model).await?,
+        body: Json(response),
+    })
+}
+
+pub async fn delete_model(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(key): Path<String>,
+    if_match: IfMatch,
+) -> Result<StatusCode, AppError> {
+    let model = find_model(&state, &key).await?;
+    crate::delete_model(State(state), Extension(user), Path(model.name), if_match).await
+}
+
+pub async fn refresh_model(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(key): Path<String>,
+) -> Result<Json<DetailedModelResponse>, AppError> {
+    let model = find_model(&state, &key).await?;
+    crate::refresh_model(State(state), Extension(user), Path(model.name)).await
+}
+
+/// Replaces the files of the model like the upload of a new version
+pub async fn replace_files(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(key): Path<String>,
+    if_match: IfMatch,
+    query: Query<UploadParams>,
+    multipart: Multipart,
+) -> Result<(StatusCode, Json<UploadResponse>), AppError> {
+    let model = find_model(&state, &key).await?;
+    upload::handle_upload_update(
+        State(state),
+        Extension(user),
+        Path(model.name),
+        if_match,
+        query,
+        multipart,
+    )
+    .await
+}
+
+async fn set_favourite(
+    state: &AppState,
+    user: &CurrentUser,
+    key: &str,
+    favourite: bool,
+) -> Result<StatusCode, AppError> {
+    let mut connection = state.pool.get().await?;
+
+    let model = slug::resolve_model_ref(&mut connection, key).await?;
+    if model.is_favourite_of(user.id, &mut connection).await? == favourite {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+    model
+        .set_favourite(user.id, favourite, &mut connection)
+        .await?;
+    let (action, summary) = if favourite {
+        (AuditAction::Favourite, "Added to the favourites")
+    } else {
+        (AuditAction::Unfavourite, "Removed from the favourites")
+    };
+    audit::record(
+        &mut connection,
+        user,
+        action,
+        AuditTarget::model(&model),
+        summary,
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn add_favourite(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(key): Path<String>,
+) -> Result<StatusCode, AppError> {
+    set_favourite(&state, &user, &key, true).await
+}
+
+pub async fn remove_favourite(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(key): Path<String>,
+) -> Result<StatusCode, AppError> {
+    set_favourite(&state, &user, &key, false).await
+}
+
+pub async fn change_slug(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(key): Path<String>,
+    request: Json<UpdateSlugRequest>,
+) -> Result<Json<UpdateSlugResponse>, AppError> {
+    let model = find_model(&state, &key).await?;
+    slug::handle_change_slug(State(state), Extension(user), Path(model.name), request).await
+}
+
+pub async fn list_revisions(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<Json<Vec<RevisionResponse>>, AppError> {
+    let model = find_model(&state, &key).await?;
+    revisions::list_revisions(State(state), Path(model.name)).await
+}
+
+pub async fn restore_revision(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path((key, revision_id)): Path<(String, i32)>,
+    query: Query<RestoreRevisionParams>,
+) -> Result<Json<DetailedModelResponse>, AppError> {
+    let model = find_model(&state, &key).await?;
+    revisions::restore_revision(
+        State(state),
+        Extension(user),
+        Path((model.name, revision_id)),
+        query,
+    )
+    .await
+}
+
+pub async fn list_prints(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<Json<Vec<PrintResponse>>, AppError> {
+    let model = find_model(&state, &key).await?;
+    prints::list_prints(State(state), Path(model.name)).await
+}
+
+pub async fn create_print(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(key): Path<String>,
+    request: Json<PrintRequest>,
+) -> Result<(StatusCode, Json<PrintResponse>), AppError> {
+    let model = find_model(&state, &key).await?;
+    prints::create_print(State(state), Extension(user), Path(model.name), request).await
+}
+
+pub async fn list_model_collections(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(key): Path<String>,
+) -> Result<Json<Vec<CollectionResponse>>, AppError> {
+    let model = find_model(&state, &key).await?;
+    crate::get_model_collections(State(state), Extension(user), Path(model.id)).await
+}
+
+pub async fn share_model(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(key): Path<String>,
+    request: Json<CreateShareLinkRequest>,
+) -> Result<(StatusCode, Json<CreatedShareLinkResponse>), AppError> {
+    let model = find_model(&state, &key).await?;
+    share::share_model(State(state), Extension(user), Path(model.name), request).await
+}
+
+/// The model folder as a zip archive
+pub async fn download_model(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+) -> Result<ZipArchive, AppError> {
+    let model = find_model(&state, &key).await?;
+    let library = model
+        .library(&state.config)
+        .map_err(|e| AppError::conflict(e.to_string()))?;
+    stream_dl::zip_folder_stream(&library.path, std::path::Path::new(&model.folder_path)).await
+}
+
+// ============ Collections ============
+
+pub async fn get_collection(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(id): Path<i32>,
+) -> Result<Tagged<Json<DetailedCollectionResponse>>, AppError> {
+    let etag = {
+        let mut connection = state.pool.get().await?;
+        collection_etag(&crate::visible_collection(&mut connection, &user, id).await?)?
+    };
+    let body = crate::get_collection(State(state), Extension(user), Path(id)).await?;
+    Ok(Tagged { etag, body })
+}
+
+pub async fn update_collection(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(id): Path<i32>,
+    if_match: IfMatch,
+    Json(request): Json<PatchCollectionRequest>,
+) -> Result<Tagged<Json<CollectionResponse>>, AppError> {
+    let body = crate::patch_collection(&state, &user, id, &if_match, request).await?;
+
+    let mut connection = state.pool.get().await?;
+    let collection = collections::table
+        .find(id)
+        .first::<Collection>(&mut connection)
+        .await?;
+    Ok(Tagged {
+        etag: collection_etag(&collection)?,
+        body,
+    })
+}
+
+pub async fn delete_collection(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path(id): Path<i32>,
+    if_match: IfMatch,
+) -> Result<StatusCode, AppError> {
+    crate::delete_collection(State(state), Extension(user), Path(id), if_match).await
+}
+
+pub async fn add_collection_model(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path((id, key)): Path<(i32, String)>,
+) -> Result<StatusCode, AppError> {
+    let model = find_model(&state, &key).await?;
+    let request = AddModelToCollectionRequest {
+        model_id: model.id,
+        collection_id: id,
+    };
+    crate::add_model_to_collection(State(state), Extension(user), Json(request)).await
+}
+
+pub async fn remove_collection_model(
+    State(state): State<AppState>,
+    Extension(user): Extension<CurrentUser>,
+    Path((id, key)): Path<(i32, String)>,
+) -> Result<StatusCode, AppError> {
+    let model = find_model(&state, &key).await?;
+    crate::remove_model_from_collection(State(state), Extension(user), Path((id, model.id))).await
+}
@@ -0,0 +1,143 @@
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use stl_io::IndexedMesh;
+use tracing::debug;
+
+use crate::convert;
+use crate::types::{FileValidationReport, FileValidationStatus};
+use crate::upload::categorize_file;
+
+const METADATA_FILES: &[&str] = &["modelpack.json", "README.md"];
+
+/// Whether a file is part of the ModelPack metadata, names are compared case-insensitively
+pub fn is_metadata_file(file_name: &str) -> bool {
+    METADATA_FILES
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(file_name))
+}
+
+fn report(file_name: String, category: &str) -> FileValidationReport {
+    FileValidationReport {
+        file_name,
+        category: category.to_string(),
+        status: FileValidationStatus::Ok,
+        triangle_count: None,
+        warnings: Vec::new(),
+        error: None,
+        preview: false,
+        duplicate_of: Vec::new(),
+    }
+}
+
+fn check_mesh(report: &mut FileValidationReport, mesh: anyhow::Result<IndexedMesh>) {
+    let mesh = match mesh {
+        Ok(mesh) => mesh,
+        Err(e) => {
+            report.status = FileValidationStatus::Failed;
+            report.error = Some(format!("Unable to read file: {}", e));
+            return;
+        }
+    };
+
+    report.triangle_count = Some(mesh.faces.len().try_into().unwrap_or(i32::MAX));
+
+    if mesh.faces.is_empty() {
+        report.status = FileValidationStatus::Failed;
+        report.error = Some("The mesh does not contain any triangles".to_string());
+    } else if let Err(e) = mesh.validate() {
+        report.status = FileValidationStatus::Warning;
+        report.warnings.push(format!(
+            "The mesh is not closed or has degenerate faces: {}",
+            e
+        ));
+    }
+}
+
+fn check_threemf(report: &mut FileValidationReport, path: &Path) {
+    let archive = File::open(path)
+        .map_err(anyhow::Error::from)
+        .and_then(|file| zip::ZipArchive::new(file).map_err(anyhow::Error::from));
+
+    match archive {
+        Ok(archive) => {
+            if !archive
+                .file_names()
+                .any(|name| name.eq_ignore_ascii_case("3D/3dmodel.model"))
+            {
+                report.status = FileValidationStatus::Failed;
+                report.error = Some("The 3MF package does not contain a 3D model".to_string());
+            }
+        }
+        Err(e) => {
+            report.status = FileValidationStatus::Failed;
+            report.error = Some(format!("Unable to read 3MF package: {}", e));
+        }
+    }
+}
+
+/// Parses a file with the converters to check whether it can be used at all,
+/// this is blocking and may take a while for CAD files
+pub fn validate_file(path: &Path) -> FileValidationReport {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let path_buf = path.to_path_buf();
+
+    if is_metadata_file(&file_name) {
+        return report(file_name, "metadata");
+    }
+
+    let category = categorize_file(path);
+    let mut report = report(file_name, category);
+
+    if std::fs::metadata(path).map(|m| m.len()).unwrap_or(0) == 0 {
+        report.status = FileValidationStatus::Failed;
+        report.error = Some("The file is empty".to_string());
+        return report;
+    }
+
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    match (category, extension.as_str()) {
+        ("mesh", "stl") => check_mesh(&mut report, convert::load_stl(&path_buf)),
+        ("mesh", "obj") => check_mesh(&mut report, convert::read_obj(&path_buf)),
+        ("mesh", "3mf") => check_threemf(&mut report, path),
+        ("cad", "step" | "stp") => check_mesh(&mut report, convert::load_step(&path_buf)),
+        ("cad", "iges" | "igs") => check_mesh(&mut report, convert::load_iges(&path_buf)),
+        ("cad", _) | ("image", _) => report.status = FileValidationStatus::Unchecked,
+        _ => {
+            report.status = FileValidationStatus::Ignored;
+            report
+                .warnings
+                .push("The file type is not part of a ModelPack and was ignored".to_string());
+        }
+    }
+
+    debug!("Validated {}: {:?}", path.display(), report.status);
+    report
+}
+
+/// Runs [`validate_file`] on the blocking pool, a panicking loader counts as a failed file
+pub async fn validate_uploaded_file(path: PathBuf) -> FileValidationReport {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let category = categorize_file(&path).to_string();
+
+    match tokio::task::spawn_blocking(move || validate_file(&path)).await {
+        Ok(report) => report,
+        Err(e) => {
+            let mut report = report(file_name, &category);
+            report.status = FileValidationStatus::Failed;
+            report.error = Some(format!("Unable to read file: {}", e));
+            report
+        }
+    }
+}
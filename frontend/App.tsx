@@ -5,7 +5,7 @@ import { ThemeProvider, useTheme } from "./components/theme-provider";
 import Models from "./Models";
 import Collections from "./Collections";
 import { BrowserRouter, Routes, Route, Link } from "react-router-dom";
-import { RefreshCcw, Upload } from "lucide-react";
+import { LogOut, RefreshCcw, Upload } from "lucide-react";
 import { useState } from "react";
 
 import { NavLink } from "react-router-dom";
@@ -18,6 +18,8 @@ import EditModel from "./EditModel";
 import SearchView from "./SearchView";
 import NotFound from "./NotFound";
 import FavouriteModels from "./FavouriteModels";
+import Login from "./Login";
+import { logout } from "./lib/auth";
 
 const getFillColor = (theme: string) => {
     if (theme === "system") {
@@ -98,6 +100,9 @@ function Navbar({ searchValue, setSearchValue }: { searchValue: string; setSearc
                         </Button>
                     </Link>
                     <Refresh />
+                    <Button variant="outline" size="icon" onClick={logout} title="Logout">
+                        <LogOut />
+                    </Button>
                     <Link to="https://github.com/fidoriel/MeshVault" target="_blank">
                         <GitHubButton />
                     </Link>
@@ -123,6 +128,7 @@ function App() {
                         <Route path="/model/:slug/edit" element={<EditModel />} />
                         <Route path="/modelpack" element={<AboutModelPack />} />
                         <Route path="/favourite" element={<FavouriteModels />} />
+                        <Route path="/login" element={<Login />} />
                         <Route
                             path="/search"
                             element={<SearchView searchValue={searchValue} setSearchValue={setSearchValue} />}
@@ -2,7 +2,7 @@ import { useEffect, useState } from "react";
 import { Card, CardContent } from "@/components/ui/card";
 import { Button } from "@/components/ui/button";
 import { Plus, Pencil, Trash2 } from "lucide-react";
-import { CollectionResponse, DetailedCollectionResponse } from "./bindings";
+import { CollectionResponse, CollectionVisibility, DetailedCollectionResponse } from "./bindings";
 import { BACKEND_BASE_URL } from "./lib/api";
 import {
     Dialog,
@@ -14,6 +14,7 @@ import {
 } from "@/components/ui/dialog";
 import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
+import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from "@/components/ui/select";
 import {
     AlertDialog,
     AlertDialogAction,
@@ -27,6 +28,37 @@ import {
 import { useToast } from "./hooks/use-toast";
 import { ModelCard } from "./Models";
 
+const VISIBILITY_LABELS: Record<CollectionVisibility, string> = {
+    [CollectionVisibility.Private]: "Private, only you",
+    [CollectionVisibility.Shared]: "Shared, everyone can add models",
+    [CollectionVisibility.Public]: "Public, everyone can view",
+};
+
+function VisibilitySelect({
+    id,
+    value,
+    onChange,
+}: {
+    id: string;
+    value: CollectionVisibility;
+    onChange: (value: CollectionVisibility) => void;
+}) {
+    return (
+        <Select value={value} onValueChange={(value) => onChange(value as CollectionVisibility)}>
+            <SelectTrigger id={id} className="col-span-3">
+                <SelectValue />
+            </SelectTrigger>
+            <SelectContent>
+                {Object.values(CollectionVisibility).map((visibility) => (
+                    <SelectItem key={visibility} value={visibility}>
+                        {VISIBILITY_LABELS[visibility]}
+                    </SelectItem>
+                ))}
+            </SelectContent>
+        </Select>
+    );
+}
+
 function CollectionCard({
     collection,
     onEdit,
@@ -119,6 +151,7 @@ function CreateCollectionDialog({
     onCreate: () => void;
 }) {
     const [name, setName] = useState("");
+    const [visibility, setVisibility] = useState<CollectionVisibility>(CollectionVisibility.Private);
     const { toast } = useToast();
 
     const handleCreate = async () => {
@@ -137,7 +170,7 @@ function CreateCollectionDialog({
                 headers: {
                     "Content-Type": "application/json",
                 },
-                body: JSON.stringify({ name: name.trim() }),
+                body: JSON.stringify({ name: name.trim(), visibility }),
             });
 
             if (!response.ok) {
@@ -182,6 +215,12 @@ function CreateCollectionDialog({
                             placeholder="My Collection"
                         />
                     </div>
+                    <div className="grid grid-cols-4 items-center gap-4">
+                        <Label htmlFor="visibility" className="text-right">
+                            Visibility
+                        </Label>
+                        <VisibilitySelect id="visibility" value={visibility} onChange={setVisibility} />
+                    </div>
                 </div>
                 <DialogFooter>
                     <Button onClick={handleCreate}>Create Collection</Button>
@@ -203,11 +242,13 @@ function EditCollectionDialog({
     onUpdate: () => void;
 }) {
     const [name, setName] = useState("");
+    const [visibility, setVisibility] = useState<CollectionVisibility>(CollectionVisibility.Private);
     const { toast } = useToast();
 
     useEffect(() => {
         if (collection) {
             setName(collection.name);
+            setVisibility(collection.visibility);
         }
     }, [collection]);
 
@@ -229,7 +270,7 @@ function EditCollectionDialog({
                 headers: {
                     "Content-Type": "application/json",
                 },
-                body: JSON.stringify({ name: name.trim() }),
+                body: JSON.stringify({ name: name.trim(), visibility }),
             });
 
             if (!response.ok) {
@@ -238,7 +279,7 @@ function EditCollectionDialog({
 
             toast({
                 title: "Success",
-                description: `Collection renamed to "${name}"`,
+                description: `Collection "${name}" updated`,
             });
 
             onOpenChange(false);
@@ -258,7 +299,7 @@ function EditCollectionDialog({
             <DialogContent>
                 <DialogHeader>
                     <DialogTitle>Edit Collection</DialogTitle>
-                    <DialogDescription>Change the collection name.</DialogDescription>
+                    <DialogDescription>Change the collection name and who can see it.</DialogDescription>
                 </DialogHeader>
                 <div className="grid gap-4 py-4">
                     <div className="grid grid-cols-4 items-center gap-4">
@@ -273,6 +314,12 @@ function EditCollectionDialog({
                             placeholder="My Collection"
                         />
                     </div>
+                    <div className="grid grid-cols-4 items-center gap-4">
+                        <Label htmlFor="edit-visibility" className="text-right">
+                            Visibility
+                        </Label>
+                        <VisibilitySelect id="edit-visibility" value={visibility} onChange={setVisibility} />
+                    </div>
                 </div>
                 <DialogFooter>
                     <Button onClick={handleUpdate}>Save Changes</Button>
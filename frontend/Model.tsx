@@ -56,7 +56,7 @@ function OptionsDropdownMenu({ model }: { model: DetailedModelResponse }) {
                 }
                 toast({
                     title: `Deleting model "${model.title}" successful`,
-                    description: `It is now permanently removed`,
+                    description: `It was moved to the trash`,
                 });
                 navigate("/");
             })
@@ -84,8 +84,7 @@ function OptionsDropdownMenu({ model }: { model: DetailedModelResponse }) {
                     <AlertDialogHeader>
                         <AlertDialogTitle>Are you absolutely sure to delete "{model.title}"?</AlertDialogTitle>
                         <AlertDialogDescription>
-                            This action cannot be undone. This will permanently delete "{model.title}" from database and
-                            filesystem.
+                            "{model.title}" will be moved to the trash and can be restored until it is purged.
                         </AlertDialogDescription>
                     </AlertDialogHeader>
                     <AlertDialogFooter>
@@ -248,7 +247,7 @@ function InfoCard({ model, refresh }: { model: DetailedModelResponse; refresh: (
             <div className="mb-6">
                 <div className="space-y-4">
                     <Link
-                        to={`/?author=${encodeURIComponent(model.author || "")}`}
+                        to={`/?author_id=${model.author_id ?? ""}&author=${encodeURIComponent(model.author || "")}`}
                         className="font-medium text-gray-400 hover:text-primary hover:underline cursor-pointer inline-block"
                     >
                         {model.author}
@@ -271,7 +270,7 @@ function InfoCard({ model, refresh }: { model: DetailedModelResponse; refresh: (
                 <Button
                     size="lg"
                     className="w-full"
-                    onClick={() => (window.location.href = BACKEND_BASE_URL + "/api/download/" + model.package_name)}
+                    onClick={() => (window.location.href = BACKEND_BASE_URL + "/api/download/" + model.package_name + "?library=" + encodeURIComponent(model.library))}
                 >
                     <Download className="mr-2 h-5 w-5" />
                     Download
@@ -300,6 +299,9 @@ function InfoCard({ model, refresh }: { model: DetailedModelResponse; refresh: (
             <div className="space-y-2">
                 <div>
                     <span className="font-bold">License:</span> {model.license}
+                    {model.license_info && model.license_info.spdx !== model.license && (
+                        <span className="text-muted-foreground ml-1">({model.license_info.spdx})</span>
+                    )}
                 </div>
                 <div>
                     <span className="font-bold">Origin URL:</span>
@@ -342,7 +344,7 @@ function File({ file, reload: reload }: { file: DetailedFileResponse; reload: ()
                 }
                 toast({
                     title: `Deleting file "${file.name}" successful`,
-                    description: `It is now permanently removed`,
+                    description: `It was moved to the trash`,
                 });
                 reload();
             })
@@ -462,8 +464,7 @@ function File({ file, reload: reload }: { file: DetailedFileResponse; reload: ()
                     <AlertDialogHeader>
                         <AlertDialogTitle>Are you absolutely sure to delete "{file.name}"?</AlertDialogTitle>
                         <AlertDialogDescription>
-                            This action cannot be undone. This will permanently delete "{file.name}" from database and
-                            filesystem.
+                            "{file.name}" will be moved to the trash and can be restored until it is purged.
                         </AlertDialogDescription>
                     </AlertDialogHeader>
                     <AlertDialogFooter>
@@ -491,7 +492,7 @@ function FileList({ model, reload: reload }: { model: DetailedModelResponse; rel
                 <Button
                     variant="outline"
                     className="flex items-center gap-2"
-                    onClick={() => (window.location.href = BACKEND_BASE_URL + "/api/download/" + model.package_name)}
+                    onClick={() => (window.location.href = BACKEND_BASE_URL + "/api/download/" + model.package_name + "?library=" + encodeURIComponent(model.library))}
                 >
                     <Download size={16} />
                     All Files (483 KB)
@@ -509,6 +510,7 @@ function FileList({ model, reload: reload }: { model: DetailedModelResponse; rel
 
 function Model() {
     const { slug } = useParams();
+    const navigate = useNavigate();
 
     const [model, setModel] = useState<DetailedModelResponse>();
     const { toast } = useToast();
@@ -529,6 +531,10 @@ function Model() {
             .then((response_models: DetailedModelResponse) => {
                 setModel(response_models);
                 document.title = response_models.title + " - MeshVault";
+                // old slugs are redirected by the backend, show the current one
+                if (response_models.name !== slug) {
+                    navigate(`/model/${response_models.name}`, { replace: true });
+                }
             })
             .catch((error) => {
                 console.error("Fetch error:", error);
@@ -3,7 +3,7 @@ import { Card, CardContent } from "@/components/ui/card";
 import { Heart, ChevronDown, ChevronRight, Loader2 } from "lucide-react";
 import { useState } from "react";
 import { Collapsible, CollapsibleContent, CollapsibleTrigger } from "@/components/ui/collapsible";
-import { ModelResponse, ModelResponseList } from "./bindings";
+import { FileTypeFacet, LicenseFacet, ModelResponse, ModelResponseList } from "./bindings";
 import { BACKEND_BASE_URL } from "./lib/api";
 import { Link, useSearchParams } from "react-router-dom";
 import { Checkbox } from "./components/ui/checkbox";
@@ -56,7 +56,7 @@ export function ModelCard({ model }: { model: ModelResponse }) {
             <div className="p-3">
                 <div className="flex items-center gap-2 mb-2">
                     <Link
-                        to={`/?author=${encodeURIComponent(model.author || "")}`}
+                        to={`/?author_id=${model.author_id ?? ""}&author=${encodeURIComponent(model.author || "")}`}
                         className="text-sm text-muted-foreground hover:text-primary hover:underline"
                     >
                         {model.author}
@@ -88,10 +88,13 @@ export function ModelCard({ model }: { model: ModelResponse }) {
 function Models() {
     const [searchParams] = useSearchParams();
     const authorFilter = searchParams.get("author");
+    const authorIdFilter = searchParams.get("author_id");
 
     const [models, setModels] = useState<ModelResponse[]>([]);
-    const [licenses, setLicenses] = useState<string[]>([]);
+    const [licenses, setLicenses] = useState<LicenseFacet[]>([]);
     const [selectedLicenses, setSelectedLicenses] = useState<string[]>([]);
+    const [fileTypes, setFileTypes] = useState<FileTypeFacet[]>([]);
+    const [selectedFileTypes, setSelectedFileTypes] = useState<string[]>([]);
     const [page, setPage] = useState(1);
     const [isLoading, setIsLoading] = useState(false);
     const [hasMore, setHasMore] = useState(true);
@@ -115,7 +118,13 @@ function Models() {
                     queryParams.append("licenses", selectedLicensesString);
                 }
 
-                if (authorFilter) {
+                if (selectedFileTypes.length > 0) {
+                    queryParams.append("file_types", selectedFileTypes.join(","));
+                }
+
+                if (authorIdFilter) {
+                    queryParams.append("author_id", authorIdFilter);
+                } else if (authorFilter) {
                     queryParams.append("author", authorFilter);
                 }
 
@@ -129,7 +138,8 @@ function Models() {
 
                 const responseModels: ModelResponseList = await response.json();
 
-                setLicenses(responseModels.licenses);
+                setLicenses(responseModels.facets.licenses);
+                setFileTypes(responseModels.facets.file_types);
 
                 if (reset) {
                     setModels(responseModels.models);
@@ -147,7 +157,7 @@ function Models() {
                 setIsLoading(false);
             }
         },
-        [selectedLicenses, authorFilter, isLoading],
+        [selectedLicenses, selectedFileTypes, authorFilter, authorIdFilter, isLoading],
     );
 
     useEffect(() => {
@@ -156,7 +166,7 @@ function Models() {
         setHasMore(true);
         fetchModels(1, true);
         // eslint-disable-next-line react-hooks/exhaustive-deps
-    }, [selectedLicenses, authorFilter]);
+    }, [selectedLicenses, selectedFileTypes, authorFilter, authorIdFilter]);
 
     useEffect(() => {
         document.title = "MeshVault";
@@ -194,6 +204,14 @@ function Models() {
         );
     };
 
+    const handleFileTypeChange = (fileType: string) => {
+        setSelectedFileTypes((prevSelected) =>
+            prevSelected.includes(fileType)
+                ? prevSelected.filter((item) => item !== fileType)
+                : [...prevSelected, fileType],
+        );
+    };
+
     return (
         <div className="min-h-screen bg-background text-foreground">
             <div className="max-w-screen-2xl mx-auto p-3">
@@ -206,18 +224,37 @@ function Models() {
                         <div className="bg-card rounded-lg p-4 space-y-4 border border-border">
                             <FilterSection title="License">
                                 <div className="space-y-2 pl-4">
-                                    {licenses.map((license) => (
-                                        <div className="flex items-center space-x-2" key={license}>
+                                    {licenses.map((facet) => (
+                                        <div className="flex items-center space-x-2" key={facet.license}>
+                                            <Checkbox
+                                                id={facet.license}
+                                                checked={selectedLicenses.includes(facet.license)}
+                                                onCheckedChange={() => handleLicenseChange(facet.license)}
+                                            />
+                                            <label
+                                                htmlFor={facet.license}
+                                                className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
+                                            >
+                                                {facet.license} ({facet.count})
+                                            </label>
+                                        </div>
+                                    ))}
+                                </div>
+                            </FilterSection>
+                            <FilterSection title="File Type">
+                                <div className="space-y-2 pl-4">
+                                    {fileTypes.map((facet) => (
+                                        <div className="flex items-center space-x-2" key={facet.file_type}>
                                             <Checkbox
-                                                id={license}
-                                                checked={selectedLicenses.includes(license)}
-                                                onCheckedChange={() => handleLicenseChange(license)}
+                                                id={`file-type-${facet.file_type}`}
+                                                checked={selectedFileTypes.includes(facet.file_type)}
+                                                onCheckedChange={() => handleFileTypeChange(facet.file_type)}
                                             />
                                             <label
-                                                htmlFor={license}
+                                                htmlFor={`file-type-${facet.file_type}`}
                                                 className="text-sm font-medium leading-none peer-disabled:cursor-not-allowed peer-disabled:opacity-70"
                                             >
-                                                {license}
+                                                {facet.file_type.toUpperCase()} ({facet.count})
                                             </label>
                                         </div>
                                     ))}
@@ -6,7 +6,7 @@ import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
 import { Textarea } from "@/components/ui/textarea";
 import { Alert, AlertDescription, AlertTitle } from "@/components/ui/alert";
-import { BACKEND_BASE_URL } from "./lib/api";
+import { BACKEND_BASE_URL, errorMessage } from "./lib/api";
 import { useToast } from "./hooks/use-toast";
 import { ModelPackV0_1, UploadResponse } from "./bindings";
 import { useNavigate } from "react-router-dom";
@@ -98,7 +98,7 @@ function UploadModel() {
             });
 
             if (!response.ok) {
-                const errorText = await response.text();
+                const errorText = await errorMessage(response);
                 throw new Error(errorText || "Upload failed");
             }
 
@@ -5,3 +5,13 @@ if (backendBaseUrl.endsWith("/")) {
 }
 
 export const BACKEND_BASE_URL = backendBaseUrl;
+
+// the message of an error response of the API, which sends errors as ErrorResponse JSON
+export async function errorMessage(response: Response): Promise<string> {
+    const text = await response.text();
+    try {
+        return JSON.parse(text).message || text;
+    } catch {
+        return text;
+    }
+}
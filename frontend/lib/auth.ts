@@ -0,0 +1,27 @@
+import { BACKEND_BASE_URL } from "./api";
+
+export const LOGIN_PATH = "/login";
+
+// sends the session cookie with every request and sends the user to the login page once it expired
+export function installAuthFetch() {
+    const originalFetch = window.fetch.bind(window);
+
+    window.fetch = async (input: RequestInfo | URL, init?: RequestInit) => {
+        const response = await originalFetch(input, { credentials: "include", ...init });
+        const url = input instanceof Request ? input.url : input.toString();
+        if (
+            response.status === 401 &&
+            !url.endsWith("/api/auth/login") &&
+            window.location.pathname !== LOGIN_PATH
+        ) {
+            const next = encodeURIComponent(window.location.pathname + window.location.search);
+            window.location.assign(`${LOGIN_PATH}?next=${next}`);
+        }
+        return response;
+    };
+}
+
+export async function logout() {
+    await fetch(BACKEND_BASE_URL + "/api/auth/logout", { method: "POST" });
+    window.location.assign(LOGIN_PATH);
+}
@@ -0,0 +1,3 @@
+-- Drop slug history
+DROP INDEX IF EXISTS idx_slug_history_model_id;
+DROP TABLE IF EXISTS slug_history;
@@ -0,0 +1,10 @@
+-- Previous slugs of a model so old links keep working after a rename
+CREATE TABLE slug_history (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    model_id INTEGER NOT NULL,
+    slug VARCHAR(4096) NOT NULL UNIQUE,
+    date_added TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (model_id) REFERENCES models3d(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_slug_history_model_id ON slug_history(model_id);
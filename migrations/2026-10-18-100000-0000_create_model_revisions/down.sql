@@ -0,0 +1,5 @@
+-- Drop revision history
+DROP INDEX IF EXISTS idx_revision_files_revision_id;
+DROP INDEX IF EXISTS idx_model_revisions_model_id;
+DROP TABLE IF EXISTS revision_files;
+DROP TABLE IF EXISTS model_revisions;
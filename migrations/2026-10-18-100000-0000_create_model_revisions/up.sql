@@ -0,0 +1,21 @@
+-- Snapshots of files replaced or deleted in a model, the content is stored below data_dir/revisions
+CREATE TABLE model_revisions (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    model_id INTEGER NOT NULL,
+    reason VARCHAR(32) NOT NULL,
+    storage_dir VARCHAR(4096) NOT NULL,
+    date_added TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (model_id) REFERENCES models3d(id) ON DELETE CASCADE
+);
+
+CREATE TABLE revision_files (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    revision_id INTEGER NOT NULL,
+    file_path VARCHAR(4096) NOT NULL,
+    change_type VARCHAR(32) NOT NULL,
+    stored BOOLEAN NOT NULL DEFAULT 0,
+    FOREIGN KEY (revision_id) REFERENCES model_revisions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_model_revisions_model_id ON model_revisions(model_id);
+CREATE INDEX idx_revision_files_revision_id ON revision_files(revision_id);
@@ -0,0 +1,3 @@
+-- Drop trash
+DROP INDEX IF EXISTS idx_trash_date_deleted;
+DROP TABLE IF EXISTS trash;
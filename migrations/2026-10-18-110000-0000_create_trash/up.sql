@@ -0,0 +1,13 @@
+-- Deleted models and files, the content is moved to the trash folder of the library
+CREATE TABLE trash (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    kind VARCHAR(16) NOT NULL,
+    model_id INTEGER NOT NULL,
+    title VARCHAR(4096) NOT NULL,
+    original_path VARCHAR(4096) NOT NULL,
+    trash_path VARCHAR(4096) NOT NULL,
+    metadata TEXT,
+    date_deleted TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_trash_date_deleted ON trash(date_deleted);
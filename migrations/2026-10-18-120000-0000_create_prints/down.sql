@@ -0,0 +1,4 @@
+-- Drop print log
+DROP INDEX IF EXISTS idx_prints_file_id;
+DROP INDEX IF EXISTS idx_prints_model_id;
+DROP TABLE IF EXISTS prints;
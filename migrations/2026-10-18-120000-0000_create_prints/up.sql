@@ -0,0 +1,20 @@
+-- Print log, file_path keeps the link when a rescan recreates the file entry
+CREATE TABLE prints (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    model_id INTEGER NOT NULL,
+    file_id INTEGER,
+    file_path VARCHAR(4096) NOT NULL,
+    printer VARCHAR(256),
+    material VARCHAR(256),
+    settings TEXT,
+    success BOOLEAN NOT NULL DEFAULT 1,
+    notes TEXT NOT NULL DEFAULT '',
+    photo VARCHAR(4096),
+    printed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    date_added TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (model_id) REFERENCES models3d(id) ON DELETE CASCADE,
+    FOREIGN KEY (file_id) REFERENCES files3d(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_prints_model_id ON prints(model_id);
+CREATE INDEX idx_prints_file_id ON prints(file_id);
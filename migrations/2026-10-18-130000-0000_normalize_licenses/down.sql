@@ -0,0 +1,5 @@
+-- Drop the normalized license columns
+ALTER TABLE models3d DROP COLUMN license_attribution;
+ALTER TABLE models3d DROP COLUMN license_share_alike;
+ALTER TABLE models3d DROP COLUMN license_non_commercial;
+ALTER TABLE models3d DROP COLUMN license_spdx;
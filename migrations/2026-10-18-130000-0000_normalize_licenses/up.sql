@@ -0,0 +1,5 @@
+-- SPDX identifier and usage flags derived from the license text, which is kept as it is
+ALTER TABLE models3d ADD COLUMN license_spdx TEXT;
+ALTER TABLE models3d ADD COLUMN license_non_commercial BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE models3d ADD COLUMN license_share_alike BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE models3d ADD COLUMN license_attribution BOOLEAN NOT NULL DEFAULT 0;
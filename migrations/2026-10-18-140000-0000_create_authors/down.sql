@@ -0,0 +1,6 @@
+-- Drop authors
+DROP INDEX IF EXISTS idx_models3d_author_id;
+ALTER TABLE models3d DROP COLUMN author_id;
+DROP INDEX IF EXISTS idx_author_aliases_author_id;
+DROP TABLE IF EXISTS author_aliases;
+DROP TABLE IF EXISTS authors;
@@ -0,0 +1,21 @@
+-- Authors collected from the ModelPacks, spelling variants are merged through their aliases
+CREATE TABLE authors (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL,
+    profile_url TEXT,
+    date_added TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE author_aliases (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    author_id INTEGER NOT NULL,
+    alias TEXT NOT NULL,
+    -- lowercase without spaces and punctuation, "John Doe" and "john_doe" share it
+    alias_key TEXT NOT NULL UNIQUE,
+    FOREIGN KEY (author_id) REFERENCES authors(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_author_aliases_author_id ON author_aliases(author_id);
+
+ALTER TABLE models3d ADD COLUMN author_id INTEGER REFERENCES authors(id);
+CREATE INDEX idx_models3d_author_id ON models3d(author_id);
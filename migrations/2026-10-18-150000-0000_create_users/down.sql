@@ -0,0 +1,4 @@
+-- Drop users and sessions
+DROP INDEX IF EXISTS idx_sessions_user_id;
+DROP TABLE IF EXISTS sessions;
+DROP TABLE IF EXISTS users;
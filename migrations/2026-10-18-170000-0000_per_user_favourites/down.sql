@@ -0,0 +1,9 @@
+-- Back to a global favourite flag, a model stays a favourite if anyone liked it
+ALTER TABLE collections DROP COLUMN visibility;
+ALTER TABLE collections DROP COLUMN owner_id;
+
+ALTER TABLE models3d ADD COLUMN favourite BOOLEAN NOT NULL DEFAULT 0;
+UPDATE models3d SET favourite = 1 WHERE id IN (SELECT model_id FROM favourites);
+
+DROP INDEX IF EXISTS idx_favourites_model_id;
+DROP TABLE IF EXISTS favourites;
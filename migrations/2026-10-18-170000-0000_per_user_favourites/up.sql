@@ -0,0 +1,22 @@
+-- Favourites per user instead of one global flag, collections get an owner and a visibility
+CREATE TABLE favourites (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    user_id INTEGER NOT NULL,
+    model_id INTEGER NOT NULL,
+    date_added TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, model_id),
+    FOREIGN KEY (model_id) REFERENCES models3d(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_favourites_model_id ON favourites(model_id);
+
+-- existing favourites are kept for every account and for the local user (id 0) used while logins are disabled
+INSERT INTO favourites (user_id, model_id) SELECT 0, id FROM models3d WHERE favourite = 1;
+INSERT INTO favourites (user_id, model_id)
+    SELECT users.id, models3d.id FROM users, models3d WHERE models3d.favourite = 1;
+
+ALTER TABLE models3d DROP COLUMN favourite;
+
+-- collections without an owner were made before accounts existed and stay visible to everyone
+ALTER TABLE collections ADD COLUMN owner_id INTEGER;
+ALTER TABLE collections ADD COLUMN visibility TEXT NOT NULL DEFAULT 'shared';
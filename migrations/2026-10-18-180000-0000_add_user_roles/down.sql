@@ -0,0 +1,4 @@
+-- Back to the admin flag, other roles become regular users
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT 0;
+UPDATE users SET is_admin = 1 WHERE role = 'admin';
+ALTER TABLE users DROP COLUMN role;
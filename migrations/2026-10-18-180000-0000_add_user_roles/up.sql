@@ -0,0 +1,4 @@
+-- Roles replace the admin flag: viewer, contributor, editor or admin
+ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'contributor';
+UPDATE users SET role = 'admin' WHERE is_admin = 1;
+ALTER TABLE users DROP COLUMN is_admin;
@@ -0,0 +1,4 @@
+-- Drop share_links
+DROP INDEX IF EXISTS idx_share_links_collection_id;
+DROP INDEX IF EXISTS idx_share_links_model_id;
+DROP TABLE IF EXISTS share_links;
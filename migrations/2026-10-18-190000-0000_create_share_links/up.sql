@@ -0,0 +1,17 @@
+-- Links that give people without an account read-only access to one model or collection
+CREATE TABLE share_links (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    model_id INTEGER,
+    collection_id INTEGER,
+    token_prefix TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_by INTEGER NOT NULL,
+    date_added TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    expires_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (model_id) REFERENCES models3d(id) ON DELETE CASCADE,
+    FOREIGN KEY (collection_id) REFERENCES collections(id) ON DELETE CASCADE,
+    CHECK ((model_id IS NULL) <> (collection_id IS NULL))
+);
+
+CREATE INDEX idx_share_links_model_id ON share_links(model_id);
+CREATE INDEX idx_share_links_collection_id ON share_links(collection_id);
@@ -0,0 +1,2 @@
+-- Drop users.email
+ALTER TABLE users DROP COLUMN email;
@@ -0,0 +1,2 @@
+-- E-mail address, filled in by an authenticating reverse proxy
+ALTER TABLE users ADD COLUMN email TEXT;
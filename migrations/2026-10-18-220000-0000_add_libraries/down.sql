@@ -0,0 +1,34 @@
+-- Back to a single library, models and trash entries of the further libraries are forgotten
+DELETE FROM trash WHERE library IS NOT NULL;
+ALTER TABLE trash DROP COLUMN library;
+
+DELETE FROM models3d WHERE library IS NOT NULL;
+
+CREATE TABLE models3d_old (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    title VARCHAR(4096) NOT NULL,
+    name VARCHAR(4096) NOT NULL UNIQUE, -- unique slug
+    license VARCHAR(256),
+    author VARCHAR(256),
+    folder_path VARCHAR(4096) NOT NULL UNIQUE,
+    origin VARCHAR(2048),
+    date_added TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    images VARCHAR,
+    description VARCHAR DEFAULT '',
+    license_spdx TEXT,
+    license_non_commercial BOOLEAN NOT NULL DEFAULT 0,
+    license_share_alike BOOLEAN NOT NULL DEFAULT 0,
+    license_attribution BOOLEAN NOT NULL DEFAULT 0,
+    author_id INTEGER REFERENCES authors(id)
+);
+
+INSERT INTO models3d_old
+SELECT
+    id, title, name, license, author, folder_path, origin, date_added, images, description,
+    license_spdx, license_non_commercial, license_share_alike, license_attribution, author_id
+FROM models3d;
+
+DROP TABLE models3d;
+ALTER TABLE models3d_old RENAME TO models3d;
+
+CREATE INDEX idx_models3d_author_id ON models3d(author_id);
@@ -0,0 +1,38 @@
+-- Models and trash entries of further libraries keep the library name, NULL is the main library.
+-- Folder paths are only unique within a library, sqlite needs a new table to drop the constraint
+CREATE TABLE models3d_new (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    title VARCHAR(4096) NOT NULL,
+    name VARCHAR(4096) NOT NULL UNIQUE, -- unique slug
+    license VARCHAR(256),
+    author VARCHAR(256),
+    folder_path VARCHAR(4096) NOT NULL,
+    origin VARCHAR(2048),
+    date_added TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    images VARCHAR,
+    description VARCHAR DEFAULT '',
+    license_spdx TEXT,
+    license_non_commercial BOOLEAN NOT NULL DEFAULT 0,
+    license_share_alike BOOLEAN NOT NULL DEFAULT 0,
+    license_attribution BOOLEAN NOT NULL DEFAULT 0,
+    author_id INTEGER REFERENCES authors(id),
+    library TEXT
+);
+
+INSERT INTO models3d_new (
+    id, title, name, license, author, folder_path, origin, date_added, images, description,
+    license_spdx, license_non_commercial, license_share_alike, license_attribution, author_id
+)
+SELECT
+    id, title, name, license, author, folder_path, origin, date_added, images, description,
+    license_spdx, license_non_commercial, license_share_alike, license_attribution, author_id
+FROM models3d;
+
+DROP TABLE models3d;
+ALTER TABLE models3d_new RENAME TO models3d;
+
+CREATE INDEX idx_models3d_author_id ON models3d(author_id);
+CREATE INDEX idx_models3d_library ON models3d(library);
+CREATE UNIQUE INDEX idx_models3d_library_folder_path ON models3d(COALESCE(library, ''), folder_path);
+
+ALTER TABLE trash ADD COLUMN library TEXT;
@@ -0,0 +1,2 @@
+-- The prefixed slugs are kept, the previous ones still resolve through the slug history
+SELECT 1;
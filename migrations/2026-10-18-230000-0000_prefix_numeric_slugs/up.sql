@@ -0,0 +1,10 @@
+-- Numbers address models by id in /api/v2, so slugs which are only digits get a prefix
+-- and the old slug moves to the history where the first API version still finds it
+INSERT OR IGNORE INTO slug_history (model_id, slug)
+SELECT id, name FROM models3d
+WHERE name <> '' AND name NOT GLOB '*[^0-9]*'
+    AND NOT EXISTS (SELECT 1 FROM models3d AS other WHERE other.name = 'model-' || models3d.name);
+
+UPDATE models3d SET name = 'model-' || name
+WHERE name <> '' AND name NOT GLOB '*[^0-9]*'
+    AND NOT EXISTS (SELECT 1 FROM models3d AS other WHERE other.name = 'model-' || models3d.name);